@@ -0,0 +1,55 @@
+use std::convert::TryFrom;
+use std::time::Instant;
+
+use oxide_gb::cartridge::Cartridge;
+use oxide_gb::cpu::Cpu;
+use oxide_gb::mbc::MBC;
+
+const INSTRUCTIONS: u64 = 20_000_000;
+
+// A tight loop that leans on 8 and 16 bit register access and the flags
+#[rustfmt::skip]
+const PROGRAM: [u8; 16] = [
+    0x21, 0x00, 0xc0, // LD HL, 0xc000
+    0x04,             // INC B
+    0x80,             // ADD A, B
+    0x4f,             // LD C, A
+    0x23,             // INC HL
+    0x09,             // ADD HL, BC
+    0xc5,             // PUSH BC
+    0xd1,             // POP DE
+    0xa9,             // XOR C
+    0xcb, 0x11,       // RL C
+    0x1b,             // DEC DE
+    0x18, 0xf3,       // JR -13
+];
+
+fn build_mbc() -> MBC {
+    let mut data = vec![0x00; 0x8000];
+    data[0x100..0x100 + PROGRAM.len()].copy_from_slice(&PROGRAM);
+
+    MBC::try_from(Cartridge::try_from(data).unwrap()).unwrap()
+}
+
+fn main() {
+    let mut mbc = build_mbc();
+    let mut cpu = Cpu::default();
+
+    cpu.program_counter = 0x100;
+    cpu.stack_pointer = 0xfffe;
+
+    let start = Instant::now();
+
+    for _ in 0..INSTRUCTIONS {
+        cpu.step(&mut mbc).unwrap();
+    }
+
+    let elapsed = start.elapsed();
+
+    println!(
+        "{} instructions in {:.2?} ({:.1} million instructions per second)",
+        INSTRUCTIONS,
+        elapsed,
+        INSTRUCTIONS as f64 / elapsed.as_secs_f64() / 1_000_000.0
+    );
+}
@@ -1,7 +1,7 @@
 use crate::cartridge::Cartridge;
 use crate::cartridge_header::RAM_BANK_SIZE;
 
-pub const ROM_BANK_SIZE: usize = 16000;
+pub const ROM_BANK_SIZE: usize = 0x4000;
 
 pub struct BankedMemory {
     pub active_bank: usize,
@@ -29,22 +29,53 @@ impl BankedMemory {
         }
     }
 
+    /// Cartridge RAM of `size` banks. Without any there are no banks at
+    /// all, so reads see an undriven bus and writes go nowhere.
     pub fn of_size(size: Option<u8>) -> Self {
-        match size {
-            Some(ram_size) => BankedMemory::new(0, RAM_BANK_SIZE, ram_size.into()),
-            None => BankedMemory::new(0, 0x8000, 1),
-        }
+        BankedMemory::new(0, RAM_BANK_SIZE, size.unwrap_or(0).into())
     }
 
     pub fn value_at(&self, location: usize) -> u8 {
-        self.banks[self.active_bank][location]
+        self.value_in_bank(self.active_bank, location)
     }
 
+    /// Banks the memory doesn't actually have read as an undriven bus
     pub fn value_in_bank(&self, bank: usize, location: usize) -> u8 {
-        self.banks[bank][location]
+        self.banks
+            .get(bank)
+            .and_then(|bank| bank.get(location))
+            .copied()
+            .unwrap_or(0xff)
     }
 
     pub fn set_at(&mut self, location: usize, value: u8) {
-        self.banks[self.active_bank][location] = value;
+        self.set_in_bank(self.active_bank, location, value);
+    }
+
+    /// Every bank laid end to end, as it would be stored in a save file
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.banks.concat()
+    }
+
+    pub fn load_bytes(&mut self, data: &[u8]) {
+        let bank_size = self.banks.first().map_or(0, Vec::len);
+
+        if bank_size == 0 {
+            return;
+        }
+
+        for (bank, chunk) in self.banks.iter_mut().zip(data.chunks(bank_size)) {
+            bank[..chunk.len()].copy_from_slice(chunk);
+        }
+    }
+
+    pub fn set_in_bank(&mut self, bank: usize, location: usize, value: u8) {
+        if let Some(byte) = self
+            .banks
+            .get_mut(bank)
+            .and_then(|bank| bank.get_mut(location))
+        {
+            *byte = value;
+        }
     }
 }
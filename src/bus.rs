@@ -0,0 +1,98 @@
+use crate::interrupts::{Interrupt, INTERRUPT_FLAG_LOCATION};
+use crate::utils::u8s_to_u16;
+
+/// Everything the CPU can reach through its address bus. `MBC` is the full
+/// Game Boy memory map, but anything implementing this can stand in for it.
+pub trait Bus {
+    fn read(&self, location: usize) -> u8;
+    fn write(&mut self, location: usize, value: u8);
+
+    /// Advances everything attached to the bus by a number of T-cycles
+    fn tick(&mut self, cycles: u32);
+
+    fn get_next_u8(&self, from_location: usize) -> u8 {
+        self.read((from_location + 1) & 0xffff)
+    }
+
+    fn get_next_u16(&self, from_location: usize) -> u16 {
+        u8s_to_u16(
+            self.read((from_location + 2) & 0xffff),
+            self.read((from_location + 1) & 0xffff),
+        )
+    }
+
+    fn request_interrupt(&mut self, interrupt: Interrupt) {
+        let requested = self.read(INTERRUPT_FLAG_LOCATION);
+        self.write(INTERRUPT_FLAG_LOCATION, requested | interrupt.bits());
+    }
+
+    /// Called by STOP. Carries out a CGB speed switch if one was armed
+    /// through KEY1 and returns true, otherwise there is nothing to switch.
+    fn switch_speed(&mut self) -> bool {
+        false
+    }
+
+    /// Called as STOP enters and leaves low power mode, during which the LCD
+    /// is blanked
+    fn set_stopped(&mut self, _stopped: bool) {}
+}
+
+/// A plain 64 KiB address space with no banking, IO or timing behaviour.
+/// Handy for tests and for tools that only care about the CPU.
+pub struct FlatMemory {
+    pub memory: Vec<u8>,
+    pub cycles: u64,
+}
+
+impl Default for FlatMemory {
+    fn default() -> Self {
+        FlatMemory {
+            memory: vec![0x00; 0x10000],
+            cycles: 0,
+        }
+    }
+}
+
+impl FlatMemory {
+    pub fn with_program(program: &[u8]) -> Self {
+        let mut result = FlatMemory::default();
+        result.memory[..program.len()].copy_from_slice(program);
+        result
+    }
+}
+
+impl Bus for FlatMemory {
+    fn read(&self, location: usize) -> u8 {
+        self.memory[location & 0xffff]
+    }
+
+    fn write(&mut self, location: usize, value: u8) {
+        self.memory[location & 0xffff] = value;
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        self.cycles += u64::from(cycles);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bus::{Bus, FlatMemory};
+    use crate::interrupts::Interrupt;
+
+    #[test]
+    fn get_next_u16_is_little_endian() {
+        let memory = FlatMemory::with_program(&[0xc3, 0x50, 0x01]);
+        assert_eq!(memory.get_next_u16(0), 0x0150);
+    }
+
+    #[test]
+    fn request_interrupt_keeps_existing_requests() {
+        let mut memory = FlatMemory::default();
+
+        memory.request_interrupt(Interrupt::VBLANK);
+        memory.request_interrupt(Interrupt::SERIAL);
+
+        assert_eq!(memory.read(0xff0f), 0x09);
+    }
+}
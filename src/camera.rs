@@ -0,0 +1,118 @@
+use std::io::Read;
+
+use png::{ColorType, Decoder, Transformations};
+
+use crate::error::EmulatorError;
+
+// The part of the Pocket Camera's sensor that makes it into a photo
+pub const SENSOR_WIDTH: usize = 128;
+pub const SENSOR_HEIGHT: usize = 112;
+
+/// A greyscale picture for a camera cartridge's sensor to look at, from 0
+/// for black to 255 for white. It's stretched to cover the whole sensor.
+pub struct CameraImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl CameraImage {
+    /// Takes rows of pixels, top to bottom
+    pub fn new(width: usize, height: usize, pixels: Vec<u8>) -> Result<Self, EmulatorError> {
+        if width == 0 || height == 0 || pixels.len() != width * height {
+            return Err(EmulatorError::InvalidImage(format!(
+                "{} pixels can't make a {}x{} image",
+                pixels.len(),
+                width,
+                height
+            )));
+        }
+
+        Ok(CameraImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Decodes a PNG of any colour type, keeping only how bright it is
+    pub fn from_png<R: Read>(reader: R) -> Result<Self, EmulatorError> {
+        let invalid = |error: png::DecodingError| EmulatorError::InvalidImage(error.to_string());
+
+        let mut decoder = Decoder::new(reader);
+        decoder.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+
+        let mut reader = decoder.read_info().map_err(invalid)?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut buffer).map_err(invalid)?;
+        let samples = match frame.color_type {
+            ColorType::Grayscale => 1,
+            ColorType::GrayscaleAlpha => 2,
+            ColorType::Rgb | ColorType::Indexed => 3,
+            ColorType::Rgba => 4,
+        };
+
+        let pixels = buffer[..frame.buffer_size()]
+            .chunks(frame.line_size)
+            .flat_map(|line| line[..frame.width as usize * samples].chunks(samples))
+            .map(|pixel| match pixel {
+                [red, green, blue, ..] if samples >= 3 => {
+                    ((u32::from(*red) * 299 + u32::from(*green) * 587 + u32::from(*blue) * 114)
+                        / 1000) as u8
+                }
+                _ => pixel[0],
+            })
+            .collect();
+
+        CameraImage::new(frame.width as usize, frame.height as usize, pixels)
+    }
+
+    /// How bright the picture is at a pixel of the sensor
+    pub fn sensor_pixel(&self, x: usize, y: usize) -> u8 {
+        let x = x * self.width / SENSOR_WIDTH;
+        let y = y * self.height / SENSOR_HEIGHT;
+
+        self.pixels[y * self.width + x]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use png::{ColorType, Encoder};
+
+    use crate::camera::{CameraImage, SENSOR_HEIGHT, SENSOR_WIDTH};
+    use crate::error::EmulatorError;
+
+    #[test]
+    fn stretches_to_fill_the_sensor() {
+        let image = CameraImage::new(2, 2, vec![0, 85, 170, 255]).unwrap();
+
+        assert_eq!(image.sensor_pixel(0, 0), 0);
+        assert_eq!(image.sensor_pixel(SENSOR_WIDTH - 1, 0), 85);
+        assert_eq!(image.sensor_pixel(0, SENSOR_HEIGHT - 1), 170);
+        assert_eq!(image.sensor_pixel(SENSOR_WIDTH / 2, SENSOR_HEIGHT / 2), 255);
+    }
+
+    #[test]
+    fn rejects_the_wrong_number_of_pixels() {
+        let result = CameraImage::new(2, 2, vec![0; 3]);
+
+        assert!(matches!(result, Err(EmulatorError::InvalidImage(_))));
+    }
+
+    #[test]
+    fn reads_colour_png_as_greyscale() {
+        let mut png = Vec::new();
+        let mut encoder = Encoder::new(&mut png, 2, 1);
+        encoder.set_color(ColorType::Rgb);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&[255, 255, 255, 255, 0, 0])
+            .unwrap();
+
+        let image = CameraImage::from_png(png.as_slice()).unwrap();
+
+        assert_eq!(image.pixels, vec![255, 76]);
+    }
+}
@@ -1,26 +1,181 @@
+use std::convert::TryFrom;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
 
-use crate::cartridge_header::CartridgeHeader;
+use crate::cartridge_header::{
+    global_checksum, header_checksum, CartridgeHeader, NO_RAM_CODE, RAM_SIZE_LOCATION,
+    ROM_SIZE_LOCATION,
+};
+use crate::error::CartridgeError;
 
 pub struct Cartridge {
     pub header: CartridgeHeader,
     pub data: Vec<u8>,
+    /// Anything odd about the image that didn't stop it loading
+    pub warnings: Vec<CartridgeError>,
 }
 
-impl From<File> for Cartridge {
-    fn from(file: File) -> Self {
+/// Checks the image against its own header
+fn validate(header: &CartridgeHeader, data: &[u8]) -> Vec<CartridgeError> {
+    let mut problems = Vec::new();
+
+    match header.rom_size_bytes.map(|size| size as usize) {
+        Some(declared) if data.len() < declared => problems.push(CartridgeError::Truncated {
+            declared,
+            actual: data.len(),
+        }),
+        Some(declared) if data.len() > declared => problems.push(CartridgeError::Oversized {
+            declared,
+            actual: data.len(),
+        }),
+        Some(_) => {}
+        None => problems.push(CartridgeError::UnknownRomSize(data[ROM_SIZE_LOCATION])),
+    }
+
+    if header.ram_size.is_none() && data[RAM_SIZE_LOCATION] != NO_RAM_CODE {
+        problems.push(CartridgeError::UnknownRamSize(data[RAM_SIZE_LOCATION]));
+    }
+
+    if !header.has_valid_logo() {
+        problems.push(CartridgeError::BadLogo);
+    }
+
+    let expected = header_checksum(data);
+    let actual = header.header_checksum;
+    if actual != expected {
+        problems.push(CartridgeError::HeaderChecksum { expected, actual });
+    }
+
+    let expected = global_checksum(data);
+    let actual = header.global_checksum;
+    if actual != expected {
+        problems.push(CartridgeError::GlobalChecksum { expected, actual });
+    }
+
+    problems
+}
+
+impl TryFrom<Vec<u8>> for Cartridge {
+    type Error = CartridgeError;
+
+    /// Fails on the first problem that stops the ROM from running, and keeps
+    /// the rest as warnings
+    fn try_from(data: Vec<u8>) -> Result<Self, Self::Error> {
+        let header = CartridgeHeader::from_binary(&data)?;
+        let (warnings, mut failures): (Vec<_>, Vec<_>) = validate(&header, &data)
+            .into_iter()
+            .partition(CartridgeError::is_warning);
+
+        if !failures.is_empty() {
+            return Err(failures.remove(0));
+        }
+
+        Ok(Cartridge {
+            header,
+            data,
+            warnings,
+        })
+    }
+}
+
+impl TryFrom<File> for Cartridge {
+    type Error = CartridgeError;
+
+    fn try_from(file: File) -> Result<Self, Self::Error> {
         let mut reader = BufReader::new(file);
         let mut cartridge_buffer: Vec<u8> = Vec::new();
 
-        reader.read_to_end(&mut cartridge_buffer).unwrap();
+        reader.read_to_end(&mut cartridge_buffer)?;
 
-        let header = CartridgeHeader::from_binary(&cartridge_buffer);
+        Cartridge::try_from(cartridge_buffer)
+    }
+}
 
-        Cartridge {
-            header,
-            data: cartridge_buffer,
-        }
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use crate::cartridge::Cartridge;
+    use crate::cartridge_header::{global_checksum, header_checksum, NINTENDO_LOGO};
+    use crate::error::CartridgeError;
+
+    /// A 64 KiB ROM with a header that checks out
+    fn valid_rom() -> Vec<u8> {
+        let mut data = vec![0x00; 0x10000];
+        data[0x104..0x134].copy_from_slice(&NINTENDO_LOGO);
+        data[0x148] = 0x01;
+        data[0x14d] = header_checksum(&data);
+
+        let checksum: u16 = data.iter().map(|byte| u16::from(*byte)).sum();
+        data[0x14e..0x150].copy_from_slice(&checksum.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn rejects_rom_without_header() {
+        let result = Cartridge::try_from(vec![0x00; 0x100]);
+
+        assert!(matches!(result, Err(CartridgeError::TooSmall(0x100))));
+    }
+
+    #[test]
+    fn loads_valid_rom_without_warnings() {
+        let cartridge = Cartridge::try_from(valid_rom()).unwrap();
+
+        assert!(cartridge.warnings.is_empty());
+        assert_eq!(cartridge.header.rom_size_bytes, Some(0x10000));
+    }
+
+    #[test]
+    fn rejects_truncated_rom() {
+        let mut data = valid_rom();
+        data.truncate(0x8000);
+
+        let result = Cartridge::try_from(data);
+
+        assert!(matches!(
+            result,
+            Err(CartridgeError::Truncated {
+                declared: 0x10000,
+                actual: 0x8000
+            })
+        ));
+    }
+
+    #[test]
+    fn header_problems_are_only_warnings() {
+        let mut data = valid_rom();
+        data[0x104] = 0x00;
+        data[0x149] = 0x07;
+
+        let cartridge = Cartridge::try_from(data).unwrap();
+
+        assert!(matches!(
+            cartridge.warnings.as_slice(),
+            [
+                CartridgeError::UnknownRamSize(0x07),
+                CartridgeError::BadLogo,
+                CartridgeError::HeaderChecksum { .. },
+                CartridgeError::GlobalChecksum { .. },
+            ]
+        ));
+    }
+
+    #[test]
+    fn warns_about_the_unused_2_kib_ram_size() {
+        let mut data = valid_rom();
+        data[0x149] = 0x01;
+        data[0x14d] = header_checksum(&data);
+        let checksum = global_checksum(&data);
+        data[0x14e..0x150].copy_from_slice(&checksum.to_be_bytes());
+
+        let cartridge = Cartridge::try_from(data).unwrap();
+
+        assert_eq!(cartridge.header.ram_size, None);
+        assert!(matches!(
+            cartridge.warnings.as_slice(),
+            [CartridgeError::UnknownRamSize(0x01)]
+        ));
     }
 }
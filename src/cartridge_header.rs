@@ -1,35 +1,63 @@
+use crate::banked_memory::ROM_BANK_SIZE;
 use crate::cartridge_type::CartridgeType;
+use crate::error::CartridgeError;
+use crate::licensee::Licensee;
 use crate::utils::buffer_slice_to_string;
 
-// const NINTENDO_LOGO_LOCATION: usize = 0x104;
-// const NINTENDO_LOGO_END: usize = 0x133;
+pub const HEADER_START: usize = 0x100;
+const ENTRY_POINT_END: usize = 0x104;
+pub const NINTENDO_LOGO_LOCATION: usize = 0x104;
+pub const NINTENDO_LOGO_END: usize = 0x133;
 
 const GAME_TITLE_LOCATION: usize = 0x134;
-const GAME_TITLE_END: usize = 0x143;
+// Color cartridges give the last 5 bytes of the title over to the
+// manufacturer code and CGB flag
+const COLOR_GAME_TITLE_END: usize = 0x13F;
+const GAME_TITLE_END: usize = 0x144;
 const MANUFACTURER_CODE_LOCATION: usize = 0x13F;
-const MANUFACTURER_CODE_END: usize = 0x142;
+const MANUFACTURER_CODE_END: usize = 0x143;
 const CGB_FLAG_LOCATION: usize = 0x143;
+const NEW_LICENSEE_CODE_LOCATION: usize = 0x144;
 const SGB_FLAG_LOCATION: usize = 0x146;
-const ROM_SIZE_LOCATION: usize = 0x148;
-const RAM_SIZE_LOCATION: usize = 0x149;
+pub const ROM_SIZE_LOCATION: usize = 0x148;
+pub const RAM_SIZE_LOCATION: usize = 0x149;
+pub const NO_RAM_CODE: u8 = 0x00;
+const DESTINATION_CODE_LOCATION: usize = 0x14A;
+const OLD_LICENSEE_CODE_LOCATION: usize = 0x14B;
+const VERSION_LOCATION: usize = 0x14C;
+const HEADER_CHECKSUM_START: usize = 0x134;
+pub const HEADER_CHECKSUM_LOCATION: usize = 0x14d;
+pub const GLOBAL_CHECKSUM_LOCATION: usize = 0x14e;
 
-pub const RAM_BANK_SIZE: usize = 0x8000;
+// One past the last byte of the header
+pub const HEADER_END: usize = 0x150;
 
-#[derive(Debug)]
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+/// The boot ROM refuses to start a cartridge unless this is in its header
+#[rustfmt::skip]
+pub const NINTENDO_LOGO: [u8; 48] = [
+    0xce, 0xed, 0x66, 0x66, 0xcc, 0x0d, 0x00, 0x0b, 0x03, 0x73, 0x00, 0x83,
+    0x00, 0x0c, 0x00, 0x0d, 0x00, 0x08, 0x11, 0x1f, 0x88, 0x89, 0x00, 0x0e,
+    0xdc, 0xcc, 0x6e, 0xe6, 0xdd, 0xdd, 0xd9, 0x99, 0xbb, 0xbb, 0x67, 0x63,
+    0x6e, 0x0e, 0xec, 0xcc, 0xdd, 0xdc, 0x99, 0x9f, 0xbb, 0xb9, 0x33, 0x3e,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ColorGameboySupport {
     NoSupport,
     Both,
     OnlyColor,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SuperGameboySupport {
     NoSupport,
     Support,
 }
 
 // Seems like it might not be often used
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DestinationCode {
     Japanese,
     NonJapanese,
@@ -43,6 +71,39 @@ fn read_cgb_flag(buffer: &[u8]) -> ColorGameboySupport {
     }
 }
 
+/// The title stops at the first NUL, and is shorter on color cartridges
+fn read_title(buffer: &[u8], color_gameboy_support: ColorGameboySupport) -> String {
+    let end = match color_gameboy_support {
+        ColorGameboySupport::NoSupport => GAME_TITLE_END,
+        _ => COLOR_GAME_TITLE_END,
+    };
+    let title = &buffer[GAME_TITLE_LOCATION..end];
+    let length = title.iter().position(|b| *b == 0x00).unwrap_or(title.len());
+
+    buffer_slice_to_string(&title[..length])
+}
+
+// Older cartridges used these bytes for the title, so only trust them on
+// color ones
+fn read_manufacturer(buffer: &[u8], color_gameboy_support: ColorGameboySupport) -> Option<String> {
+    match color_gameboy_support {
+        ColorGameboySupport::NoSupport => None,
+        _ => Some(buffer_slice_to_string(
+            &buffer[MANUFACTURER_CODE_LOCATION..MANUFACTURER_CODE_END],
+        )),
+    }
+}
+
+fn read_licensee(buffer: &[u8]) -> Licensee {
+    Licensee::from_codes(
+        buffer[OLD_LICENSEE_CODE_LOCATION],
+        [
+            buffer[NEW_LICENSEE_CODE_LOCATION],
+            buffer[NEW_LICENSEE_CODE_LOCATION + 1],
+        ],
+    )
+}
+
 fn read_sgb_flag(buffer: &[u8]) -> SuperGameboySupport {
     match buffer[SGB_FLAG_LOCATION] {
         0x03 => SuperGameboySupport::Support,
@@ -50,24 +111,22 @@ fn read_sgb_flag(buffer: &[u8]) -> SuperGameboySupport {
     }
 }
 
-fn read_rom_size_bytes(buffer: &[u8]) -> u32 {
-    match buffer[ROM_SIZE_LOCATION] {
-        0x00 => 32000,
-        0x01 => 64000,
-        0x02 => 128000,
-        0x03 => 256000,
-        0x04 => 512000,
-        0x05 => 1000000,
-        0x06 => 2000000,
-        0x07 => 4000000,
-        0x08 => 8000000,
-        0x52 => 1100000,
-        0x53 => 1200000,
-        0x54 => 1500000,
-        _ => u32::MAX,
-    }
+/// 32 KiB doubled for each step, plus a few odd sizes that may never have
+/// been used
+fn read_rom_size_bytes(buffer: &[u8]) -> Option<u32> {
+    let banks = match buffer[ROM_SIZE_LOCATION] {
+        code @ 0x00..=0x08 => 2 << code,
+        0x52 => 72,
+        0x53 => 80,
+        0x54 => 96,
+        _ => return None,
+    };
+
+    Some(banks * ROM_BANK_SIZE as u32)
 }
 
+/// Banks of RAM. Code 0x01 was reserved for 2 KiB, but no cartridge uses
+/// it, so it's treated as unknown like anything else past 0x05.
 fn read_ram_size(buffer: &[u8]) -> Option<u8> {
     match buffer[RAM_SIZE_LOCATION] {
         0x02 => Some(1),
@@ -82,36 +141,147 @@ fn read_ram_size(buffer: &[u8]) -> Option<u8> {
 // Technically it's 0x00 and 0x01, but I assume anything else aside from
 // 0x00 won't be "Japanese"
 fn read_destination_code(buffer: &[u8]) -> DestinationCode {
-    match buffer[0x14A] {
+    match buffer[DESTINATION_CODE_LOCATION] {
         0x00 => DestinationCode::Japanese,
         _ => DestinationCode::NonJapanese,
     }
 }
 
 pub struct CartridgeHeader {
+    pub entry_point: [u8; 4],
+    pub logo: [u8; 48],
     pub title: String,
-    pub manufacturer: String,
+    pub manufacturer: Option<String>,
     pub cartridge_type: CartridgeType,
     pub color_gameboy_support: ColorGameboySupport,
     pub super_gameboy_support: SuperGameboySupport,
-    pub rom_size_bytes: u32,
+    pub licensee: Licensee,
+    pub rom_size_bytes: Option<u32>,
     pub ram_size: Option<u8>,
     pub destination_code: DestinationCode,
+    /// The mask ROM version, usually 0
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+    /// Everything from 0x100 to 0x14f as it was in the ROM
+    pub raw: [u8; HEADER_END - HEADER_START],
+}
+
+/// What the boot ROM expects at 0x14d, worked out from the rest of the
+/// header
+pub fn header_checksum(data: &[u8]) -> u8 {
+    data[HEADER_CHECKSUM_START..HEADER_CHECKSUM_LOCATION]
+        .iter()
+        .fold(0u8, |checksum, byte| {
+            checksum.wrapping_sub(*byte).wrapping_sub(1)
+        })
+}
+
+/// The sum of every byte in the ROM but the checksum itself, which nothing
+/// actually checks
+pub fn global_checksum(data: &[u8]) -> u16 {
+    data.iter()
+        .enumerate()
+        .filter(|(location, _)| {
+            !(GLOBAL_CHECKSUM_LOCATION..GLOBAL_CHECKSUM_LOCATION + 2).contains(location)
+        })
+        .fold(0u16, |checksum, (_, byte)| {
+            checksum.wrapping_add((*byte).into())
+        })
 }
 
 impl CartridgeHeader {
-    pub fn from_binary(b: &[u8]) -> CartridgeHeader {
-        CartridgeHeader {
-            title: buffer_slice_to_string(&b[GAME_TITLE_LOCATION..GAME_TITLE_END]),
-            manufacturer: buffer_slice_to_string(
-                &b[MANUFACTURER_CODE_LOCATION..MANUFACTURER_CODE_END],
-            ),
+    pub fn from_binary(b: &[u8]) -> Result<CartridgeHeader, CartridgeError> {
+        if b.len() < HEADER_END {
+            return Err(CartridgeError::TooSmall(b.len()));
+        }
+
+        let mut entry_point = [0; 4];
+        entry_point.copy_from_slice(&b[HEADER_START..ENTRY_POINT_END]);
+        let mut logo = [0; 48];
+        logo.copy_from_slice(&b[NINTENDO_LOGO_LOCATION..=NINTENDO_LOGO_END]);
+        let mut raw = [0; HEADER_END - HEADER_START];
+        raw.copy_from_slice(&b[HEADER_START..HEADER_END]);
+
+        let color_gameboy_support = read_cgb_flag(b);
+
+        Ok(CartridgeHeader {
+            entry_point,
+            logo,
+            title: read_title(b, color_gameboy_support),
+            manufacturer: read_manufacturer(b, color_gameboy_support),
             cartridge_type: CartridgeType::from_cartridge(b),
-            color_gameboy_support: read_cgb_flag(b),
+            color_gameboy_support,
             super_gameboy_support: read_sgb_flag(b),
+            licensee: read_licensee(b),
             rom_size_bytes: read_rom_size_bytes(b),
             ram_size: read_ram_size(b),
             destination_code: read_destination_code(b),
-        }
+            version: b[VERSION_LOCATION],
+            header_checksum: b[HEADER_CHECKSUM_LOCATION],
+            global_checksum: u16::from_be_bytes([
+                b[GLOBAL_CHECKSUM_LOCATION],
+                b[GLOBAL_CHECKSUM_LOCATION + 1],
+            ]),
+            raw,
+        })
+    }
+
+    pub fn has_valid_logo(&self) -> bool {
+        self.logo == NINTENDO_LOGO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cartridge_header::{
+        CartridgeHeader, ColorGameboySupport, HEADER_END, NINTENDO_LOGO,
+    };
+    use crate::licensee::Licensee;
+
+    fn header_bytes(title: &[u8]) -> Vec<u8> {
+        let mut data = vec![0x00; HEADER_END];
+        data[0x104..0x134].copy_from_slice(&NINTENDO_LOGO);
+        data[0x134..0x134 + title.len()].copy_from_slice(title);
+        data
+    }
+
+    #[test]
+    fn reads_full_title_on_older_cartridges() {
+        let header = CartridgeHeader::from_binary(&header_bytes(b"SIXTEEN CHAR NAM")).unwrap();
+
+        assert_eq!(header.title, "SIXTEEN CHAR NAM");
+        assert_eq!(header.manufacturer, None);
+        assert!(header.has_valid_logo());
+    }
+
+    #[test]
+    fn splits_title_and_manufacturer_on_color_cartridges() {
+        let mut data = header_bytes(b"POKEMON\0\0\0\0AAXE");
+        data[0x143] = 0x80;
+
+        let header = CartridgeHeader::from_binary(&data).unwrap();
+
+        assert_eq!(header.color_gameboy_support, ColorGameboySupport::Both);
+        assert_eq!(header.title, "POKEMON");
+        assert_eq!(header.manufacturer.as_deref(), Some("AAXE"));
+    }
+
+    #[test]
+    fn reads_licensee_version_and_checksums() {
+        let mut data = header_bytes(b"TEST");
+        data[0x144..0x146].copy_from_slice(b"01");
+        data[0x14b] = 0x33;
+        data[0x14c] = 0x02;
+        data[0x14d] = 0xab;
+        data[0x14e..0x150].copy_from_slice(&[0x12, 0x34]);
+
+        let header = CartridgeHeader::from_binary(&data).unwrap();
+
+        assert_eq!(header.licensee, Licensee::New(*b"01"));
+        assert_eq!(header.version, 0x02);
+        assert_eq!(header.header_checksum, 0xab);
+        assert_eq!(header.global_checksum, 0x1234);
+        assert_eq!(header.raw[0x4c], 0x02);
     }
 }
@@ -1,6 +1,6 @@
 const CARTRIDGE_TYPE_LOCATION: usize = 0x147;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CartridgeType {
     RomOnly,
     MBC1,
@@ -67,4 +67,30 @@ impl CartridgeType {
             _ => CartridgeType::Unknown,
         }
     }
+
+    /// Whether the cartridge keeps its RAM, or anything else, powered by a
+    /// battery, so it should be saved between sessions. That's more than
+    /// the `RamBattery` types: MBC2 RAM and the MBC3 clock are battery backed
+    /// too.
+    pub fn has_battery(&self) -> bool {
+        matches!(
+            self,
+            CartridgeType::MBC1RamBattery
+                | CartridgeType::MBC2Battery
+                | CartridgeType::RomRamBattery
+                | CartridgeType::MMM01RamBattery
+                | CartridgeType::MBC3TimerBattery
+                | CartridgeType::MBC3TimerRamBattery
+                | CartridgeType::MBC3RamBattery
+                | CartridgeType::MBC5RamBattery
+                | CartridgeType::MBC5RumbleRamBattery
+                | CartridgeType::MBC7SensorRumbleRamBattery
+                | CartridgeType::HuC1RamBattery
+                // The header doesn't say so for these, but they all have one
+                | CartridgeType::MBC6
+                | CartridgeType::PocketCamera
+                | CartridgeType::BandaiTama5
+                | CartridgeType::HuC3
+        )
+    }
 }
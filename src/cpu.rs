@@ -1,9 +1,16 @@
+use crate::bus::Bus;
 use crate::cpu_registers::{CombinedRegister, GeneralRegister, Registers};
+use crate::error::EmulatorError;
 use crate::flag_register::FlagRegisterValue;
-use crate::mbc::MBC;
+use crate::instruction::{Instruction, Operation};
+use crate::interrupts::{
+    Interrupt, INTERRUPT_DISPATCH_CYCLES, INTERRUPT_ENABLE_LOCATION, INTERRUPT_FLAG_LOCATION,
+};
+use crate::joypad::JOYPAD_LOCATION;
 use crate::ops::Ops;
 use crate::prefix_ops::PrefixOps;
-use crate::utils::{u16_to_u8s, u8s_to_u16, BitWise, Carryable};
+use crate::timer::DIVIDER_LOCATION;
+use crate::utils::{u16_to_u8s, u8s_to_u16, BitWise, Carryable, CarryableWith};
 
 pub const CLOCK_MHZ: u32 = 4194304;
 
@@ -13,50 +20,27 @@ pub struct Cpu {
     pub stack_pointer: u16,
     pub registers: Registers,
     pub interrupts_enabled: bool,
+    pub halted: bool,
+    // Low power mode entered by STOP, left when a button is pressed
+    pub stopped: bool,
+    // Set by an illegal opcode, after which nothing but a reset helps
+    pub locked: bool,
+    // EI only sets IME once the instruction after it has finished
+    enable_interrupts_next: bool,
+    // HALT with IME off and an interrupt already pending fails to advance
+    // PC past the following opcode byte, so it gets read twice
+    halt_bug: bool,
     pub current_op: u8,
+    // Prints every instruction along with the register state before it runs
+    pub trace: bool,
     count: u64,
+    // Set by any instruction that moves the program counter itself, which
+    // also marks a conditional branch as taken for timing purposes
+    branched: bool,
+    // T-cycles the bus has been ticked by during the current step
+    cycles: u32,
 }
 
-#[rustfmt::skip]
-const OPCODE_CYCLES: [usize; 256] = [
-    1, 3, 2, 2, 1, 1, 2, 1, 5, 2, 2, 2, 1, 1, 2, 1,
-    0, 3, 2, 2, 1, 1, 2, 1, 3, 2, 2, 2, 1, 1, 2, 1,
-    2, 3, 2, 2, 1, 1, 2, 1, 2, 2, 2, 2, 1, 1, 2, 1,
-    2, 3, 2, 2, 3, 3, 3, 1, 2, 2, 2, 2, 1, 1, 2, 1,
-    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
-    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
-    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
-    2, 2, 2, 2, 2, 2, 0, 2, 1, 1, 1, 1, 1, 1, 2, 1,
-    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
-    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
-    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
-    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
-    2, 3, 3, 4, 3, 4, 2, 4, 2, 4, 3, 0, 3, 6, 2, 4,
-    2, 3, 3, 0, 3, 4, 2, 4, 2, 4, 3, 0, 3, 0, 2, 4,
-    3, 3, 2, 0, 0, 4, 2, 4, 4, 1, 4, 0, 0, 0, 2, 4,
-    3, 3, 2, 1, 0, 4, 2, 4, 3, 2, 4, 1, 0, 0, 2, 4
-];
-
-#[rustfmt::skip]
-const PREFIX_OPCODE_CYCLES: [usize; 256] = [
-    2, 2, 2, 2, 2, 2, 4, 2, 2, 2, 2, 2, 2, 2, 4, 2,
-    2, 2, 2, 2, 2, 2, 4, 2, 2, 2, 2, 2, 2, 2, 4, 2,
-    2, 2, 2, 2, 2, 2, 4, 2, 2, 2, 2, 2, 2, 2, 4, 2,
-    2, 2, 2, 2, 2, 2, 4, 2, 2, 2, 2, 2, 2, 2, 4, 2,
-    2, 2, 2, 2, 2, 2, 3, 2, 2, 2, 2, 2, 2, 2, 3, 2,
-    2, 2, 2, 2, 2, 2, 3, 2, 2, 2, 2, 2, 2, 2, 3, 2,
-    2, 2, 2, 2, 2, 2, 3, 2, 2, 2, 2, 2, 2, 2, 3, 2,
-    2, 2, 2, 2, 2, 2, 3, 2, 2, 2, 2, 2, 2, 2, 3, 2,
-    2, 2, 2, 2, 2, 2, 4, 2, 2, 2, 2, 2, 2, 2, 4, 2,
-    2, 2, 2, 2, 2, 2, 4, 2, 2, 2, 2, 2, 2, 2, 4, 2,
-    2, 2, 2, 2, 2, 2, 4, 2, 2, 2, 2, 2, 2, 2, 4, 2,
-    2, 2, 2, 2, 2, 2, 4, 2, 2, 2, 2, 2, 2, 2, 4, 2,
-    2, 2, 2, 2, 2, 2, 4, 2, 2, 2, 2, 2, 2, 2, 4, 2,
-    2, 2, 2, 2, 2, 2, 4, 2, 2, 2, 2, 2, 2, 2, 4, 2,
-    2, 2, 2, 2, 2, 2, 4, 2, 2, 2, 2, 2, 2, 2, 4, 2,
-    2, 2, 2, 2, 2, 2, 4, 2, 2, 2, 2, 2, 2, 2, 4, 2
-];
-
 impl Ops for Cpu {
     /// An explicit "nothing" instruction to the CPU
     fn nop(&mut self) {}
@@ -71,154 +55,234 @@ impl Ops for Cpu {
             .unset_flag(FlagRegisterValue::HALF_CARRY);
     }
 
-    fn stop(&mut self) {
-        panic!("Stopped");
+    /// Adjusts A back into binary-coded decimal after an addition or
+    /// subtraction, using the N, H and C flags left behind by that operation
+    fn daa(&mut self) {
+        let a_val = self.registers.get(GeneralRegister::A);
+        let negative = self.registers.is_flag_set(FlagRegisterValue::NEGATIVE);
+        let half_carry = self.registers.is_flag_set(FlagRegisterValue::HALF_CARRY);
+        let mut carry = self.registers.is_flag_set(FlagRegisterValue::CARRY);
+        let mut adjustment = 0;
+
+        if half_carry || (!negative && a_val & 0x0f > 0x09) {
+            adjustment |= 0x06;
+        }
+
+        if carry || (!negative && a_val > 0x99) {
+            adjustment |= 0x60;
+            carry = true;
+        }
+
+        let result = if negative {
+            a_val.wrapping_sub(adjustment)
+        } else {
+            a_val.wrapping_add(adjustment)
+        };
+
+        self.registers
+            .set(GeneralRegister::A, result)
+            .toggle_flag(FlagRegisterValue::ZERO, result == 0)
+            .unset_flag(FlagRegisterValue::HALF_CARRY)
+            .toggle_flag(FlagRegisterValue::CARRY, carry);
+    }
+
+    fn stop<B: Bus>(&mut self, bus: &mut B) {
+        // Both STOP and the speed switch reset the divider
+        bus.write(DIVIDER_LOCATION, 0);
+
+        if !bus.switch_speed() {
+            self.stopped = true;
+            bus.set_stopped(true);
+        }
+    }
+
+    fn halt<B: Bus>(&mut self, bus: &B) {
+        if !self.interrupts_enabled && !self.pending_interrupts(bus).is_empty() {
+            self.halt_bug = true;
+        } else {
+            self.halted = true;
+        }
     }
 
     fn di(&mut self) {
         self.interrupts_enabled = false;
+        self.enable_interrupts_next = false;
     }
 
     fn ei(&mut self) {
-        self.interrupts_enabled = true;
+        self.enable_interrupts_next = true;
     }
 
-    fn ret(&mut self, mbc: &MBC) {
-        self.program_counter = self.pop_stack(mbc).into();
+    fn ret<B: Bus>(&mut self, bus: &mut B) {
+        let location = self.pop_stack(bus);
+        self.jump(location.into());
     }
 
-    fn ret_f(&mut self, mbc: &MBC, flag: FlagRegisterValue, truthy: bool) {
+    fn ret_f<B: Bus>(&mut self, bus: &mut B, flag: FlagRegisterValue, truthy: bool) {
+        // Checking the condition takes an M-cycle of its own
+        self.idle(bus);
+
         if self.registers.is_flag_set(flag) == truthy {
-            self.ret(mbc);
+            self.ret(bus);
         }
     }
 
-    fn reti(&mut self, mbc: &MBC) {
-        self.ret(mbc);
-        self.ei();
+    fn reti<B: Bus>(&mut self, bus: &mut B) {
+        // Unlike EI, RETI enables interrupts immediately
+        self.ret(bus);
+        self.interrupts_enabled = true;
+    }
+
+    fn rst<B: Bus>(&mut self, bus: &mut B, address: u16) {
+        self.push_stack(bus, self.program_counter as u16);
+        self.jump(address.into());
     }
 
     fn ld_r_r(&mut self, to: GeneralRegister, from: GeneralRegister) {
         self.registers.set(to, self.registers.get(from));
     }
 
-    fn ld_r_d8(&mut self, mbc: &MBC, register: GeneralRegister) {
-        self.registers
-            .set(register, mbc.get_next_u8(self.program_counter));
+    fn ld_r_d8(&mut self, register: GeneralRegister, value: u8) {
+        self.registers.set(register, value);
     }
 
-    fn ld_rr_d16(&mut self, mbc: &MBC, register: CombinedRegister) {
-        let value = mbc.get_next_u16(self.program_counter);
+    fn ld_rr_d16(&mut self, register: CombinedRegister, value: u16) {
         self.registers.set16(register, value);
     }
 
-    fn ld_mem_rr_r(&mut self, mbc: &mut MBC, to_address: CombinedRegister, from: GeneralRegister) {
-        mbc.write(
+    fn ld_mem_rr_r<B: Bus>(
+        &mut self,
+        bus: &mut B,
+        to_address: CombinedRegister,
+        from: GeneralRegister,
+    ) {
+        self.write(
+            bus,
             self.registers.get16(to_address).into(),
             self.registers.get(from),
         );
     }
 
-    fn ldi_mem_rr_r(&mut self, mbc: &mut MBC, to_address: CombinedRegister, from: GeneralRegister) {
-        self.ld_mem_rr_r(mbc, to_address, from);
+    fn ldi_mem_rr_r<B: Bus>(
+        &mut self,
+        bus: &mut B,
+        to_address: CombinedRegister,
+        from: GeneralRegister,
+    ) {
+        self.ld_mem_rr_r(bus, to_address, from);
         self.registers.increment16(to_address);
     }
 
-    fn ldd_mem_rr_r(&mut self, mbc: &mut MBC, to_address: CombinedRegister, from: GeneralRegister) {
-        self.ld_mem_rr_r(mbc, to_address, from);
+    fn ldd_mem_rr_r<B: Bus>(
+        &mut self,
+        bus: &mut B,
+        to_address: CombinedRegister,
+        from: GeneralRegister,
+    ) {
+        self.ld_mem_rr_r(bus, to_address, from);
         self.registers.decrement16(to_address);
     }
 
-    fn ld_r_mem_rr(&mut self, mbc: &MBC, to: GeneralRegister, from_address: CombinedRegister) {
-        self.registers
-            .set(to, mbc.read(self.registers.get16(from_address).into()));
+    fn ld_r_mem_rr<B: Bus>(
+        &mut self,
+        bus: &mut B,
+        to: GeneralRegister,
+        from_address: CombinedRegister,
+    ) {
+        let value = self.read(bus, self.registers.get16(from_address).into());
+        self.registers.set(to, value);
     }
 
-    fn ldi_r_mem_rr(&mut self, mbc: &MBC, to: GeneralRegister, from_address: CombinedRegister) {
-        self.ld_r_mem_rr(mbc, to, from_address);
+    fn ldi_r_mem_rr<B: Bus>(
+        &mut self,
+        bus: &mut B,
+        to: GeneralRegister,
+        from_address: CombinedRegister,
+    ) {
+        self.ld_r_mem_rr(bus, to, from_address);
         self.registers.increment16(from_address);
     }
 
-    fn ldd_r_mem_rr(&mut self, mbc: &MBC, to: GeneralRegister, from_address: CombinedRegister) {
-        self.ld_r_mem_rr(mbc, to, from_address);
+    fn ldd_r_mem_rr<B: Bus>(
+        &mut self,
+        bus: &mut B,
+        to: GeneralRegister,
+        from_address: CombinedRegister,
+    ) {
+        self.ld_r_mem_rr(bus, to, from_address);
         self.registers.decrement16(from_address);
     }
 
-    fn ld_mem_rr_d8(&mut self, mbc: &mut MBC, address: CombinedRegister) {
-        mbc.write(
-            self.registers.get16(address).into(),
-            mbc.get_next_u8(self.program_counter),
-        );
+    fn ld_mem_rr_d8<B: Bus>(&mut self, bus: &mut B, address: CombinedRegister, value: u8) {
+        self.write(bus, self.registers.get16(address).into(), value);
     }
 
-    fn ld_mem_a8_a(&mut self, mbc: &mut MBC) {
-        mbc.write(
-            (0xff00 + mbc.get_next_u8(self.program_counter) as u16).into(),
+    fn ld_mem_a8_a<B: Bus>(&mut self, bus: &mut B, offset: u8) {
+        self.write(
+            bus,
+            (0xff00 + offset as u16).into(),
             self.registers.get(GeneralRegister::A),
         );
     }
 
-    fn ld_a_mem_a8(&mut self, mbc: &MBC) {
-        self.registers.set(
-            GeneralRegister::A,
-            mbc.read((0xff00 + mbc.get_next_u8(self.program_counter) as u16).into()),
-        );
+    fn ld_a_mem_a8<B: Bus>(&mut self, bus: &mut B, offset: u8) {
+        let value = self.read(bus, (0xff00 + offset as u16).into());
+        self.registers.set(GeneralRegister::A, value);
     }
 
-    fn ld_mem_r_a(&mut self, mbc: &mut MBC, register: GeneralRegister) {
-        mbc.write(
-            self.registers.get(register).into(),
+    fn ld_mem_r_a<B: Bus>(&mut self, bus: &mut B, register: GeneralRegister) {
+        self.write(
+            bus,
+            (0xff00 + self.registers.get(register) as u16).into(),
             self.registers.get(GeneralRegister::A),
         );
     }
 
-    fn ld_a_mem_r(&mut self, mbc: &MBC, register: GeneralRegister) {
-        self.registers.set(
-            GeneralRegister::A,
-            mbc.read((0xff00 + mbc.read(self.registers.get(register).into()) as u16).into()),
-        );
+    fn ld_a_mem_r<B: Bus>(&mut self, bus: &mut B, register: GeneralRegister) {
+        let value = self.read(bus, (0xff00 + self.registers.get(register) as u16).into());
+        self.registers.set(GeneralRegister::A, value);
     }
 
-    fn ld_mem_a16_a(&mut self, mbc: &mut MBC) {
-        mbc.write(
-            mbc.get_next_u16(self.program_counter).into(),
-            self.registers.get(GeneralRegister::A),
-        );
+    fn ld_mem_a16_a<B: Bus>(&mut self, bus: &mut B, location: u16) {
+        self.write(bus, location.into(), self.registers.get(GeneralRegister::A));
     }
 
-    fn ld_a_mem_a16(&mut self, mbc: &MBC) {
-        self.registers.set(
-            GeneralRegister::A,
-            mbc.read(mbc.get_next_u16(self.program_counter).into()),
-        );
+    fn ld_a_mem_a16<B: Bus>(&mut self, bus: &mut B, location: u16) {
+        let value = self.read(bus, location.into());
+        self.registers.set(GeneralRegister::A, value);
     }
 
-    fn ld_sp_d16(&mut self, mbc: &MBC) {
-        self.stack_pointer = mbc.get_next_u16(self.program_counter);
+    fn ld_sp_d16(&mut self, value: u16) {
+        self.stack_pointer = value;
     }
 
     fn ld_sp_rr(&mut self, register: CombinedRegister) {
         self.stack_pointer = self.registers.get16(register);
     }
 
-    fn ld_mem_a16_sp(&mut self, mbc: &mut MBC) {
-        let location = mbc.get_next_u16(self.program_counter);
+    fn ld_rr_sp_s8(&mut self, register: CombinedRegister, offset: i8) {
+        let result = self.sp_s8_inner(offset);
+        self.registers.set16(register, result);
+    }
+
+    fn ld_mem_a16_sp<B: Bus>(&mut self, bus: &mut B, location: u16) {
         let [value1, value2] = u16_to_u8s(self.stack_pointer);
 
-        mbc.write(location.into(), value1);
-        mbc.write((location + 1).into(), value2);
+        self.write(bus, location.into(), value1);
+        self.write(bus, location.wrapping_add(1).into(), value2);
     }
 
     fn add_r(&mut self, register: GeneralRegister) {
         self.add_inner(self.registers.get(register));
     }
 
-    fn add_d8(&mut self, mbc: &MBC) {
-        self.add_inner(mbc.get_next_u8(self.program_counter));
+    fn add_d8(&mut self, value: u8) {
+        self.add_inner(value);
     }
 
-    fn add_mem_rr(&mut self, mbc: &MBC, register: CombinedRegister) {
-        self.add_inner(mbc.read(self.registers.get16(register).into()));
+    fn add_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
+        let value = self.read(bus, self.registers.get16(register).into());
+        self.add_inner(value);
     }
 
     fn add_rr(&mut self, register: CombinedRegister) {
@@ -229,88 +293,99 @@ impl Ops for Cpu {
         self.add_16_inner(self.stack_pointer);
     }
 
+    fn add_sp_s8(&mut self, offset: i8) {
+        self.stack_pointer = self.sp_s8_inner(offset);
+    }
+
     fn adc_r(&mut self, register: GeneralRegister) {
         self.adc_inner(self.registers.get(register));
     }
 
-    fn adc_d8(&mut self, mbc: &MBC) {
-        self.adc_inner(mbc.get_next_u8(self.program_counter));
+    fn adc_d8(&mut self, value: u8) {
+        self.adc_inner(value);
     }
 
-    fn adc_mem_rr(&mut self, mbc: &MBC, register: CombinedRegister) {
-        self.adc_inner(mbc.read(self.registers.get16(register).into()));
+    fn adc_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
+        let value = self.read(bus, self.registers.get16(register).into());
+        self.adc_inner(value);
     }
 
     fn sub_r(&mut self, register: GeneralRegister) {
         self.sub_inner(self.registers.get(register));
     }
 
-    fn sub_d8(&mut self, mbc: &MBC) {
-        self.sub_inner(mbc.get_next_u8(self.program_counter));
+    fn sub_d8(&mut self, value: u8) {
+        self.sub_inner(value);
     }
 
-    fn sub_mem_rr(&mut self, mbc: &MBC, register: CombinedRegister) {
-        self.sub_inner(mbc.read(self.registers.get16(register).into()));
+    fn sub_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
+        let value = self.read(bus, self.registers.get16(register).into());
+        self.sub_inner(value);
     }
 
     fn sbc_r(&mut self, register: GeneralRegister) {
         self.sbc_inner(self.registers.get(register));
     }
 
-    fn sbc_d8(&mut self, mbc: &MBC) {
-        self.sbc_inner(mbc.get_next_u8(self.program_counter));
+    fn sbc_d8(&mut self, value: u8) {
+        self.sbc_inner(value);
     }
 
-    fn sbc_mem_rr(&mut self, mbc: &MBC, register: CombinedRegister) {
-        self.sbc_inner(mbc.read(self.registers.get16(register).into()));
+    fn sbc_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
+        let value = self.read(bus, self.registers.get16(register).into());
+        self.sbc_inner(value);
     }
 
     fn and_r(&mut self, register: GeneralRegister) {
         self.and_inner(self.registers.get(register));
     }
 
-    fn and_d8(&mut self, mbc: &MBC) {
-        self.and_inner(mbc.get_next_u8(self.program_counter));
+    fn and_d8(&mut self, value: u8) {
+        self.and_inner(value);
     }
 
-    fn and_mem_rr(&mut self, mbc: &MBC, register: CombinedRegister) {
-        self.and_inner(mbc.read(self.registers.get16(register).into()));
+    fn and_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
+        let value = self.read(bus, self.registers.get16(register).into());
+        self.and_inner(value);
     }
 
     fn xor_r(&mut self, register: GeneralRegister) {
         self.xor_inner(self.registers.get(register));
     }
 
-    fn xor_d8(&mut self, mbc: &MBC) {
-        self.xor_inner(mbc.get_next_u8(self.program_counter));
+    fn xor_d8(&mut self, value: u8) {
+        self.xor_inner(value);
     }
 
-    fn xor_mem_rr(&mut self, mbc: &MBC, register: CombinedRegister) {
-        self.xor_inner(mbc.read(self.registers.get16(register).into()));
+    fn xor_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
+        let value = self.read(bus, self.registers.get16(register).into());
+        self.xor_inner(value);
     }
 
     fn or_r(&mut self, register: GeneralRegister) {
         self.or_inner(self.registers.get(register));
     }
 
-    fn or_d8(&mut self, mbc: &MBC) {
-        self.or_inner(mbc.get_next_u8(self.program_counter));
+    fn or_d8(&mut self, value: u8) {
+        self.or_inner(value);
     }
 
-    fn or_mem_rr(&mut self, mbc: &MBC, register: CombinedRegister) {
-        self.or_inner(mbc.read(self.registers.get16(register).into()));
+    fn or_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
+        let value = self.read(bus, self.registers.get16(register).into());
+        self.or_inner(value);
     }
 
     fn cp_r(&mut self, register: GeneralRegister) {
         self.cp_inner(self.registers.get(register));
     }
 
-    fn cp_d8(&mut self, mbc: &MBC) {
-        self.cp_inner(mbc.get_next_u8(self.program_counter));
+    fn cp_d8(&mut self, value: u8) {
+        self.cp_inner(value);
     }
 
-    fn cp_mem_rr(&mut self, mbc: &MBC, register: CombinedRegister) {
-        self.cp_inner(mbc.read(self.registers.get16(register).into()));
+    fn cp_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
+        let value = self.read(bus, self.registers.get16(register).into());
+        self.cp_inner(value);
     }
 
     fn inc_r(&mut self, register: GeneralRegister) {
@@ -326,10 +401,9 @@ impl Ops for Cpu {
             .set(register, result);
     }
 
-    fn inc_mem_rr(&mut self, mbc: &mut MBC, register: CombinedRegister) {
-        let result = mbc
-            .read(self.registers.get16(register).into())
-            .wrapping_add(1);
+    fn inc_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
+        let location: usize = self.registers.get16(register).into();
+        let result = self.read(bus, location).wrapping_add(1);
 
         self.registers
             .toggle_flag(FlagRegisterValue::ZERO, result == 0)
@@ -339,7 +413,7 @@ impl Ops for Cpu {
                 result.inc_should_half_carry(),
             );
 
-        mbc.write(self.registers.get16(register).into(), result);
+        self.write(bus, location, result);
     }
 
     fn inc_rr(&mut self, register: CombinedRegister) {
@@ -364,20 +438,19 @@ impl Ops for Cpu {
             .set(register, result);
     }
 
-    fn dec_mem_rr(&mut self, mbc: &mut MBC, register: CombinedRegister) {
-        let result = mbc
-            .read(self.registers.get16(register).into())
-            .wrapping_sub(1);
+    fn dec_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
+        let location: usize = self.registers.get16(register).into();
+        let result = self.read(bus, location).wrapping_sub(1);
 
         self.registers
             .toggle_flag(FlagRegisterValue::ZERO, result == 0)
-            .unset_flag(FlagRegisterValue::NEGATIVE)
+            .set_flag(FlagRegisterValue::NEGATIVE)
             .toggle_flag(
                 FlagRegisterValue::HALF_CARRY,
                 result.dec_should_half_carry(),
             );
 
-        mbc.write(self.registers.get16(register).into(), result);
+        self.write(bus, location, result);
     }
 
     fn dec_rr(&mut self, register: CombinedRegister) {
@@ -389,51 +462,54 @@ impl Ops for Cpu {
         self.stack_pointer = self.stack_pointer.wrapping_sub(1);
     }
 
-    fn push_rr(&mut self, mbc: &mut MBC, register: CombinedRegister) {
-        self.push_stack(mbc, self.registers.get16(register));
+    fn push_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
+        self.push_stack(bus, self.registers.get16(register));
     }
 
-    fn pop_rr(&mut self, mbc: &MBC, register: CombinedRegister) {
-        let out = self.pop_stack(mbc);
+    fn pop_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
+        let out = self.pop_stack(bus);
         self.registers.set16(register, out);
     }
 
-    fn jp(&mut self, mbc: &MBC, flag: Option<FlagRegisterValue>, truthy: bool) {
+    fn jp(&mut self, flag: Option<FlagRegisterValue>, truthy: bool, location: u16) {
         match flag {
-            Some(f) if self.registers.is_flag_set(f) != truthy => {
-                return;
-            }
-            _ => {
-                let jump_location = mbc.get_next_u16(self.program_counter);
-
-                self.program_counter = jump_location.into();
-            }
+            Some(f) if self.registers.is_flag_set(f) != truthy => {}
+            _ => self.jump(location.into()),
         }
     }
 
-    fn jr(&mut self, mbc: &MBC, flag: Option<FlagRegisterValue>, truthy: bool) {
+    fn jr(&mut self, flag: Option<FlagRegisterValue>, truthy: bool, offset: i8) {
         match flag {
-            Some(f) if self.registers.is_flag_set(f) != truthy => {
-                return;
-            }
+            Some(f) if self.registers.is_flag_set(f) != truthy => {}
             _ => {
-                let relative_location = mbc.get_next_u8(self.program_counter) as i8;
-
-                self.program_counter =
-                    self.program_counter
-                        .wrapping_add(relative_location as usize) as usize;
+                // Relative to the end of this two byte instruction
+                let location = (self.program_counter as u16).wrapping_add(offset as u16);
+                self.jump(location.into());
             }
         }
     }
 
-    fn call_f_a16(&mut self, mbc: &mut MBC, flag: FlagRegisterValue, truthy: bool) {
+    fn jp_rr(&mut self, register: CombinedRegister) {
+        self.jump(self.registers.get16(register).into());
+    }
+
+    fn call_f_a16<B: Bus>(
+        &mut self,
+        bus: &mut B,
+        flag: FlagRegisterValue,
+        truthy: bool,
+        location: u16,
+    ) {
         if self.registers.is_flag_set(flag) == truthy {
-            self.call(mbc);
+            self.call(bus, location);
         }
     }
 
     fn scf(&mut self) {
-        self.registers.set_flag(FlagRegisterValue::CARRY);
+        self.registers
+            .set_flag(FlagRegisterValue::CARRY)
+            .unset_flag(FlagRegisterValue::NEGATIVE)
+            .unset_flag(FlagRegisterValue::HALF_CARRY);
     }
 
     fn cpl(&mut self) {
@@ -484,10 +560,11 @@ impl PrefixOps for Cpu {
         self.registers.set(register, result);
     }
 
-    fn rlc_mem_rr(&mut self, mbc: &mut MBC, register: CombinedRegister) {
+    fn rlc_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
         let location: usize = self.registers.get16(register).into();
-        let result = self.rlc_inner(mbc.read(location));
-        mbc.write(location, result);
+        let value = self.read(bus, location);
+        let result = self.rlc_inner(value);
+        self.write(bus, location, result);
     }
 
     fn rl_r(&mut self, register: GeneralRegister) {
@@ -495,10 +572,11 @@ impl PrefixOps for Cpu {
         self.registers.set(register, result);
     }
 
-    fn rl_mem_rr(&mut self, mbc: &mut MBC, register: CombinedRegister) {
+    fn rl_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
         let location: usize = self.registers.get16(register).into();
-        let result = self.rl_inner(mbc.read(location));
-        mbc.write(location, result);
+        let value = self.read(bus, location);
+        let result = self.rl_inner(value);
+        self.write(bus, location, result);
     }
 
     fn rrc_r(&mut self, register: GeneralRegister) {
@@ -506,10 +584,11 @@ impl PrefixOps for Cpu {
         self.registers.set(register, result);
     }
 
-    fn rrc_mem_rr(&mut self, mbc: &mut MBC, register: CombinedRegister) {
+    fn rrc_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
         let location: usize = self.registers.get16(register).into();
-        let result = self.rrc_inner(mbc.read(location));
-        mbc.write(location, result);
+        let value = self.read(bus, location);
+        let result = self.rrc_inner(value);
+        self.write(bus, location, result);
     }
 
     fn rr_r(&mut self, register: GeneralRegister) {
@@ -517,22 +596,59 @@ impl PrefixOps for Cpu {
         self.registers.set(register, result);
     }
 
-    fn rr_mem_rr(&mut self, mbc: &mut MBC, register: CombinedRegister) {
+    fn rr_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
         let location: usize = self.registers.get16(register).into();
-        let result = self.rr_inner(mbc.read(location));
-        mbc.write(location, result);
+        let value = self.read(bus, location);
+        let result = self.rr_inner(value);
+        self.write(bus, location, result);
+    }
+
+    fn sla_r(&mut self, register: GeneralRegister) {
+        let result = self.sla_inner(self.registers.get(register));
+        self.registers.set(register, result);
+    }
+
+    fn sla_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
+        let location: usize = self.registers.get16(register).into();
+        let value = self.read(bus, location);
+        let result = self.sla_inner(value);
+        self.write(bus, location, result);
+    }
+
+    fn sra_r(&mut self, register: GeneralRegister) {
+        let result = self.sra_inner(self.registers.get(register));
+        self.registers.set(register, result);
+    }
+
+    fn sra_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
+        let location: usize = self.registers.get16(register).into();
+        let value = self.read(bus, location);
+        let result = self.sra_inner(value);
+        self.write(bus, location, result);
+    }
+
+    fn swap_r(&mut self, register: GeneralRegister) {
+        let result = self.swap_inner(self.registers.get(register));
+        self.registers.set(register, result);
+    }
+
+    fn swap_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
+        let location: usize = self.registers.get16(register).into();
+        let value = self.read(bus, location);
+        let result = self.swap_inner(value);
+        self.write(bus, location, result);
     }
 
     fn srl_r(&mut self, register: GeneralRegister) {
-        let value = self.registers.get(register);
-        let result = value.rotate_right(1).unset_bit(1 << 7);
+        let result = self.srl_inner(self.registers.get(register));
+        self.registers.set(register, result);
+    }
 
-        self.registers
-            .toggle_flag(FlagRegisterValue::ZERO, result == 0)
-            .unset_flag(FlagRegisterValue::NEGATIVE)
-            .unset_flag(FlagRegisterValue::HALF_CARRY)
-            .toggle_flag(FlagRegisterValue::CARRY, result.is_bit_set(1 << 0))
-            .set(register, result);
+    fn srl_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister) {
+        let location: usize = self.registers.get16(register).into();
+        let value = self.read(bus, location);
+        let result = self.srl_inner(value);
+        self.write(bus, location, result);
     }
 
     fn bit_r(&mut self, bit: u8, register: GeneralRegister) {
@@ -540,45 +656,98 @@ impl PrefixOps for Cpu {
         self.bit_inner(bit, value);
     }
 
-    fn bit_mem_rr(&mut self, mbc: &MBC, bit: u8, register: CombinedRegister) {
-        let value = mbc.read(self.registers.get16(register).into());
+    fn bit_mem_rr<B: Bus>(&mut self, bus: &mut B, bit: u8, register: CombinedRegister) {
+        let value = self.read(bus, self.registers.get16(register).into());
         self.bit_inner(bit, value);
     }
+
+    fn res_r(&mut self, bit: u8, register: GeneralRegister) {
+        let result = self.registers.get(register).unset_bit(1 << bit);
+        self.registers.set(register, result);
+    }
+
+    fn res_mem_rr<B: Bus>(&mut self, bus: &mut B, bit: u8, register: CombinedRegister) {
+        let location: usize = self.registers.get16(register).into();
+        let result = self.read(bus, location).unset_bit(1 << bit);
+        self.write(bus, location, result);
+    }
+
+    fn set_r(&mut self, bit: u8, register: GeneralRegister) {
+        let result = self.registers.get(register).set_bit(1 << bit);
+        self.registers.set(register, result);
+    }
+
+    fn set_mem_rr<B: Bus>(&mut self, bus: &mut B, bit: u8, register: CombinedRegister) {
+        let location: usize = self.registers.get16(register).into();
+        let result = self.read(bus, location).set_bit(1 << bit);
+        self.write(bus, location, result);
+    }
 }
 
 impl Cpu {
-    fn pop_stack(&mut self, mbc: &MBC) -> u16 {
-        let low_byte = mbc.read(self.stack_pointer.into());
+    fn pop_stack<B: Bus>(&mut self, bus: &mut B) -> u16 {
+        let low_byte = self.read(bus, self.stack_pointer.into());
         self.stack_pointer = self.stack_pointer.wrapping_add(1);
         //println!("Low Byte: {:#06x}", low_byte);
-        let high_byte = mbc.read(self.stack_pointer.into());
+        let high_byte = self.read(bus, self.stack_pointer.into());
         self.stack_pointer = self.stack_pointer.wrapping_add(1);
         //println!("High Byte: {:#06x}", high_byte);
 
         u8s_to_u16(high_byte, low_byte)
     }
 
-    fn push_stack(&mut self, mbc: &mut MBC, value: u16) {
+    fn push_stack<B: Bus>(&mut self, bus: &mut B, value: u16) {
         let [high, low] = u16_to_u8s(value);
+
+        // SP is decremented in an M-cycle of its own before the first write
+        self.idle(bus);
         self.stack_pointer = self.stack_pointer.wrapping_sub(1);
-        mbc.write(self.stack_pointer.into(), high);
+        self.write(bus, self.stack_pointer.into(), high);
         self.stack_pointer = self.stack_pointer.wrapping_sub(1);
-        mbc.write(self.stack_pointer.into(), low);
+        self.write(bus, self.stack_pointer.into(), low);
     }
 
-    fn carry_val(&self) -> u8 {
-        self.registers.is_flag_set(FlagRegisterValue::CARRY) as u8
+    /// Advances the rest of the machine by one M-cycle
+    fn idle<B: Bus>(&mut self, bus: &mut B) {
+        bus.tick(4);
+        self.cycles += 4;
+    }
+
+    /// Reads from the bus, taking one M-cycle
+    fn read<B: Bus>(&mut self, bus: &mut B, location: usize) -> u8 {
+        self.idle(bus);
+        bus.read(location)
+    }
+
+    /// Writes to the bus, taking one M-cycle
+    fn write<B: Bus>(&mut self, bus: &mut B, location: usize, value: u8) {
+        self.idle(bus);
+        bus.write(location, value);
+    }
+
+    /// Burns whatever internal M-cycles an operation has left after its
+    /// memory accesses, so the step as a whole takes `cycles` T-cycles
+    fn idle_until<B: Bus>(&mut self, bus: &mut B, cycles: u32) {
+        debug_assert!(self.cycles <= cycles, "memory accesses overran timing");
+
+        while self.cycles < cycles {
+            self.idle(bus);
+        }
     }
 
-    /// Represents an unused CPU instruction. If this instruction is called
-    /// there is most likely a bug in the emulator or the rom.
-    fn nothing(&mut self) {
-        panic!("Invalid CPU Instruction");
+    fn jump(&mut self, location: usize) {
+        self.program_counter = location;
+        self.branched = true;
     }
 
-    fn not_implemented(&mut self, message: &str) {
-        // Hunting for Not Implementeds now
-        panic!("TODO: {}", message);
+    fn carry_val(&self) -> u8 {
+        self.registers.is_flag_set(FlagRegisterValue::CARRY) as u8
+    }
+
+    /// Unused opcodes hang the real CPU until it is reset, while the rest of
+    /// the machine carries on running
+    fn lock(&mut self) {
+        self.locked = true;
     }
 
     fn add_inner(&mut self, value: u8) {
@@ -597,7 +766,24 @@ impl Cpu {
     }
 
     fn adc_inner(&mut self, value: u8) {
-        self.add_inner(value.wrapping_add(self.carry_val()));
+        let a_value = self.registers.get(GeneralRegister::A);
+        let carry = self.carry_val();
+        let result = a_value.wrapping_add(value).wrapping_add(carry);
+
+        // The carry has to be counted separately, folding it into the value
+        // first loses it when the value is 0xff
+        self.registers
+            .set(GeneralRegister::A, result)
+            .toggle_flag(FlagRegisterValue::ZERO, result == 0)
+            .unset_flag(FlagRegisterValue::NEGATIVE)
+            .toggle_flag(
+                FlagRegisterValue::HALF_CARRY,
+                a_value.add_should_half_carry_with(value, carry),
+            )
+            .toggle_flag(
+                FlagRegisterValue::CARRY,
+                a_value.add_should_carry_with(value, carry),
+            );
     }
 
     fn add_16_inner(&mut self, value: u16) {
@@ -614,6 +800,24 @@ impl Cpu {
             .toggle_flag(FlagRegisterValue::CARRY, hl_val.add_should_carry(value));
     }
 
+    /// Shared by `ADD SP, i8` and `LD HL, SP+i8`. The offset is signed, but
+    /// H and C are always taken from an unsigned add on the low byte of SP.
+    fn sp_s8_inner(&mut self, offset: i8) -> u16 {
+        let offset = offset as u8;
+        let sp_low = self.stack_pointer as u8;
+
+        self.registers
+            .unset_flag(FlagRegisterValue::ZERO)
+            .unset_flag(FlagRegisterValue::NEGATIVE)
+            .toggle_flag(
+                FlagRegisterValue::HALF_CARRY,
+                sp_low.add_should_half_carry(offset),
+            )
+            .toggle_flag(FlagRegisterValue::CARRY, sp_low.add_should_carry(offset));
+
+        self.stack_pointer.wrapping_add(offset as i8 as u16)
+    }
+
     fn sub_inner(&mut self, value: u8) {
         let a_val = self.registers.get(GeneralRegister::A);
         let result = a_val.wrapping_sub(value);
@@ -630,7 +834,22 @@ impl Cpu {
     }
 
     fn sbc_inner(&mut self, value: u8) {
-        self.sub_inner(value.wrapping_add(self.carry_val()));
+        let a_val = self.registers.get(GeneralRegister::A);
+        let carry = self.carry_val();
+        let result = a_val.wrapping_sub(value).wrapping_sub(carry);
+
+        self.registers
+            .toggle_flag(FlagRegisterValue::ZERO, result == 0)
+            .set_flag(FlagRegisterValue::NEGATIVE)
+            .toggle_flag(
+                FlagRegisterValue::HALF_CARRY,
+                a_val.sub_should_half_carry_with(value, carry),
+            )
+            .toggle_flag(
+                FlagRegisterValue::CARRY,
+                a_val.sub_should_carry_with(value, carry),
+            )
+            .set(GeneralRegister::A, result);
     }
 
     fn and_inner(&mut self, value: u8) {
@@ -638,6 +857,7 @@ impl Cpu {
         let result = a_val & value;
 
         self.registers
+            .set(GeneralRegister::A, result)
             .toggle_flag(FlagRegisterValue::ZERO, result == 0)
             .unset_flag(FlagRegisterValue::NEGATIVE)
             .set_flag(FlagRegisterValue::HALF_CARRY)
@@ -688,23 +908,15 @@ impl Cpu {
             .set_flag(FlagRegisterValue::HALF_CARRY);
     }
 
-    fn call(&mut self, mbc: &mut MBC) {
-        let call_location = mbc.get_next_u16(self.program_counter);
-
-        let [high, low] = u16_to_u8s((self.program_counter + 3) as u16);
-
-        mbc.write(self.stack_pointer.wrapping_sub(1).into(), high);
-        mbc.write(self.stack_pointer.wrapping_sub(2).into(), low);
-
-        self.stack_pointer = self.stack_pointer.wrapping_sub(2);
-
-        self.program_counter = call_location as usize;
+    fn call<B: Bus>(&mut self, bus: &mut B, location: u16) {
+        self.push_stack(bus, self.program_counter as u16);
+        self.jump(location.into());
     }
 
     fn rl_inner(&mut self, value: u8) -> u8 {
         let carry = self.carry_val();
         let will_carry = value.is_bit_set(1 << 7);
-        let result = value.rotate_left(1) | carry;
+        let result = (value << 1) | carry;
 
         self.registers
             .toggle_flag(FlagRegisterValue::ZERO, result == 0)
@@ -717,8 +929,7 @@ impl Cpu {
 
     fn rlc_inner(&mut self, value: u8) -> u8 {
         let will_carry = value.is_bit_set(1 << 7);
-        let truncate_bit = value.is_bit_set(1 << 7) as u8;
-        let result = value.rotate_left(1) | truncate_bit;
+        let result = value.rotate_left(1);
 
         self.registers
             .toggle_flag(FlagRegisterValue::ZERO, result == 0)
@@ -731,8 +942,8 @@ impl Cpu {
 
     fn rr_inner(&mut self, value: u8) -> u8 {
         let carry = self.carry_val();
-        let will_carry = value.is_bit_set(0);
-        let result = value.rotate_right(1) | carry.rotate_left(7);
+        let will_carry = value.is_bit_set(1 << 0);
+        let result = (value >> 1) | carry.rotate_left(7);
 
         self.registers
             .toggle_flag(FlagRegisterValue::ZERO, result == 0)
@@ -744,9 +955,8 @@ impl Cpu {
     }
 
     fn rrc_inner(&mut self, value: u8) -> u8 {
-        let will_carry = value.is_bit_set(0);
-        let truncate_bit = value.is_bit_set(0) as u8;
-        let result = value.rotate_right(1) | truncate_bit.rotate_left(7);
+        let will_carry = value.is_bit_set(1 << 0);
+        let result = value.rotate_right(1);
 
         self.registers
             .toggle_flag(FlagRegisterValue::ZERO, result == 0)
@@ -757,500 +967,944 @@ impl Cpu {
         result
     }
 
-    fn res_r(&mut self, bit: u8, register: GeneralRegister) {
-        let value = self.registers.get(register);
-        value.unset_bit(1 << bit);
-    }
-
-    fn res_mem_rr(&mut self, mbc: &mut MBC, bit: u8) {
-        let location: usize = self.registers.get16(CombinedRegister::HL).into();
-        let value = mbc.read(location);
-        let result = value.unset_bit(1 << bit);
-
-        mbc.write(location, result);
-    }
-
-    fn prefix(&mut self, mbc: &mut MBC) {
-        let op = mbc.get_next_u8(self.program_counter);
-
-        match op {
-            0x00 => self.rlc_r(GeneralRegister::B),
-            0x01 => self.rlc_r(GeneralRegister::C),
-            0x02 => self.rlc_r(GeneralRegister::D),
-            0x03 => self.rlc_r(GeneralRegister::E),
-            0x04 => self.rlc_r(GeneralRegister::H),
-            0x05 => self.rlc_r(GeneralRegister::L),
-            0x06 => self.rlc_mem_rr(mbc, CombinedRegister::HL),
-            0x07 => self.rlc_r(GeneralRegister::A),
-            0x08 => self.rrc_r(GeneralRegister::B),
-            0x09 => self.rrc_r(GeneralRegister::C),
-            0x0A => self.rrc_r(GeneralRegister::D),
-            0x0B => self.rrc_r(GeneralRegister::E),
-            0x0C => self.rrc_r(GeneralRegister::H),
-            0x0D => self.rrc_r(GeneralRegister::L),
-            0x0E => self.rrc_mem_rr(mbc, CombinedRegister::HL),
-            0x0F => self.rrc_r(GeneralRegister::A),
-
-            0x10 => self.rl_r(GeneralRegister::B),
-            0x11 => self.rl_r(GeneralRegister::C),
-            0x12 => self.rl_r(GeneralRegister::D),
-            0x13 => self.rl_r(GeneralRegister::E),
-            0x14 => self.rl_r(GeneralRegister::H),
-            0x15 => self.rl_r(GeneralRegister::L),
-            0x16 => self.rl_mem_rr(mbc, CombinedRegister::HL),
-            0x17 => self.rl_r(GeneralRegister::A),
-            0x18 => self.rr_r(GeneralRegister::B),
-            0x19 => self.rr_r(GeneralRegister::C),
-            0x1A => self.rr_r(GeneralRegister::D),
-            0x1B => self.rr_r(GeneralRegister::E),
-            0x1C => self.rr_r(GeneralRegister::H),
-            0x1D => self.rr_r(GeneralRegister::L),
-            0x1E => self.rr_mem_rr(mbc, CombinedRegister::HL),
-            0x1F => self.rr_r(GeneralRegister::A),
-
-            0x38 => self.srl_r(GeneralRegister::B),
-            0x39 => self.srl_r(GeneralRegister::C),
-            0x3A => self.srl_r(GeneralRegister::D),
-            0x3B => self.srl_r(GeneralRegister::E),
-            0x3C => self.srl_r(GeneralRegister::H),
-            0x3D => self.srl_r(GeneralRegister::L),
-
-            0x3F => self.srl_r(GeneralRegister::A),
-
-            0x40 => self.bit_r(0, GeneralRegister::B),
-            0x41 => self.bit_r(0, GeneralRegister::C),
-            0x42 => self.bit_r(0, GeneralRegister::D),
-            0x43 => self.bit_r(0, GeneralRegister::E),
-            0x44 => self.bit_r(0, GeneralRegister::H),
-            0x45 => self.bit_r(0, GeneralRegister::L),
-            0x46 => self.bit_mem_rr(mbc, 0, CombinedRegister::HL),
-            0x47 => self.bit_r(0, GeneralRegister::A),
-            0x48 => self.bit_r(1, GeneralRegister::B),
-            0x49 => self.bit_r(1, GeneralRegister::C),
-            0x4A => self.bit_r(1, GeneralRegister::D),
-            0x4B => self.bit_r(1, GeneralRegister::E),
-            0x4C => self.bit_r(1, GeneralRegister::H),
-            0x4D => self.bit_r(1, GeneralRegister::L),
-            0x4E => self.bit_mem_rr(mbc, 1, CombinedRegister::HL),
-            0x4F => self.bit_r(1, GeneralRegister::A),
-
-            0x50 => self.bit_r(2, GeneralRegister::B),
-            0x51 => self.bit_r(2, GeneralRegister::C),
-            0x52 => self.bit_r(2, GeneralRegister::D),
-            0x53 => self.bit_r(2, GeneralRegister::E),
-            0x54 => self.bit_r(2, GeneralRegister::H),
-            0x55 => self.bit_r(2, GeneralRegister::L),
-            0x56 => self.bit_mem_rr(mbc, 2, CombinedRegister::HL),
-            0x57 => self.bit_r(2, GeneralRegister::A),
-            0x58 => self.bit_r(3, GeneralRegister::B),
-            0x59 => self.bit_r(3, GeneralRegister::C),
-            0x5A => self.bit_r(3, GeneralRegister::D),
-            0x5B => self.bit_r(3, GeneralRegister::E),
-            0x5C => self.bit_r(3, GeneralRegister::H),
-            0x5D => self.bit_r(3, GeneralRegister::L),
-            0x5E => self.bit_mem_rr(mbc, 3, CombinedRegister::HL),
-            0x5F => self.bit_r(3, GeneralRegister::A),
-
-            0x60 => self.bit_r(4, GeneralRegister::B),
-            0x61 => self.bit_r(4, GeneralRegister::C),
-            0x62 => self.bit_r(4, GeneralRegister::D),
-            0x63 => self.bit_r(4, GeneralRegister::E),
-            0x64 => self.bit_r(4, GeneralRegister::H),
-            0x65 => self.bit_r(4, GeneralRegister::L),
-            0x66 => self.bit_mem_rr(mbc, 4, CombinedRegister::HL),
-            0x67 => self.bit_r(4, GeneralRegister::A),
-            0x68 => self.bit_r(5, GeneralRegister::B),
-            0x69 => self.bit_r(5, GeneralRegister::C),
-            0x6A => self.bit_r(5, GeneralRegister::D),
-            0x6B => self.bit_r(5, GeneralRegister::E),
-            0x6C => self.bit_r(5, GeneralRegister::H),
-            0x6D => self.bit_r(5, GeneralRegister::L),
-            0x6E => self.bit_mem_rr(mbc, 5, CombinedRegister::HL),
-            0x6F => self.bit_r(5, GeneralRegister::A),
-
-            0x70 => self.bit_r(6, GeneralRegister::B),
-            0x71 => self.bit_r(6, GeneralRegister::C),
-            0x72 => self.bit_r(6, GeneralRegister::D),
-            0x73 => self.bit_r(6, GeneralRegister::E),
-            0x74 => self.bit_r(6, GeneralRegister::H),
-            0x75 => self.bit_r(6, GeneralRegister::L),
-            0x76 => self.bit_mem_rr(mbc, 6, CombinedRegister::HL),
-            0x77 => self.bit_r(6, GeneralRegister::A),
-            0x78 => self.bit_r(7, GeneralRegister::B),
-            0x79 => self.bit_r(7, GeneralRegister::C),
-            0x7A => self.bit_r(7, GeneralRegister::D),
-            0x7B => self.bit_r(7, GeneralRegister::E),
-            0x7C => self.bit_r(7, GeneralRegister::H),
-            0x7D => self.bit_r(7, GeneralRegister::L),
-            0x7E => self.bit_mem_rr(mbc, 7, CombinedRegister::HL),
-            0x7F => self.bit_r(7, GeneralRegister::A),
-
-            0x80 => self.res_r(0, GeneralRegister::B),
-            0x81 => self.res_r(0, GeneralRegister::C),
-            0x82 => self.res_r(0, GeneralRegister::D),
-            0x83 => self.res_r(0, GeneralRegister::E),
-            0x84 => self.res_r(0, GeneralRegister::H),
-            0x85 => self.res_r(0, GeneralRegister::L),
-            0x86 => self.res_mem_rr(mbc, 0),
-            0x87 => self.res_r(0, GeneralRegister::A),
-            0x88 => self.res_r(1, GeneralRegister::B),
-            0x89 => self.res_r(1, GeneralRegister::C),
-            0x8A => self.res_r(1, GeneralRegister::D),
-            0x8B => self.res_r(1, GeneralRegister::E),
-            0x8C => self.res_r(1, GeneralRegister::H),
-            0x8D => self.res_r(1, GeneralRegister::L),
-            0x8E => self.res_mem_rr(mbc, 1),
-            0x8F => self.res_r(1, GeneralRegister::A),
-
-            0x90 => self.res_r(2, GeneralRegister::B),
-            0x91 => self.res_r(2, GeneralRegister::C),
-            0x92 => self.res_r(2, GeneralRegister::D),
-            0x93 => self.res_r(2, GeneralRegister::E),
-            0x94 => self.res_r(2, GeneralRegister::H),
-            0x95 => self.res_r(2, GeneralRegister::L),
-            0x96 => self.res_mem_rr(mbc, 2),
-            0x97 => self.res_r(2, GeneralRegister::A),
-            0x98 => self.res_r(3, GeneralRegister::B),
-            0x99 => self.res_r(3, GeneralRegister::C),
-            0x9A => self.res_r(3, GeneralRegister::D),
-            0x9B => self.res_r(3, GeneralRegister::E),
-            0x9C => self.res_r(3, GeneralRegister::H),
-            0x9D => self.res_r(3, GeneralRegister::L),
-            0x9E => self.res_mem_rr(mbc, 3),
-            0x9F => self.res_r(3, GeneralRegister::A),
-
-            0xA0 => self.res_r(4, GeneralRegister::B),
-            0xA1 => self.res_r(4, GeneralRegister::C),
-            0xA2 => self.res_r(4, GeneralRegister::D),
-            0xA3 => self.res_r(4, GeneralRegister::E),
-            0xA4 => self.res_r(4, GeneralRegister::H),
-            0xA5 => self.res_r(4, GeneralRegister::L),
-            0xA6 => self.res_mem_rr(mbc, 4),
-            0xA7 => self.res_r(4, GeneralRegister::A),
-            0xA8 => self.res_r(5, GeneralRegister::B),
-            0xA9 => self.res_r(5, GeneralRegister::C),
-            0xAA => self.res_r(5, GeneralRegister::D),
-            0xAB => self.res_r(5, GeneralRegister::E),
-            0xAC => self.res_r(5, GeneralRegister::H),
-            0xAD => self.res_r(5, GeneralRegister::L),
-            0xAE => self.res_mem_rr(mbc, 5),
-            0xAF => self.res_r(5, GeneralRegister::A),
-
-            0xB0 => self.res_r(6, GeneralRegister::B),
-            0xB1 => self.res_r(6, GeneralRegister::C),
-            0xB2 => self.res_r(6, GeneralRegister::D),
-            0xB3 => self.res_r(6, GeneralRegister::E),
-            0xB4 => self.res_r(6, GeneralRegister::H),
-            0xB5 => self.res_r(6, GeneralRegister::L),
-            0xB6 => self.res_mem_rr(mbc, 6),
-            0xB7 => self.res_r(6, GeneralRegister::A),
-            0xB8 => self.res_r(7, GeneralRegister::B),
-            0xB9 => self.res_r(7, GeneralRegister::C),
-            0xBA => self.res_r(7, GeneralRegister::D),
-            0xBB => self.res_r(7, GeneralRegister::E),
-            0xBC => self.res_r(7, GeneralRegister::H),
-            0xBD => self.res_r(7, GeneralRegister::L),
-            0xBE => self.res_mem_rr(mbc, 7),
-            0xBF => self.res_r(7, GeneralRegister::A),
-
-            _ => self.not_implemented(&format!("Prefix not implemented: {:#04x}", op)),
+    fn sla_inner(&mut self, value: u8) -> u8 {
+        let will_carry = value.is_bit_set(1 << 7);
+        let result = value << 1;
+
+        self.registers
+            .toggle_flag(FlagRegisterValue::ZERO, result == 0)
+            .unset_flag(FlagRegisterValue::NEGATIVE)
+            .unset_flag(FlagRegisterValue::HALF_CARRY)
+            .toggle_flag(FlagRegisterValue::CARRY, will_carry);
+
+        result
+    }
+
+    fn sra_inner(&mut self, value: u8) -> u8 {
+        // Arithmetic shift, so bit 7 keeps its sign
+        let will_carry = value.is_bit_set(1 << 0);
+        let result = (value >> 1) | (value & (1 << 7));
+
+        self.registers
+            .toggle_flag(FlagRegisterValue::ZERO, result == 0)
+            .unset_flag(FlagRegisterValue::NEGATIVE)
+            .unset_flag(FlagRegisterValue::HALF_CARRY)
+            .toggle_flag(FlagRegisterValue::CARRY, will_carry);
+
+        result
+    }
+
+    fn srl_inner(&mut self, value: u8) -> u8 {
+        let will_carry = value.is_bit_set(1 << 0);
+        let result = value >> 1;
+
+        self.registers
+            .toggle_flag(FlagRegisterValue::ZERO, result == 0)
+            .unset_flag(FlagRegisterValue::NEGATIVE)
+            .unset_flag(FlagRegisterValue::HALF_CARRY)
+            .toggle_flag(FlagRegisterValue::CARRY, will_carry);
+
+        result
+    }
+
+    fn swap_inner(&mut self, value: u8) -> u8 {
+        let result = value.rotate_left(4);
+
+        self.registers
+            .toggle_flag(FlagRegisterValue::ZERO, result == 0)
+            .unset_flag(FlagRegisterValue::NEGATIVE)
+            .unset_flag(FlagRegisterValue::HALF_CARRY)
+            .unset_flag(FlagRegisterValue::CARRY);
+
+        result
+    }
+
+    /// Interrupts that are both requested (IF) and enabled (IE)
+    fn pending_interrupts<B: Bus>(&self, bus: &B) -> Interrupt {
+        Interrupt::from_bits_truncate(
+            bus.read(INTERRUPT_ENABLE_LOCATION) & bus.read(INTERRUPT_FLAG_LOCATION),
+        )
+    }
+
+    fn dispatch_interrupt<B: Bus>(&mut self, bus: &mut B, interrupt: Interrupt) {
+        let requested = bus.read(INTERRUPT_FLAG_LOCATION);
+
+        self.interrupts_enabled = false;
+        bus.write(INTERRUPT_FLAG_LOCATION, requested & !interrupt.bits());
+
+        self.idle(bus);
+        self.push_stack(bus, self.program_counter as u16);
+        self.program_counter = interrupt.vector().into();
+
+        self.idle_until(bus, INTERRUPT_DISPATCH_CYCLES);
+    }
+
+    /// Services the highest priority pending interrupt if IME allows it,
+    /// otherwise runs a single instruction. The bus is ticked an M-cycle at a
+    /// time as the step goes, and the total T-cycles taken are returned.
+    ///
+    /// Hitting an illegal opcode returns an error once and leaves the CPU
+    /// locked up. Stepping a locked CPU keeps the rest of the machine running.
+    pub fn step<B: Bus>(&mut self, bus: &mut B) -> Result<u32, EmulatorError> {
+        self.cycles = 0;
+        self.step_inner(bus)?;
+        Ok(self.cycles)
+    }
+
+    fn step_inner<B: Bus>(&mut self, bus: &mut B) -> Result<(), EmulatorError> {
+        if self.locked {
+            self.idle(bus);
+            return Ok(());
+        }
+
+        if self.stopped {
+            // Any selected button line going low wakes the CPU back up
+            if bus.read(JOYPAD_LOCATION) & 0b0000_1111 == 0b0000_1111 {
+                // The clock is stopped, so nothing else gets ticked, but
+                // callers still need time to pass
+                self.cycles = 4;
+                return Ok(());
+            }
+
+            self.stopped = false;
+            bus.set_stopped(false);
+        }
+
+        let pending = self.pending_interrupts(bus);
+
+        if self.halted {
+            // Any pending interrupt ends HALT, even when IME is off
+            if pending.is_empty() {
+                self.idle(bus);
+                return Ok(());
+            }
+
+            self.halted = false;
+        }
+
+        if self.interrupts_enabled {
+            if let Some(interrupt) = pending.highest_priority() {
+                self.dispatch_interrupt(bus, interrupt);
+                return Ok(());
+            }
         }
 
-        self.program_counter += PREFIX_OPCODE_CYCLES[op as usize];
+        let enable_interrupts = self.enable_interrupts_next;
+        self.execute(bus)?;
+
+        if enable_interrupts && self.enable_interrupts_next {
+            self.interrupts_enabled = true;
+            self.enable_interrupts_next = false;
+        }
+
+        Ok(())
     }
 
-    pub fn apply_operation(&mut self, mbc: &mut MBC) {
-        self.current_op = mbc.read(self.program_counter);
+    fn execute<B: Bus>(&mut self, bus: &mut B) -> Result<(), EmulatorError> {
+        let mut location = self.program_counter;
+        let opcode = bus.read(location);
+
+        if self.halt_bug {
+            // The opcode byte is read again as the start of its own operands
+            self.halt_bug = false;
+            location = location.wrapping_sub(1) & 0xffff;
+        }
+
+        let instruction = Instruction::decode_opcode(bus, opcode, location);
+
+        self.current_op = opcode;
         self.count += 1;
 
-        println!(
-            "[{}] ({:#06x}) {:#04x} AF={:#06x} BC={:#06x} DE={:#06x} HL={:#06x} SP={:#06x}",
-            self.count,
-            self.program_counter,
-            self.current_op,
-            self.registers.get16(CombinedRegister::AF),
-            self.registers.get16(CombinedRegister::BC),
-            self.registers.get16(CombinedRegister::DE),
-            self.registers.get16(CombinedRegister::HL),
-            self.stack_pointer,
-        );
+        if self.trace {
+            println!(
+                "[{}] ({:#06x}) {:<20} AF={:#06x} BC={:#06x} DE={:#06x} HL={:#06x} SP={:#06x}",
+                self.count,
+                location,
+                instruction.to_string(),
+                self.registers.get16(CombinedRegister::AF),
+                self.registers.get16(CombinedRegister::BC),
+                self.registers.get16(CombinedRegister::DE),
+                self.registers.get16(CombinedRegister::HL),
+                self.stack_pointer,
+            );
+        }
+
+        // PC already points past the instruction while it runs, the same as
+        // on hardware, so relative jumps and calls work from there
+        self.program_counter = (location + instruction.length) & 0xffff;
+        self.branched = false;
+
+        // One M-cycle for each byte fetched, including the opcode itself.
+        // STOP skips its padding byte without spending a cycle on it.
+        let fetch_cycles = (instruction.length as u32 * 4).min(instruction.cycles);
+        self.idle_until(bus, fetch_cycles);
+
+        self.apply(bus, instruction.operation);
+
+        if self.locked {
+            return Err(EmulatorError::IllegalInstruction { opcode, location });
+        }
+
+        if self.branched {
+            self.idle_until(bus, instruction.cycles + instruction.branch_cycles);
+        } else {
+            self.idle_until(bus, instruction.cycles);
+        }
+
+        Ok(())
+    }
+
+    fn apply<B: Bus>(&mut self, bus: &mut B, operation: Operation) {
+        match operation {
+            Operation::Nop => self.nop(),
+            Operation::Ccf => self.ccf(),
+            Operation::Daa => self.daa(),
+            Operation::Stop => self.stop(bus),
+            Operation::Halt => self.halt(bus),
+            Operation::Di => self.di(),
+            Operation::Ei => self.ei(),
+            Operation::Ret => self.ret(bus),
+            Operation::RetF(flag, truthy) => self.ret_f(bus, flag, truthy),
+            Operation::Reti => self.reti(bus),
+            Operation::Rst(address) => self.rst(bus, address),
+            Operation::LdRR(to, from) => self.ld_r_r(to, from),
+            Operation::LdRD8(register, value) => self.ld_r_d8(register, value),
+            Operation::LdRrD16(register, value) => self.ld_rr_d16(register, value),
+            Operation::LdMemRrR(to_address, from) => self.ld_mem_rr_r(bus, to_address, from),
+            Operation::LdiMemRrR(to_address, from) => self.ldi_mem_rr_r(bus, to_address, from),
+            Operation::LddMemRrR(to_address, from) => self.ldd_mem_rr_r(bus, to_address, from),
+            Operation::LdRMemRr(to, from_address) => self.ld_r_mem_rr(bus, to, from_address),
+            Operation::LdiRMemRr(to, from_address) => self.ldi_r_mem_rr(bus, to, from_address),
+            Operation::LddRMemRr(to, from_address) => self.ldd_r_mem_rr(bus, to, from_address),
+            Operation::LdMemRrD8(address, value) => self.ld_mem_rr_d8(bus, address, value),
+            Operation::LdMemA8A(offset) => self.ld_mem_a8_a(bus, offset),
+            Operation::LdAMemA8(offset) => self.ld_a_mem_a8(bus, offset),
+            Operation::LdMemA16A(location) => self.ld_mem_a16_a(bus, location),
+            Operation::LdAMemA16(location) => self.ld_a_mem_a16(bus, location),
+            Operation::LdMemRA(register) => self.ld_mem_r_a(bus, register),
+            Operation::LdAMemR(register) => self.ld_a_mem_r(bus, register),
+            Operation::LdSpD16(value) => self.ld_sp_d16(value),
+            Operation::LdMemA16Sp(location) => self.ld_mem_a16_sp(bus, location),
+            Operation::LdSpRr(register) => self.ld_sp_rr(register),
+            Operation::LdRrSpS8(register, offset) => self.ld_rr_sp_s8(register, offset),
+            Operation::AddR(register) => self.add_r(register),
+            Operation::AddD8(value) => self.add_d8(value),
+            Operation::AddMemRr(register) => self.add_mem_rr(bus, register),
+            Operation::AddRr(register) => self.add_rr(register),
+            Operation::AddSp => self.add_sp(),
+            Operation::AddSpS8(offset) => self.add_sp_s8(offset),
+            Operation::AdcR(register) => self.adc_r(register),
+            Operation::AdcD8(value) => self.adc_d8(value),
+            Operation::AdcMemRr(register) => self.adc_mem_rr(bus, register),
+            Operation::SubR(register) => self.sub_r(register),
+            Operation::SubD8(value) => self.sub_d8(value),
+            Operation::SubMemRr(register) => self.sub_mem_rr(bus, register),
+            Operation::SbcR(register) => self.sbc_r(register),
+            Operation::SbcD8(value) => self.sbc_d8(value),
+            Operation::SbcMemRr(register) => self.sbc_mem_rr(bus, register),
+            Operation::AndR(register) => self.and_r(register),
+            Operation::AndD8(value) => self.and_d8(value),
+            Operation::AndMemRr(register) => self.and_mem_rr(bus, register),
+            Operation::XorR(register) => self.xor_r(register),
+            Operation::XorD8(value) => self.xor_d8(value),
+            Operation::XorMemRr(register) => self.xor_mem_rr(bus, register),
+            Operation::OrR(register) => self.or_r(register),
+            Operation::OrD8(value) => self.or_d8(value),
+            Operation::OrMemRr(register) => self.or_mem_rr(bus, register),
+            Operation::CpR(register) => self.cp_r(register),
+            Operation::CpD8(value) => self.cp_d8(value),
+            Operation::CpMemRr(register) => self.cp_mem_rr(bus, register),
+            Operation::IncR(register) => self.inc_r(register),
+            Operation::IncMemRr(register) => self.inc_mem_rr(bus, register),
+            Operation::IncRr(register) => self.inc_rr(register),
+            Operation::IncSp => self.inc_sp(),
+            Operation::DecR(register) => self.dec_r(register),
+            Operation::DecMemRr(register) => self.dec_mem_rr(bus, register),
+            Operation::DecRr(register) => self.dec_rr(register),
+            Operation::DecSp => self.dec_sp(),
+            Operation::PushRr(register) => self.push_rr(bus, register),
+            Operation::PopRr(register) => self.pop_rr(bus, register),
+            Operation::Jp(flag, truthy, location) => self.jp(flag, truthy, location),
+            Operation::Jr(flag, truthy, offset) => self.jr(flag, truthy, offset),
+            Operation::JpRr(register) => self.jp_rr(register),
+            Operation::Call(location) => self.call(bus, location),
+            Operation::CallFA16(flag, truthy, location) => {
+                self.call_f_a16(bus, flag, truthy, location)
+            }
+            Operation::Scf => self.scf(),
+            Operation::Cpl => self.cpl(),
+            Operation::Rlca => self.rlca(),
+            Operation::Rla => self.rla(),
+            Operation::Rrca => self.rrca(),
+            Operation::Rra => self.rra(),
+            Operation::RlcR(register) => self.rlc_r(register),
+            Operation::RlcMemRr(register) => self.rlc_mem_rr(bus, register),
+            Operation::RlR(register) => self.rl_r(register),
+            Operation::RlMemRr(register) => self.rl_mem_rr(bus, register),
+            Operation::RrcR(register) => self.rrc_r(register),
+            Operation::RrcMemRr(register) => self.rrc_mem_rr(bus, register),
+            Operation::RrR(register) => self.rr_r(register),
+            Operation::RrMemRr(register) => self.rr_mem_rr(bus, register),
+            Operation::SlaR(register) => self.sla_r(register),
+            Operation::SlaMemRr(register) => self.sla_mem_rr(bus, register),
+            Operation::SraR(register) => self.sra_r(register),
+            Operation::SraMemRr(register) => self.sra_mem_rr(bus, register),
+            Operation::SwapR(register) => self.swap_r(register),
+            Operation::SwapMemRr(register) => self.swap_mem_rr(bus, register),
+            Operation::SrlR(register) => self.srl_r(register),
+            Operation::SrlMemRr(register) => self.srl_mem_rr(bus, register),
+            Operation::BitR(bit, register) => self.bit_r(bit, register),
+            Operation::BitMemRr(bit, register) => self.bit_mem_rr(bus, bit, register),
+            Operation::ResR(bit, register) => self.res_r(bit, register),
+            Operation::ResMemRr(bit, register) => self.res_mem_rr(bus, bit, register),
+            Operation::SetR(bit, register) => self.set_r(bit, register),
+            Operation::SetMemRr(bit, register) => self.set_mem_rr(bus, bit, register),
+            Operation::Illegal => self.lock(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use crate::bus::{Bus, FlatMemory};
+    use crate::cartridge::Cartridge;
+    use crate::cpu::Cpu;
+    use crate::cpu_registers::{CombinedRegister, GeneralRegister};
+    use crate::error::EmulatorError;
+    use crate::flag_register::FlagRegisterValue;
+    use crate::interrupts::Interrupt;
+    use crate::joypad::Button;
+    use crate::mbc::MBC;
+
+    fn get_mock_mbc(program: &[u8]) -> MBC {
+        let mut data = vec![0x00; 0x8000];
+        data[..program.len()].copy_from_slice(program);
+
+        MBC::try_from(Cartridge::try_from(data).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn daa_after_addition() {
+        // 0x45 + 0x38 = 0x7d, which should read as BCD 83
+        let mut mbc = get_mock_mbc(&[0x80, 0x27]);
+        let mut cpu = Cpu::default();
+
+        cpu.registers
+            .set(GeneralRegister::A, 0x45)
+            .set(GeneralRegister::B, 0x38);
+
+        cpu.step(&mut mbc).unwrap();
+        cpu.step(&mut mbc).unwrap();
+
+        assert_eq!(cpu.registers.get(GeneralRegister::A), 0x83);
+        assert!(!cpu.registers.is_flag_set(FlagRegisterValue::CARRY));
+    }
+
+    #[test]
+    fn daa_after_addition_carries() {
+        // 0x99 + 0x01 = 0x9a, which wraps to BCD 00 with a carry
+        let mut mbc = get_mock_mbc(&[0x80, 0x27]);
+        let mut cpu = Cpu::default();
+
+        cpu.registers
+            .set(GeneralRegister::A, 0x99)
+            .set(GeneralRegister::B, 0x01);
+
+        cpu.step(&mut mbc).unwrap();
+        cpu.step(&mut mbc).unwrap();
+
+        assert_eq!(cpu.registers.get(GeneralRegister::A), 0x00);
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::ZERO));
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::CARRY));
+    }
+
+    #[test]
+    fn daa_after_subtraction() {
+        // 0x42 - 0x09 = 0x39, which should read as BCD 33
+        let mut mbc = get_mock_mbc(&[0x90, 0x27]);
+        let mut cpu = Cpu::default();
+
+        cpu.registers
+            .set(GeneralRegister::A, 0x42)
+            .set(GeneralRegister::B, 0x09);
+
+        cpu.step(&mut mbc).unwrap();
+        cpu.step(&mut mbc).unwrap();
+
+        assert_eq!(cpu.registers.get(GeneralRegister::A), 0x33);
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::NEGATIVE));
+    }
+
+    #[test]
+    fn rst_pushes_return_address() {
+        let mut mbc = get_mock_mbc(&[0x00, 0x00, 0x00, 0xef]);
+        let mut cpu = Cpu {
+            program_counter: 0x0003,
+            stack_pointer: 0xfffe,
+            ..Cpu::default()
+        };
+
+        cpu.step(&mut mbc).unwrap();
+
+        assert_eq!(cpu.program_counter, 0x0028);
+        assert_eq!(cpu.stack_pointer, 0xfffc);
+        assert_eq!(mbc.read(0xfffc), 0x04);
+        assert_eq!(mbc.read(0xfffd), 0x00);
+    }
+
+    #[test]
+    fn jp_hl() {
+        let mut mbc = get_mock_mbc(&[0xe9]);
+        let mut cpu = Cpu::default();
+
+        cpu.registers.set16(CombinedRegister::HL, 0x1234);
+        cpu.step(&mut mbc).unwrap();
+
+        assert_eq!(cpu.program_counter, 0x1234);
+    }
+
+    #[test]
+    fn add_sp_negative_offset_flags() {
+        // -1 is 0xff, so the low byte add of 0x01 + 0xff carries out of both
+        // nibbles even though SP goes down
+        let mut mbc = get_mock_mbc(&[0xe8, 0xff]);
+        let mut cpu = Cpu {
+            stack_pointer: 0x0001,
+            ..Cpu::default()
+        };
+
+        cpu.step(&mut mbc).unwrap();
+
+        assert_eq!(cpu.stack_pointer, 0x0000);
+        assert_eq!(cpu.program_counter, 0x0002);
+        assert!(!cpu.registers.is_flag_set(FlagRegisterValue::ZERO));
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::HALF_CARRY));
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::CARRY));
+    }
+
+    #[test]
+    fn and_stores_result_in_a() {
+        let mut memory = FlatMemory::with_program(&[0xe6, 0x0f]);
+        let mut cpu = Cpu::default();
+        cpu.registers.set(GeneralRegister::A, 0x3c);
+
+        cpu.step(&mut memory).unwrap();
+
+        assert_eq!(cpu.registers.get(GeneralRegister::A), 0x0c);
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::HALF_CARRY));
+    }
+
+    #[test]
+    fn adc_carries_out_of_incoming_carry() {
+        // 0x00 + 0xff + carry wraps to zero, and has to carry out doing so
+        let mut memory = FlatMemory::with_program(&[0xce, 0xff]);
+        let mut cpu = Cpu::default();
+        cpu.registers.set_flag(FlagRegisterValue::CARRY);
+
+        cpu.step(&mut memory).unwrap();
+
+        assert_eq!(cpu.registers.get(GeneralRegister::A), 0x00);
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::ZERO));
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::HALF_CARRY));
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::CARRY));
+    }
+
+    #[test]
+    fn sbc_borrows_through_incoming_carry() {
+        let mut memory = FlatMemory::with_program(&[0xde, 0xff]);
+        let mut cpu = Cpu::default();
+        cpu.registers
+            .set(GeneralRegister::A, 0x10)
+            .set_flag(FlagRegisterValue::CARRY);
+
+        cpu.step(&mut memory).unwrap();
+
+        assert_eq!(cpu.registers.get(GeneralRegister::A), 0x10);
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::NEGATIVE));
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::HALF_CARRY));
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::CARRY));
+    }
+
+    #[test]
+    fn ld_hl_sp_plus_offset() {
+        let mut mbc = get_mock_mbc(&[0xf8, 0x02]);
+        let mut cpu = Cpu {
+            stack_pointer: 0xfff8,
+            ..Cpu::default()
+        };
+
+        cpu.step(&mut mbc).unwrap();
+
+        assert_eq!(cpu.registers.get16(CombinedRegister::HL), 0xfffa);
+        assert_eq!(cpu.stack_pointer, 0xfff8);
+        assert!(!cpu.registers.is_flag_set(FlagRegisterValue::HALF_CARRY));
+        assert!(!cpu.registers.is_flag_set(FlagRegisterValue::CARRY));
+    }
+
+    #[test]
+    fn halt_waits_for_pending_interrupt() {
+        let mut mbc = get_mock_mbc(&[0x76, 0x00]);
+        let mut cpu = Cpu::default();
+
+        cpu.step(&mut mbc).unwrap();
+        cpu.step(&mut mbc).unwrap();
+
+        assert!(cpu.halted);
+        assert_eq!(cpu.program_counter, 0x0001);
+
+        mbc.write(0xffff, 0x01);
+        mbc.write(0xff0f, 0x01);
+        cpu.step(&mut mbc).unwrap();
+
+        assert!(!cpu.halted);
+        assert_eq!(cpu.program_counter, 0x0002);
+    }
+
+    #[test]
+    fn swap_register() {
+        let mut mbc = get_mock_mbc(&[0xcb, 0x37]);
+        let mut cpu = Cpu::default();
+
+        cpu.registers.set(GeneralRegister::A, 0xf1);
+        cpu.step(&mut mbc).unwrap();
+
+        assert_eq!(cpu.registers.get(GeneralRegister::A), 0x1f);
+        assert_eq!(cpu.program_counter, 0x0002);
+        assert!(!cpu.registers.is_flag_set(FlagRegisterValue::ZERO));
+        assert!(!cpu.registers.is_flag_set(FlagRegisterValue::CARRY));
+    }
+
+    #[test]
+    fn sla_shifts_into_carry() {
+        let mut mbc = get_mock_mbc(&[0xcb, 0x20]);
+        let mut cpu = Cpu::default();
+
+        cpu.registers.set(GeneralRegister::B, 0x80);
+        cpu.step(&mut mbc).unwrap();
+
+        assert_eq!(cpu.registers.get(GeneralRegister::B), 0x00);
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::ZERO));
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::CARRY));
+    }
+
+    #[test]
+    fn sra_keeps_sign_bit() {
+        let mut mbc = get_mock_mbc(&[0xcb, 0x29]);
+        let mut cpu = Cpu::default();
+
+        cpu.registers.set(GeneralRegister::C, 0x81);
+        cpu.step(&mut mbc).unwrap();
+
+        assert_eq!(cpu.registers.get(GeneralRegister::C), 0xc0);
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::CARRY));
+    }
+
+    #[test]
+    fn srl_clears_top_bit() {
+        let mut mbc = get_mock_mbc(&[0xcb, 0x3f]);
+        let mut cpu = Cpu::default();
+
+        cpu.registers.set(GeneralRegister::A, 0x81);
+        cpu.step(&mut mbc).unwrap();
+
+        assert_eq!(cpu.registers.get(GeneralRegister::A), 0x40);
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::CARRY));
+    }
+
+    #[test]
+    fn rr_rotates_through_carry() {
+        let mut mbc = get_mock_mbc(&[0xcb, 0x1a]);
+        let mut cpu = Cpu::default();
+
+        cpu.registers.set(GeneralRegister::D, 0x01);
+        cpu.step(&mut mbc).unwrap();
+
+        assert_eq!(cpu.registers.get(GeneralRegister::D), 0x00);
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::ZERO));
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::CARRY));
+    }
+
+    #[test]
+    fn set_and_res_memory() {
+        let mut mbc = get_mock_mbc(&[0xcb, 0xfe, 0xcb, 0x86]);
+        let mut cpu = Cpu::default();
+
+        cpu.registers.set16(CombinedRegister::HL, 0xc000);
+        mbc.write(0xc000, 0x01);
+
+        cpu.step(&mut mbc).unwrap();
+        assert_eq!(mbc.read(0xc000), 0x81);
+
+        cpu.step(&mut mbc).unwrap();
+        assert_eq!(mbc.read(0xc000), 0x80);
+        assert_eq!(cpu.program_counter, 0x0004);
+    }
+
+    #[test]
+    fn res_register() {
+        let mut mbc = get_mock_mbc(&[0xcb, 0xbf]);
+        let mut cpu = Cpu::default();
+
+        cpu.registers.set(GeneralRegister::A, 0xff);
+        cpu.step(&mut mbc).unwrap();
+
+        assert_eq!(cpu.registers.get(GeneralRegister::A), 0x7f);
+    }
+
+    #[test]
+    fn step_returns_cycles() {
+        let mut mbc = get_mock_mbc(&[0x00, 0x01, 0x34, 0x12, 0x08, 0x00, 0xc0]);
+        let mut cpu = Cpu::default();
+
+        assert_eq!(cpu.step(&mut mbc).unwrap(), 4);
+        assert_eq!(cpu.step(&mut mbc).unwrap(), 12);
+        assert_eq!(cpu.program_counter, 0x0004);
+        assert_eq!(cpu.step(&mut mbc).unwrap(), 20);
+        assert_eq!(cpu.program_counter, 0x0007);
+    }
+
+    #[test]
+    fn conditional_jump_cycles() {
+        // JR NZ, -2 spins on itself while Z is clear
+        let mut mbc = get_mock_mbc(&[0x20, 0xfe]);
+        let mut cpu = Cpu::default();
+
+        assert_eq!(cpu.step(&mut mbc).unwrap(), 12);
+        assert_eq!(cpu.program_counter, 0x0000);
+
+        cpu.registers.set_flag(FlagRegisterValue::ZERO);
+
+        assert_eq!(cpu.step(&mut mbc).unwrap(), 8);
+        assert_eq!(cpu.program_counter, 0x0002);
+    }
+
+    #[test]
+    fn conditional_call_and_return_cycles() {
+        let mut program = vec![0xc4, 0x10, 0x00, 0xcc, 0x10, 0x00];
+        program.resize(0x10, 0x00);
+        program.extend_from_slice(&[0xc8, 0xc0]);
+
+        let mut mbc = get_mock_mbc(&program);
+        let mut cpu = Cpu {
+            stack_pointer: 0xfffe,
+            ..Cpu::default()
+        };
+
+        assert_eq!(cpu.step(&mut mbc).unwrap(), 24);
+        assert_eq!(cpu.program_counter, 0x0010);
+        assert_eq!(cpu.step(&mut mbc).unwrap(), 8);
+        assert_eq!(cpu.step(&mut mbc).unwrap(), 20);
+        assert_eq!(cpu.program_counter, 0x0003);
+        assert_eq!(cpu.step(&mut mbc).unwrap(), 12);
+        assert_eq!(cpu.program_counter, 0x0006);
+    }
+
+    #[test]
+    fn prefix_cycles() {
+        let mut mbc = get_mock_mbc(&[0xcb, 0x11, 0xcb, 0x46, 0xcb, 0x06]);
+        let mut cpu = Cpu::default();
+
+        cpu.registers.set16(CombinedRegister::HL, 0xc000);
+
+        assert_eq!(cpu.step(&mut mbc).unwrap(), 8);
+        assert_eq!(cpu.step(&mut mbc).unwrap(), 12);
+        assert_eq!(cpu.step(&mut mbc).unwrap(), 16);
+        assert_eq!(cpu.program_counter, 0x0006);
+    }
+
+    #[test]
+    fn interrupt_dispatch() {
+        let mut mbc = get_mock_mbc(&[0x00]);
+        let mut cpu = Cpu {
+            program_counter: 0x0150,
+            stack_pointer: 0xfffe,
+            interrupts_enabled: true,
+            ..Cpu::default()
+        };
+
+        mbc.write(0xffff, 0x1f);
+        mbc.request_interrupt(Interrupt::TIMER | Interrupt::JOYPAD);
+
+        assert_eq!(cpu.step(&mut mbc).unwrap(), 20);
+        assert_eq!(cpu.program_counter, 0x0050);
+        assert!(!cpu.interrupts_enabled);
+        assert_eq!(mbc.read(0xff0f) & 0x1f, Interrupt::JOYPAD.bits());
+        assert_eq!(mbc.read(0xfffc), 0x50);
+        assert_eq!(mbc.read(0xfffd), 0x01);
+    }
+
+    #[test]
+    fn interrupt_not_enabled_is_ignored() {
+        let mut mbc = get_mock_mbc(&[0x00]);
+        let mut cpu = Cpu {
+            interrupts_enabled: true,
+            ..Cpu::default()
+        };
+
+        mbc.write(0xffff, Interrupt::VBLANK.bits());
+        mbc.request_interrupt(Interrupt::TIMER);
+
+        assert_eq!(cpu.step(&mut mbc).unwrap(), 4);
+        assert_eq!(cpu.program_counter, 0x0001);
+    }
+
+    #[test]
+    fn ei_is_delayed_by_one_instruction() {
+        let mut mbc = get_mock_mbc(&[0xfb, 0x00, 0x00]);
+        let mut cpu = Cpu {
+            stack_pointer: 0xfffe,
+            ..Cpu::default()
+        };
+
+        mbc.write(0xffff, 0x01);
+        mbc.request_interrupt(Interrupt::VBLANK);
+
+        cpu.step(&mut mbc).unwrap();
+        assert!(!cpu.interrupts_enabled);
+
+        cpu.step(&mut mbc).unwrap();
+        assert!(cpu.interrupts_enabled);
+        assert_eq!(cpu.program_counter, 0x0002);
+
+        cpu.step(&mut mbc).unwrap();
+        assert_eq!(cpu.program_counter, 0x0040);
+    }
+
+    #[test]
+    fn di_cancels_pending_ei() {
+        let mut mbc = get_mock_mbc(&[0xfb, 0xf3, 0x00]);
+        let mut cpu = Cpu::default();
+
+        cpu.step(&mut mbc).unwrap();
+        cpu.step(&mut mbc).unwrap();
+        cpu.step(&mut mbc).unwrap();
+
+        assert!(!cpu.interrupts_enabled);
+    }
+
+    #[test]
+    fn reti_enables_interrupts_immediately() {
+        let mut mbc = get_mock_mbc(&[0xd9]);
+        let mut cpu = Cpu {
+            stack_pointer: 0xfffc,
+            ..Cpu::default()
+        };
+
+        mbc.write(0xfffc, 0x34);
+        mbc.write(0xfffd, 0x12);
+        cpu.step(&mut mbc).unwrap();
+
+        assert!(cpu.interrupts_enabled);
+        assert_eq!(cpu.program_counter, 0x1234);
+    }
+
+    #[test]
+    fn halt_wakes_into_interrupt_handler() {
+        let mut mbc = get_mock_mbc(&[0x76, 0x00]);
+        let mut cpu = Cpu {
+            stack_pointer: 0xfffe,
+            interrupts_enabled: true,
+            ..Cpu::default()
+        };
+
+        mbc.write(0xffff, 0x04);
+        cpu.step(&mut mbc).unwrap();
+        assert!(cpu.halted);
+
+        mbc.request_interrupt(Interrupt::TIMER);
 
-        let op = self.current_op;
-
-        match op {
-            0x00 => self.nop(),
-            0x01 => self.ld_rr_d16(mbc, CombinedRegister::BC),
-            0x02 => self.ld_mem_rr_r(mbc, CombinedRegister::BC, GeneralRegister::A),
-            0x03 => self.inc_rr(CombinedRegister::BC),
-            0x04 => self.inc_r(GeneralRegister::B),
-            0x05 => self.dec_r(GeneralRegister::B),
-            0x06 => self.ld_r_d8(mbc, GeneralRegister::B),
-            0x07 => self.rlca(),
-            0x08 => self.ld_mem_a16_sp(mbc),
-            0x09 => self.add_rr(CombinedRegister::BC),
-            0x0A => self.ld_r_mem_rr(mbc, GeneralRegister::A, CombinedRegister::BC),
-            0x0B => self.dec_rr(CombinedRegister::BC),
-            0x0C => self.inc_r(GeneralRegister::C),
-            0x0D => self.dec_r(GeneralRegister::C),
-            0x0E => self.ld_r_d8(mbc, GeneralRegister::C),
-            0x0F => self.rrca(),
-
-            0x10 => self.stop(),
-            0x11 => self.ld_rr_d16(mbc, CombinedRegister::DE),
-            0x12 => self.ld_mem_rr_r(mbc, CombinedRegister::DE, GeneralRegister::A),
-            0x13 => self.inc_rr(CombinedRegister::DE),
-            0x14 => self.inc_r(GeneralRegister::D),
-            0x15 => self.dec_r(GeneralRegister::D),
-            0x16 => self.ld_r_d8(mbc, GeneralRegister::D),
-            0x17 => self.rla(),
-            0x18 => self.jr(mbc, None, false),
-            0x19 => self.add_rr(CombinedRegister::DE),
-            0x1A => self.ld_r_mem_rr(mbc, GeneralRegister::A, CombinedRegister::DE),
-            0x1B => self.dec_rr(CombinedRegister::DE),
-            0x1C => self.inc_r(GeneralRegister::E),
-            0x1D => self.dec_r(GeneralRegister::E),
-            0x1E => self.ld_r_d8(mbc, GeneralRegister::E),
-            0x1F => self.rra(),
-
-            0x20 => self.jr(mbc, Some(FlagRegisterValue::ZERO), false),
-            0x21 => self.ld_rr_d16(mbc, CombinedRegister::HL),
-            0x22 => self.ldi_mem_rr_r(mbc, CombinedRegister::HL, GeneralRegister::A),
-            0x23 => self.inc_rr(CombinedRegister::HL),
-            0x24 => self.inc_r(GeneralRegister::H),
-            0x25 => self.dec_r(GeneralRegister::H),
-            0x26 => self.ld_r_d8(mbc, GeneralRegister::H),
-            0x27 => self.not_implemented("DAA"),
-            0x28 => self.jr(mbc, Some(FlagRegisterValue::ZERO), true),
-            0x29 => self.add_rr(CombinedRegister::HL),
-            0x2A => self.ldi_r_mem_rr(mbc, GeneralRegister::A, CombinedRegister::HL),
-            0x2B => self.dec_rr(CombinedRegister::HL),
-            0x2C => self.inc_r(GeneralRegister::L),
-            0x2D => self.dec_r(GeneralRegister::L),
-            0x2E => self.ld_r_d8(mbc, GeneralRegister::L),
-            0x2F => self.cpl(),
-
-            0x30 => self.jr(mbc, Some(FlagRegisterValue::CARRY), false),
-            0x31 => self.ld_sp_d16(mbc),
-            0x32 => self.ldd_mem_rr_r(mbc, CombinedRegister::HL, GeneralRegister::A),
-            0x33 => self.inc_sp(),
-            0x34 => self.inc_mem_rr(mbc, CombinedRegister::HL),
-            0x35 => self.inc_mem_rr(mbc, CombinedRegister::HL),
-            0x36 => self.ld_mem_rr_d8(mbc, CombinedRegister::HL),
-            0x37 => self.scf(),
-            0x38 => self.jr(mbc, Some(FlagRegisterValue::CARRY), true),
-            0x39 => self.add_sp(),
-            0x3A => self.ldd_r_mem_rr(mbc, GeneralRegister::A, CombinedRegister::HL),
-            0x3B => self.dec_sp(),
-            0x3C => self.inc_r(GeneralRegister::A),
-            0x3D => self.dec_r(GeneralRegister::A),
-            0x3E => self.ld_r_d8(mbc, GeneralRegister::A),
-            0x3F => self.ccf(),
-
-            0x40 => self.ld_r_r(GeneralRegister::B, GeneralRegister::B),
-            0x41 => self.ld_r_r(GeneralRegister::B, GeneralRegister::C),
-            0x42 => self.ld_r_r(GeneralRegister::B, GeneralRegister::D),
-            0x43 => self.ld_r_r(GeneralRegister::B, GeneralRegister::E),
-            0x44 => self.ld_r_r(GeneralRegister::B, GeneralRegister::H),
-            0x45 => self.ld_r_r(GeneralRegister::B, GeneralRegister::L),
-            0x46 => self.ld_r_mem_rr(mbc, GeneralRegister::B, CombinedRegister::HL),
-            0x47 => self.ld_r_r(GeneralRegister::B, GeneralRegister::A),
-            0x48 => self.ld_r_r(GeneralRegister::C, GeneralRegister::B),
-            0x49 => self.ld_r_r(GeneralRegister::C, GeneralRegister::C),
-            0x4A => self.ld_r_r(GeneralRegister::C, GeneralRegister::D),
-            0x4B => self.ld_r_r(GeneralRegister::C, GeneralRegister::E),
-            0x4C => self.ld_r_r(GeneralRegister::C, GeneralRegister::H),
-            0x4D => self.ld_r_r(GeneralRegister::C, GeneralRegister::L),
-            0x4E => self.ld_r_mem_rr(mbc, GeneralRegister::C, CombinedRegister::HL),
-            0x4F => self.ld_r_r(GeneralRegister::C, GeneralRegister::A),
-
-            0x50 => self.ld_r_r(GeneralRegister::D, GeneralRegister::B),
-            0x51 => self.ld_r_r(GeneralRegister::D, GeneralRegister::C),
-            0x52 => self.ld_r_r(GeneralRegister::D, GeneralRegister::D),
-            0x53 => self.ld_r_r(GeneralRegister::D, GeneralRegister::E),
-            0x54 => self.ld_r_r(GeneralRegister::D, GeneralRegister::H),
-            0x55 => self.ld_r_r(GeneralRegister::D, GeneralRegister::L),
-            0x56 => self.ld_r_mem_rr(mbc, GeneralRegister::D, CombinedRegister::HL),
-            0x57 => self.ld_r_r(GeneralRegister::D, GeneralRegister::A),
-            0x58 => self.ld_r_r(GeneralRegister::E, GeneralRegister::B),
-            0x59 => self.ld_r_r(GeneralRegister::E, GeneralRegister::C),
-            0x5A => self.ld_r_r(GeneralRegister::E, GeneralRegister::D),
-            0x5B => self.ld_r_r(GeneralRegister::E, GeneralRegister::E),
-            0x5C => self.ld_r_r(GeneralRegister::E, GeneralRegister::H),
-            0x5D => self.ld_r_r(GeneralRegister::E, GeneralRegister::L),
-            0x5E => self.ld_r_mem_rr(mbc, GeneralRegister::E, CombinedRegister::HL),
-            0x5F => self.ld_r_r(GeneralRegister::E, GeneralRegister::A),
-
-            0x60 => self.ld_r_r(GeneralRegister::H, GeneralRegister::B),
-            0x61 => self.ld_r_r(GeneralRegister::H, GeneralRegister::C),
-            0x62 => self.ld_r_r(GeneralRegister::H, GeneralRegister::D),
-            0x63 => self.ld_r_r(GeneralRegister::H, GeneralRegister::E),
-            0x64 => self.ld_r_r(GeneralRegister::H, GeneralRegister::H),
-            0x65 => self.ld_r_r(GeneralRegister::H, GeneralRegister::L),
-            0x66 => self.ld_r_mem_rr(mbc, GeneralRegister::H, CombinedRegister::HL),
-            0x67 => self.ld_r_r(GeneralRegister::H, GeneralRegister::A),
-            0x68 => self.ld_r_r(GeneralRegister::L, GeneralRegister::B),
-            0x69 => self.ld_r_r(GeneralRegister::L, GeneralRegister::C),
-            0x6A => self.ld_r_r(GeneralRegister::L, GeneralRegister::D),
-            0x6B => self.ld_r_r(GeneralRegister::L, GeneralRegister::E),
-            0x6C => self.ld_r_r(GeneralRegister::L, GeneralRegister::H),
-            0x6D => self.ld_r_r(GeneralRegister::L, GeneralRegister::L),
-            0x6E => self.ld_r_mem_rr(mbc, GeneralRegister::L, CombinedRegister::HL),
-            0x6F => self.ld_r_r(GeneralRegister::L, GeneralRegister::A),
-
-            0x70 => self.ld_mem_rr_r(mbc, CombinedRegister::HL, GeneralRegister::B),
-            0x71 => self.ld_mem_rr_r(mbc, CombinedRegister::HL, GeneralRegister::C),
-            0x72 => self.ld_mem_rr_r(mbc, CombinedRegister::HL, GeneralRegister::D),
-            0x73 => self.ld_mem_rr_r(mbc, CombinedRegister::HL, GeneralRegister::E),
-            0x74 => self.ld_mem_rr_r(mbc, CombinedRegister::HL, GeneralRegister::H),
-            0x75 => self.ld_mem_rr_r(mbc, CombinedRegister::HL, GeneralRegister::L),
-            0x76 => self.not_implemented("HALT"),
-            0x77 => self.ld_mem_rr_r(mbc, CombinedRegister::HL, GeneralRegister::A),
-            0x78 => self.ld_r_r(GeneralRegister::A, GeneralRegister::B),
-            0x79 => self.ld_r_r(GeneralRegister::A, GeneralRegister::C),
-            0x7A => self.ld_r_r(GeneralRegister::A, GeneralRegister::D),
-            0x7B => self.ld_r_r(GeneralRegister::A, GeneralRegister::E),
-            0x7C => self.ld_r_r(GeneralRegister::A, GeneralRegister::H),
-            0x7D => self.ld_r_r(GeneralRegister::A, GeneralRegister::L),
-            0x7E => self.ld_r_mem_rr(mbc, GeneralRegister::A, CombinedRegister::HL),
-            0x7F => self.ld_r_r(GeneralRegister::A, GeneralRegister::A),
-
-            0x80 => self.add_r(GeneralRegister::B),
-            0x81 => self.add_r(GeneralRegister::C),
-            0x82 => self.add_r(GeneralRegister::D),
-            0x83 => self.add_r(GeneralRegister::E),
-            0x84 => self.add_r(GeneralRegister::H),
-            0x85 => self.add_r(GeneralRegister::L),
-            0x86 => self.add_mem_rr(mbc, CombinedRegister::HL),
-            0x87 => self.add_r(GeneralRegister::A),
-            0x88 => self.adc_r(GeneralRegister::B),
-            0x89 => self.adc_r(GeneralRegister::C),
-            0x8A => self.adc_r(GeneralRegister::D),
-            0x8B => self.adc_r(GeneralRegister::E),
-            0x8C => self.adc_r(GeneralRegister::H),
-            0x8D => self.adc_r(GeneralRegister::L),
-            0x8E => self.adc_mem_rr(mbc, CombinedRegister::HL),
-            0x8F => self.adc_r(GeneralRegister::A),
-
-            0x90 => self.sub_r(GeneralRegister::B),
-            0x91 => self.sub_r(GeneralRegister::C),
-            0x92 => self.sub_r(GeneralRegister::D),
-            0x93 => self.sub_r(GeneralRegister::E),
-            0x94 => self.sub_r(GeneralRegister::H),
-            0x95 => self.sub_r(GeneralRegister::L),
-            0x96 => self.sub_mem_rr(mbc, CombinedRegister::HL),
-            0x97 => self.sub_r(GeneralRegister::A),
-            0x98 => self.sbc_r(GeneralRegister::B),
-            0x99 => self.sbc_r(GeneralRegister::C),
-            0x9A => self.sbc_r(GeneralRegister::D),
-            0x9B => self.sbc_r(GeneralRegister::E),
-            0x9C => self.sbc_r(GeneralRegister::H),
-            0x9D => self.sbc_r(GeneralRegister::L),
-            0x9E => self.sbc_mem_rr(mbc, CombinedRegister::HL),
-            0x9F => self.sbc_r(GeneralRegister::A),
-
-            0xA0 => self.and_r(GeneralRegister::B),
-            0xA1 => self.and_r(GeneralRegister::C),
-            0xA2 => self.and_r(GeneralRegister::D),
-            0xA3 => self.and_r(GeneralRegister::E),
-            0xA4 => self.and_r(GeneralRegister::H),
-            0xA5 => self.and_r(GeneralRegister::L),
-            0xA6 => self.and_mem_rr(mbc, CombinedRegister::HL),
-            0xA7 => self.and_r(GeneralRegister::A),
-            0xA8 => self.xor_r(GeneralRegister::B),
-            0xA9 => self.xor_r(GeneralRegister::C),
-            0xAA => self.xor_r(GeneralRegister::D),
-            0xAB => self.xor_r(GeneralRegister::E),
-            0xAC => self.xor_r(GeneralRegister::H),
-            0xAD => self.xor_r(GeneralRegister::L),
-            0xAE => self.xor_mem_rr(mbc, CombinedRegister::HL),
-            0xAF => self.xor_r(GeneralRegister::A),
-
-            0xB0 => self.or_r(GeneralRegister::B),
-            0xB1 => self.or_r(GeneralRegister::C),
-            0xB2 => self.or_r(GeneralRegister::D),
-            0xB3 => self.or_r(GeneralRegister::E),
-            0xB4 => self.or_r(GeneralRegister::H),
-            0xB5 => self.or_r(GeneralRegister::L),
-            0xB6 => self.or_mem_rr(mbc, CombinedRegister::HL),
-            0xB7 => self.or_r(GeneralRegister::A),
-            0xB8 => self.cp_r(GeneralRegister::B),
-            0xB9 => self.cp_r(GeneralRegister::C),
-            0xBA => self.cp_r(GeneralRegister::D),
-            0xBB => self.cp_r(GeneralRegister::E),
-            0xBC => self.cp_r(GeneralRegister::H),
-            0xBD => self.cp_r(GeneralRegister::L),
-            0xBE => self.cp_mem_rr(mbc, CombinedRegister::HL),
-            0xBF => self.cp_r(GeneralRegister::A),
-
-            0xC0 => self.ret_f(mbc, FlagRegisterValue::ZERO, false),
-            0xC1 => self.pop_rr(mbc, CombinedRegister::BC),
-            0xC2 => self.jp(mbc, Some(FlagRegisterValue::ZERO), false),
-            0xC3 => self.jp(mbc, None, false),
-            0xC4 => self.call_f_a16(mbc, FlagRegisterValue::ZERO, false),
-            0xC5 => self.push_rr(mbc, CombinedRegister::BC),
-            0xC6 => self.add_d8(mbc),
-            0xC7 => self.not_implemented("RST 00h"),
-            0xC8 => self.ret_f(mbc, FlagRegisterValue::ZERO, true),
-            0xC9 => self.ret(mbc),
-            0xCA => self.jp(mbc, Some(FlagRegisterValue::ZERO), true),
-            0xCB => self.prefix(mbc),
-            0xCC => self.call_f_a16(mbc, FlagRegisterValue::ZERO, true),
-            0xCD => self.call(mbc),
-            0xCE => self.adc_d8(mbc),
-            0xCF => self.not_implemented("RST 08h"),
-
-            0xD0 => self.ret_f(mbc, FlagRegisterValue::CARRY, false),
-            0xD1 => self.pop_rr(mbc, CombinedRegister::DE),
-            0xD2 => self.jp(mbc, Some(FlagRegisterValue::CARRY), false),
-            0xD3 => self.nothing(),
-            0xD4 => self.call_f_a16(mbc, FlagRegisterValue::CARRY, false),
-            0xD5 => self.push_rr(mbc, CombinedRegister::DE),
-            0xD6 => self.sub_d8(mbc),
-            0xD7 => self.not_implemented("RST 10h"),
-            0xD8 => self.ret_f(mbc, FlagRegisterValue::CARRY, true),
-            0xD9 => self.reti(mbc),
-            0xDA => self.jp(mbc, Some(FlagRegisterValue::CARRY), true),
-            0xDB => self.nothing(),
-            0xDC => self.call_f_a16(mbc, FlagRegisterValue::CARRY, true),
-            0xDD => self.nothing(),
-            0xDE => self.sbc_d8(mbc),
-            0xDF => self.not_implemented("RST 18h"),
-
-            0xE0 => self.ld_mem_a8_a(mbc),
-            0xE1 => self.pop_rr(mbc, CombinedRegister::HL),
-            0xE2 => self.ld_mem_r_a(mbc, GeneralRegister::C),
-            0xE3 => self.nothing(),
-            0xE4 => self.nothing(),
-            0xE5 => self.push_rr(mbc, CombinedRegister::HL),
-            0xE6 => self.and_d8(mbc),
-            0xE7 => self.not_implemented("RST 20h"),
-            0xE8 => self.not_implemented("ADD SP, i8"),
-            0xE9 => self.not_implemented("JP HL"),
-            0xEA => self.ld_mem_a16_a(mbc),
-            0xEB => self.nothing(),
-            0xEC => self.nothing(),
-            0xED => self.nothing(),
-            0xEE => self.xor_d8(mbc),
-            0xEF => self.not_implemented("RST 28h"),
-
-            0xF0 => self.ld_a_mem_a8(mbc),
-            0xF1 => self.pop_rr(mbc, CombinedRegister::AF),
-            0xF2 => self.ld_a_mem_r(mbc, GeneralRegister::C),
-            0xF3 => self.di(),
-            0xF4 => self.nothing(),
-            0xF5 => self.push_rr(mbc, CombinedRegister::AF),
-            0xF6 => self.or_d8(mbc),
-            0xF7 => self.not_implemented("RST 30h"),
-            0xF8 => self.not_implemented("LD HL, SP+i"),
-            0xF9 => self.ld_sp_rr(CombinedRegister::HL),
-            0xFA => self.ld_a_mem_a16(mbc),
-            0xFB => self.ei(),
-            0xFC => self.nothing(),
-            0xFD => self.nothing(),
-            0xFE => self.cp_d8(mbc),
-            0xFF => self.not_implemented("RST 38h"),
+        assert_eq!(cpu.step(&mut mbc).unwrap(), 20);
+        assert!(!cpu.halted);
+        assert_eq!(cpu.program_counter, 0x0050);
+        assert_eq!(mbc.read(0xfffc), 0x01);
+    }
+
+    #[test]
+    fn halt_bug_reads_next_byte_twice() {
+        // HALT, INC A with IME off and an interrupt already pending
+        let mut mbc = get_mock_mbc(&[0x76, 0x3c, 0x00]);
+        let mut cpu = Cpu::default();
+
+        mbc.write(0xffff, 0x01);
+        mbc.request_interrupt(Interrupt::VBLANK);
+
+        cpu.step(&mut mbc).unwrap();
+        assert!(!cpu.halted);
+
+        cpu.step(&mut mbc).unwrap();
+        cpu.step(&mut mbc).unwrap();
+
+        assert_eq!(cpu.registers.get(GeneralRegister::A), 0x02);
+        assert_eq!(cpu.program_counter, 0x0002);
+    }
+
+    #[test]
+    fn halt_bug_reads_opcode_as_operand() {
+        // HALT, LD A, d8 with the d8 taken from the opcode byte itself
+        let mut memory = FlatMemory::with_program(&[0x76, 0x3e, 0x05]);
+        let mut cpu = Cpu::default();
+
+        memory.write(0xffff, 0x01);
+        memory.request_interrupt(Interrupt::VBLANK);
+
+        cpu.step(&mut memory).unwrap();
+        cpu.step(&mut memory).unwrap();
+
+        assert_eq!(cpu.registers.get(GeneralRegister::A), 0x3e);
+        assert_eq!(cpu.program_counter, 0x0002);
+    }
+
+    #[test]
+    fn dec_mem_hl() {
+        let mut memory = FlatMemory::with_program(&[0x35]);
+        let mut cpu = Cpu::default();
+
+        cpu.registers.set16(CombinedRegister::HL, 0xc000);
+        memory.write(0xc000, 0x10);
+        cpu.step(&mut memory).unwrap();
+
+        assert_eq!(memory.read(0xc000), 0x0f);
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::NEGATIVE));
+        assert!(cpu.registers.is_flag_set(FlagRegisterValue::HALF_CARRY));
+    }
+
+    #[test]
+    fn timer_advances_between_memory_accesses() {
+        // LDH A, (TIMA) twice with TIMA counting every 16 T-cycles
+        let mut mbc = get_mock_mbc(&[0xf0, 0x05, 0xf0, 0x05]);
+        let mut cpu = Cpu::default();
+
+        mbc.write(0xff07, 0b101);
+
+        // The read lands on the third M-cycle, before the first increment
+        cpu.step(&mut mbc).unwrap();
+        assert_eq!(cpu.registers.get(GeneralRegister::A), 0);
+
+        cpu.step(&mut mbc).unwrap();
+        assert_eq!(cpu.registers.get(GeneralRegister::A), 1);
+    }
+
+    #[test]
+    fn push_ticks_the_bus_per_m_cycle() {
+        let mut memory = FlatMemory::with_program(&[0xc5]);
+        let mut cpu = Cpu {
+            stack_pointer: 0xd000,
+            ..Cpu::default()
+        };
+
+        cpu.registers.set16(CombinedRegister::BC, 0x1234);
+
+        assert_eq!(cpu.step(&mut memory).unwrap(), 16);
+        assert_eq!(memory.cycles, 16);
+        assert_eq!(memory.read(0xcfff), 0x12);
+        assert_eq!(memory.read(0xcffe), 0x34);
+    }
+
+    #[test]
+    fn stop_waits_for_a_button_press() {
+        // STOP, padding byte, INC A
+        let mut mbc = get_mock_mbc(&[0x10, 0x00, 0x3c]);
+        let mut cpu = Cpu::default();
+
+        mbc.tick(1024);
+        mbc.write(0xff00, 0b0010_0000);
+        cpu.step(&mut mbc).unwrap();
+
+        assert!(cpu.stopped);
+        assert!(mbc.lcd_blanked());
+        assert_eq!(cpu.program_counter, 0x0002);
+        assert_eq!(mbc.read(0xff04), 0);
+
+        assert_eq!(cpu.step(&mut mbc).unwrap(), 4);
+        assert_eq!(mbc.read(0xff04), 0);
+        assert_eq!(cpu.registers.get(GeneralRegister::A), 0);
+
+        mbc.press(Button::Right);
+        cpu.step(&mut mbc).unwrap();
+
+        assert!(!cpu.stopped);
+        assert!(!mbc.lcd_blanked());
+        assert_eq!(cpu.registers.get(GeneralRegister::A), 1);
+    }
+
+    #[test]
+    fn stop_switches_speed_when_armed() {
+        let mut data = vec![0x00; 0x8000];
+        data[..3].copy_from_slice(&[0x10, 0x00, 0x3c]);
+        // CGB compatible
+        data[0x143] = 0x80;
+
+        let mut mbc = MBC::try_from(Cartridge::try_from(data).unwrap()).unwrap();
+        let mut cpu = Cpu::default();
+
+        mbc.write(0xff4d, 0x01);
+        assert_eq!(mbc.read(0xff4d), 0x7f);
+
+        cpu.step(&mut mbc).unwrap();
+        cpu.step(&mut mbc).unwrap();
+
+        assert!(!cpu.stopped);
+        assert!(mbc.double_speed());
+        assert_eq!(mbc.read(0xff4d), 0xfe);
+        assert_eq!(cpu.registers.get(GeneralRegister::A), 1);
+    }
+
+    #[test]
+    fn illegal_opcode_locks_up() {
+        let mut memory = FlatMemory::with_program(&[0x00, 0xd3, 0x3c]);
+        let mut cpu = Cpu::default();
+
+        memory.write(0xffff, 0x01);
+        cpu.step(&mut memory).unwrap();
+
+        match cpu.step(&mut memory) {
+            Err(EmulatorError::IllegalInstruction { opcode, location }) => {
+                assert_eq!(opcode, 0xd3);
+                assert_eq!(location, 0x0001);
+            }
+            result => panic!("Expected a lock up, got {:?}", result),
         }
 
-        self.program_counter += OPCODE_CYCLES[op as usize];
+        // Locked for good, even with an interrupt pending
+        memory.request_interrupt(Interrupt::VBLANK);
+        cpu.interrupts_enabled = true;
+
+        assert_eq!(cpu.step(&mut memory).unwrap(), 4);
+        assert!(cpu.locked);
+        assert_eq!(cpu.program_counter, 0x0002);
+        assert_eq!(cpu.registers.get(GeneralRegister::A), 0);
+    }
+
+    #[test]
+    fn runs_against_flat_memory() {
+        // LD A, 0x42; LD (0x8000), A; CALL 0x0010
+        let mut memory =
+            FlatMemory::with_program(&[0x3e, 0x42, 0xea, 0x00, 0x80, 0xcd, 0x10, 0x00]);
+        let mut cpu = Cpu {
+            stack_pointer: 0xfffe,
+            ..Cpu::default()
+        };
+
+        cpu.step(&mut memory).unwrap();
+        cpu.step(&mut memory).unwrap();
+        cpu.step(&mut memory).unwrap();
+
+        assert_eq!(memory.read(0x8000), 0x42);
+        assert_eq!(cpu.program_counter, 0x0010);
+        assert_eq!(memory.read(0xfffc), 0x08);
+    }
+
+    #[test]
+    fn step_ticks_the_bus() {
+        let mut memory = FlatMemory::with_program(&[0x00, 0xcb, 0x06, 0xc3, 0x00, 0x00]);
+        let mut cpu = Cpu::default();
+
+        cpu.step(&mut memory).unwrap();
+        cpu.step(&mut memory).unwrap();
+        cpu.step(&mut memory).unwrap();
+
+        assert_eq!(memory.cycles, 4 + 16 + 16);
     }
 }
@@ -1,6 +1,5 @@
 use crate::flag_register::FlagRegisterValue;
 use crate::utils::{u16_to_u8s, u8s_to_u16, BitWise};
-use std::collections::HashMap;
 
 #[derive(Hash, PartialEq, Eq, Debug, Clone, Copy)]
 pub enum GeneralRegister {
@@ -22,80 +21,47 @@ pub enum CombinedRegister {
     HL,
 }
 
-const ALL_REGISTERS: [GeneralRegister; 8] = [
-    GeneralRegister::A,
-    GeneralRegister::F,
-    GeneralRegister::B,
-    GeneralRegister::C,
-    GeneralRegister::D,
-    GeneralRegister::E,
-    GeneralRegister::H,
-    GeneralRegister::L,
-];
-
-// const ALL_COMBINED_REGISTERS: [CombinedRegister; 4] = [
-//     CombinedRegister::AF,
-//     CombinedRegister::BC,
-//     CombinedRegister::DE,
-//     CombinedRegister::HL,
-// ];
+// Only the top 4 bits of F are backed by real flags
+const FLAG_REGISTER_MASK: u8 = 0xf0;
 
+/// The eight 8-bit registers, laid out in `GeneralRegister` order so each
+/// `CombinedRegister` is the high byte at `2 * pair` followed by the low byte.
+#[derive(Default)]
 pub struct Registers {
-    registers: HashMap<GeneralRegister, u8>,
+    registers: [u8; 8],
 }
 
 impl Registers {
     pub fn set(&mut self, register: GeneralRegister, value: u8) -> &mut Self {
-        self.registers.insert(register, value);
+        self.registers[register as usize] = match register {
+            GeneralRegister::F => value & FLAG_REGISTER_MASK,
+            _ => value,
+        };
+
         self
     }
 
     pub fn get(&self, register: GeneralRegister) -> u8 {
-        // 'get' seems overcomplicated, but I want to
-        // account for missing registers if something weird happens
-        *self.registers.get(&register).unwrap_or(&0)
+        self.registers[register as usize]
     }
 
     pub fn set16(&mut self, register: CombinedRegister, value: u16) -> &mut Self {
-        let [left, right] = u16_to_u8s(value);
-
-        match register {
-            CombinedRegister::AF => {
-                self.set(GeneralRegister::A, left);
-                self.set(GeneralRegister::F, right);
-            }
-            CombinedRegister::BC => {
-                self.set(GeneralRegister::B, left);
-                self.set(GeneralRegister::C, right);
-            }
-            CombinedRegister::DE => {
-                self.set(GeneralRegister::D, left);
-                self.set(GeneralRegister::E, right);
-            }
-            CombinedRegister::HL => {
-                self.set(GeneralRegister::H, left);
-                self.set(GeneralRegister::L, right);
-            }
+        let [high, mut low] = u16_to_u8s(value);
+        let index = register as usize * 2;
+
+        if register == CombinedRegister::AF {
+            low &= FLAG_REGISTER_MASK;
         }
 
+        self.registers[index] = high;
+        self.registers[index + 1] = low;
+
         self
     }
 
     pub fn get16(&self, register: CombinedRegister) -> u16 {
-        match register {
-            CombinedRegister::AF => {
-                u8s_to_u16(self.get(GeneralRegister::A), self.get(GeneralRegister::F))
-            }
-            CombinedRegister::BC => {
-                u8s_to_u16(self.get(GeneralRegister::B), self.get(GeneralRegister::C))
-            }
-            CombinedRegister::DE => {
-                u8s_to_u16(self.get(GeneralRegister::D), self.get(GeneralRegister::E))
-            }
-            CombinedRegister::HL => {
-                u8s_to_u16(self.get(GeneralRegister::H), self.get(GeneralRegister::L))
-            }
-        }
+        let index = register as usize * 2;
+        u8s_to_u16(self.registers[index], self.registers[index + 1])
     }
 
     pub fn set_flag(&mut self, flag: FlagRegisterValue) -> &mut Self {
@@ -143,10 +109,48 @@ impl Registers {
     }
 }
 
-impl Default for Registers {
-    fn default() -> Self {
-        Registers {
-            registers: HashMap::from(ALL_REGISTERS.map(|r| (r, 0))),
-        }
+#[cfg(test)]
+mod tests {
+    use crate::cpu_registers::{CombinedRegister, GeneralRegister, Registers};
+    use crate::flag_register::FlagRegisterValue;
+
+    #[test]
+    fn pairs_share_storage_with_halves() {
+        let mut registers = Registers::default();
+
+        registers.set16(CombinedRegister::BC, 0x1234);
+        registers
+            .set(GeneralRegister::L, 0xcd)
+            .set(GeneralRegister::H, 0xab);
+
+        assert_eq!(registers.get(GeneralRegister::B), 0x12);
+        assert_eq!(registers.get(GeneralRegister::C), 0x34);
+        assert_eq!(registers.get16(CombinedRegister::HL), 0xabcd);
+        assert_eq!(registers.get16(CombinedRegister::DE), 0x0000);
+    }
+
+    #[test]
+    fn flag_register_low_nibble_is_always_zero() {
+        let mut registers = Registers::default();
+
+        registers.set(GeneralRegister::F, 0xff);
+        assert_eq!(registers.get(GeneralRegister::F), 0xf0);
+
+        registers.set16(CombinedRegister::AF, 0x12ff);
+        assert_eq!(registers.get16(CombinedRegister::AF), 0x12f0);
+    }
+
+    #[test]
+    fn flags() {
+        let mut registers = Registers::default();
+
+        registers
+            .set_flag(FlagRegisterValue::ZERO)
+            .set_flag(FlagRegisterValue::CARRY)
+            .unset_flag(FlagRegisterValue::CARRY);
+
+        assert!(registers.is_flag_set(FlagRegisterValue::ZERO));
+        assert!(!registers.is_flag_set(FlagRegisterValue::CARRY));
+        assert_eq!(registers.get(GeneralRegister::F), 0x80);
     }
 }
@@ -0,0 +1,181 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::cartridge_type::CartridgeType;
+
+/// Something wrong with a ROM image. Some of these mean the image can't be
+/// run at all, the rest are only warnings: real hardware never checks them,
+/// or the boot ROM would, but that's skipped.
+#[derive(Debug)]
+pub enum CartridgeError {
+    Io(io::Error),
+    /// The ROM is too small to hold a cartridge header
+    TooSmall(usize),
+    /// The ROM is shorter than the size in its header, so it's been cut off
+    Truncated {
+        declared: usize,
+        actual: usize,
+    },
+    /// The ROM is longer than the size in its header
+    Oversized {
+        declared: usize,
+        actual: usize,
+    },
+    UnknownRomSize(u8),
+    UnknownRamSize(u8),
+    /// The logo doesn't match the one the boot ROM checks for
+    BadLogo,
+    HeaderChecksum {
+        expected: u8,
+        actual: u8,
+    },
+    GlobalChecksum {
+        expected: u16,
+        actual: u16,
+    },
+}
+
+impl CartridgeError {
+    /// Whether the ROM can still be run in spite of this
+    pub fn is_warning(&self) -> bool {
+        !matches!(
+            self,
+            CartridgeError::Io(_) | CartridgeError::TooSmall(_) | CartridgeError::Truncated { .. }
+        )
+    }
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CartridgeError::Io(error) => write!(f, "Could not read ROM: {}", error),
+            CartridgeError::TooSmall(size) => write!(
+                f,
+                "ROM is {} bytes, too small to contain a cartridge header",
+                size
+            ),
+            CartridgeError::Truncated { declared, actual } => write!(
+                f,
+                "ROM is {} bytes but its header says {}, it may be truncated",
+                actual, declared
+            ),
+            CartridgeError::Oversized { declared, actual } => write!(
+                f,
+                "ROM is {} bytes but its header says {}",
+                actual, declared
+            ),
+            CartridgeError::UnknownRomSize(code) => {
+                write!(f, "Header has an unknown ROM size code {:#04x}", code)
+            }
+            CartridgeError::UnknownRamSize(code) => {
+                write!(f, "Header has an unknown RAM size code {:#04x}", code)
+            }
+            CartridgeError::BadLogo => write!(f, "Header logo doesn't match Nintendo's"),
+            CartridgeError::HeaderChecksum { expected, actual } => write!(
+                f,
+                "Header checksum is {:#04x}, expected {:#04x}",
+                actual, expected
+            ),
+            CartridgeError::GlobalChecksum { expected, actual } => write!(
+                f,
+                "Global checksum is {:#06x}, expected {:#06x}",
+                actual, expected
+            ),
+        }
+    }
+}
+
+impl Error for CartridgeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CartridgeError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CartridgeError {
+    fn from(error: io::Error) -> Self {
+        CartridgeError::Io(error)
+    }
+}
+
+/// Everything that can go wrong loading or running a ROM, for hosts that
+/// would rather report a problem than have the emulator panic
+#[derive(Debug)]
+pub enum EmulatorError {
+    Io(io::Error),
+    /// No ROM was given on the command line
+    MissingRom,
+    /// A file opened at startup, like the ROM, a save or a picture, couldn't
+    /// be read
+    File {
+        path: PathBuf,
+        error: io::Error,
+    },
+    /// The socket for the infrared link couldn't be opened
+    InfraredLink(io::Error),
+    /// The ROM couldn't be loaded
+    Cartridge(CartridgeError),
+    /// The header names a cartridge type there's no mapper for yet
+    UnsupportedCartridge(CartridgeType),
+    /// The CPU hit one of the unused opcodes and has locked up, just as the
+    /// real hardware does. It stays locked until it is reset.
+    IllegalInstruction {
+        opcode: u8,
+        location: usize,
+    },
+    /// A picture for a camera cartridge couldn't be decoded
+    InvalidImage(String),
+}
+
+impl fmt::Display for EmulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EmulatorError::Io(error) => write!(f, "I/O error: {}", error),
+            EmulatorError::MissingRom => write!(f, "Must provide a cartridge file path"),
+            EmulatorError::File { path, error } => {
+                write!(f, "Could not read {}: {}", path.display(), error)
+            }
+            EmulatorError::InfraredLink(error) => {
+                write!(f, "Could not open the infrared link: {}", error)
+            }
+            EmulatorError::Cartridge(error) => write!(f, "{}", error),
+            EmulatorError::UnsupportedCartridge(cartridge_type) => {
+                write!(f, "{:?} cartridges are not supported", cartridge_type)
+            }
+            EmulatorError::IllegalInstruction { opcode, location } => write!(
+                f,
+                "CPU locked up on illegal opcode {:#04x} at {:#06x}",
+                opcode, location
+            ),
+            EmulatorError::InvalidImage(reason) => write!(f, "Could not load image: {}", reason),
+        }
+    }
+}
+
+impl Error for EmulatorError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EmulatorError::Io(error) => Some(error),
+            EmulatorError::File { error, .. } => Some(error),
+            EmulatorError::InfraredLink(error) => Some(error),
+            EmulatorError::Cartridge(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for EmulatorError {
+    fn from(error: io::Error) -> Self {
+        EmulatorError::Io(error)
+    }
+}
+
+impl From<CartridgeError> for EmulatorError {
+    fn from(error: CartridgeError) -> Self {
+        EmulatorError::Cartridge(error)
+    }
+}
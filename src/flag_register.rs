@@ -10,8 +10,8 @@ bitflags! {
     }
 }
 
-impl Into<u8> for FlagRegisterValue {
-    fn into(self) -> u8 {
-        self.bits() as u8
+impl From<FlagRegisterValue> for u8 {
+    fn from(flag: FlagRegisterValue) -> u8 {
+        flag.bits()
     }
 }
@@ -0,0 +1,165 @@
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+
+/// Whatever sits across from a cartridge's infrared LED and receiver
+pub trait InfraredLink {
+    /// Turns this side's LED on or off
+    fn set_led(&mut self, on: bool);
+
+    /// Whether the receiver can currently see the other side's LED
+    fn receiving(&mut self) -> bool;
+}
+
+// Cycles between looks at the receiver, about one per scanline
+pub const POLL_CYCLES: u32 = 456;
+
+/// A cartridge's end of a link. Looking at the receiver can cost a system
+/// call, so it's only polled every `POLL_CYCLES` and the level kept between.
+#[derive(Default)]
+pub struct PolledLink {
+    link: Option<Box<dyn InfraredLink>>,
+    receiving: bool,
+    cycles: u32,
+}
+
+impl PolledLink {
+    pub fn connect(&mut self, link: Box<dyn InfraredLink>) {
+        self.link = Some(link);
+    }
+
+    pub fn set_led(&mut self, on: bool) {
+        if let Some(link) = &mut self.link {
+            link.set_led(on);
+        }
+    }
+
+    /// What the receiver saw the last time it was polled
+    pub fn receiving(&self) -> bool {
+        self.receiving
+    }
+
+    pub fn tick(&mut self, cycles: u32) {
+        self.cycles += cycles;
+
+        if self.cycles < POLL_CYCLES {
+            return;
+        }
+
+        self.cycles %= POLL_CYCLES;
+
+        if let Some(link) = &mut self.link {
+            self.receiving = link.receiving();
+        }
+    }
+}
+
+/// Links the infrared ports of two emulators running on the same machine.
+/// Each side sends a single byte over UDP whenever its LED changes, 1 for on
+/// and 0 for off, so the other side's receiver sees the same light.
+pub struct LocalLink {
+    socket: UdpSocket,
+    peer: SocketAddr,
+    receiving: bool,
+}
+
+impl LocalLink {
+    /// Listens on `port` and shines the LED at whatever is listening on
+    /// `peer_port`
+    pub fn new(port: u16, peer_port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, port))?;
+        socket.set_nonblocking(true)?;
+
+        Ok(LocalLink {
+            socket,
+            peer: SocketAddr::from((Ipv4Addr::LOCALHOST, peer_port)),
+            receiving: false,
+        })
+    }
+
+    pub fn local_port(&self) -> io::Result<u16> {
+        Ok(self.socket.local_addr()?.port())
+    }
+}
+
+impl InfraredLink for LocalLink {
+    fn set_led(&mut self, on: bool) {
+        // With nobody on the other end the light just goes nowhere
+        let _ = self.socket.send_to(&[on as u8], self.peer);
+    }
+
+    fn receiving(&mut self) -> bool {
+        let mut message = [0; 1];
+
+        // Only the latest change matters
+        while let Ok(size) = self.socket.recv(&mut message) {
+            if size > 0 {
+                self.receiving = message[0] != 0;
+            }
+        }
+
+        self.receiving
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddr};
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::infrared::{InfraredLink, LocalLink, PolledLink, POLL_CYCLES};
+
+    /// Counts how often the receiver is looked at
+    struct Counter(u32);
+
+    impl InfraredLink for Counter {
+        fn set_led(&mut self, _on: bool) {}
+
+        fn receiving(&mut self) -> bool {
+            self.0 += 1;
+            self.0 % 2 == 1
+        }
+    }
+
+    fn wait_for(link: &mut LocalLink, expected: bool) -> bool {
+        (0..100).any(|_| {
+            thread::sleep(Duration::from_millis(5));
+            link.receiving() == expected
+        })
+    }
+
+    #[test]
+    fn polled_link_only_polls_every_so_often() {
+        let mut link = PolledLink::default();
+        link.connect(Box::new(Counter(0)));
+
+        for _ in 0..POLL_CYCLES / 4 - 1 {
+            link.tick(4);
+        }
+        assert!(!link.receiving());
+
+        link.tick(4);
+        assert!(link.receiving());
+
+        link.tick(4);
+        assert!(link.receiving());
+    }
+
+    #[test]
+    fn local_link_carries_the_led_both_ways() {
+        let mut first = LocalLink::new(0, 0).unwrap();
+        let mut second = LocalLink::new(0, first.local_port().unwrap()).unwrap();
+        first.peer = SocketAddr::from((Ipv4Addr::LOCALHOST, second.local_port().unwrap()));
+
+        assert!(!first.receiving());
+
+        second.set_led(true);
+        assert!(wait_for(&mut first, true));
+
+        first.set_led(true);
+        assert!(wait_for(&mut second, true));
+
+        second.set_led(false);
+        assert!(wait_for(&mut first, false));
+    }
+}
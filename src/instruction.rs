@@ -0,0 +1,1135 @@
+use std::fmt;
+
+use crate::bus::Bus;
+use crate::cpu_registers::{CombinedRegister, GeneralRegister};
+use crate::flag_register::FlagRegisterValue;
+
+/// Size in bytes of each base instruction, including its operands
+#[rustfmt::skip]
+const OPCODE_LENGTHS: [usize; 256] = [
+    1, 3, 1, 1, 1, 1, 2, 1, 3, 1, 1, 1, 1, 1, 2, 1,
+    2, 3, 1, 1, 1, 1, 2, 1, 2, 1, 1, 1, 1, 1, 2, 1,
+    2, 3, 1, 1, 1, 1, 2, 1, 2, 1, 1, 1, 1, 1, 2, 1,
+    2, 3, 1, 1, 1, 1, 2, 1, 2, 1, 1, 1, 1, 1, 2, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 3, 3, 3, 1, 2, 1, 1, 1, 3, 2, 3, 3, 2, 1,
+    1, 1, 3, 1, 3, 1, 2, 1, 1, 1, 3, 1, 3, 1, 2, 1,
+    2, 1, 1, 1, 1, 1, 2, 1, 2, 1, 3, 1, 1, 1, 2, 1,
+    2, 1, 1, 1, 1, 1, 2, 1, 2, 1, 3, 1, 1, 1, 2, 1
+];
+
+/// T-cycles taken by each base instruction. Conditional jumps, calls and
+/// returns are listed with their not-taken cost, see `branch_cycles`.
+/// 0xCB is timed by `PREFIX_OPCODE_CYCLES` instead.
+#[rustfmt::skip]
+const OPCODE_CYCLES: [u32; 256] = [
+     4, 12,  8,  8,  4,  4,  8,  4, 20,  8,  8,  8,  4,  4,  8,  4,
+     4, 12,  8,  8,  4,  4,  8,  4, 12,  8,  8,  8,  4,  4,  8,  4,
+     8, 12,  8,  8,  4,  4,  8,  4,  8,  8,  8,  8,  4,  4,  8,  4,
+     8, 12,  8,  8, 12, 12, 12,  4,  8,  8,  8,  8,  4,  4,  8,  4,
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4,
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4,
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4,
+     8,  8,  8,  8,  8,  8,  4,  8,  4,  4,  4,  4,  4,  4,  8,  4,
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4,
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4,
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4,
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4,
+     8, 12, 12, 16, 12, 16,  8, 16,  8, 16, 12,  0, 12, 24,  8, 16,
+     8, 12, 12,  4, 12, 16,  8, 16,  8, 16, 12,  4, 12,  4,  8, 16,
+    12, 12,  8,  4,  4, 16,  8, 16, 16,  4, 16,  4,  4,  4,  8, 16,
+    12, 12,  8,  4,  4, 16,  8, 16, 12,  8, 16,  4,  4,  4,  8, 16
+];
+
+/// T-cycles taken by each CB-prefixed instruction, including the fetch of
+/// the prefix byte itself. The (HL) forms pay for their extra memory access.
+#[rustfmt::skip]
+const PREFIX_OPCODE_CYCLES: [u32; 256] = [
+    8, 8, 8, 8, 8, 8, 16, 8, 8, 8, 8, 8, 8, 8, 16, 8,
+    8, 8, 8, 8, 8, 8, 16, 8, 8, 8, 8, 8, 8, 8, 16, 8,
+    8, 8, 8, 8, 8, 8, 16, 8, 8, 8, 8, 8, 8, 8, 16, 8,
+    8, 8, 8, 8, 8, 8, 16, 8, 8, 8, 8, 8, 8, 8, 16, 8,
+    8, 8, 8, 8, 8, 8, 12, 8, 8, 8, 8, 8, 8, 8, 12, 8,
+    8, 8, 8, 8, 8, 8, 12, 8, 8, 8, 8, 8, 8, 8, 12, 8,
+    8, 8, 8, 8, 8, 8, 12, 8, 8, 8, 8, 8, 8, 8, 12, 8,
+    8, 8, 8, 8, 8, 8, 12, 8, 8, 8, 8, 8, 8, 8, 12, 8,
+    8, 8, 8, 8, 8, 8, 16, 8, 8, 8, 8, 8, 8, 8, 16, 8,
+    8, 8, 8, 8, 8, 8, 16, 8, 8, 8, 8, 8, 8, 8, 16, 8,
+    8, 8, 8, 8, 8, 8, 16, 8, 8, 8, 8, 8, 8, 8, 16, 8,
+    8, 8, 8, 8, 8, 8, 16, 8, 8, 8, 8, 8, 8, 8, 16, 8,
+    8, 8, 8, 8, 8, 8, 16, 8, 8, 8, 8, 8, 8, 8, 16, 8,
+    8, 8, 8, 8, 8, 8, 16, 8, 8, 8, 8, 8, 8, 8, 16, 8,
+    8, 8, 8, 8, 8, 8, 16, 8, 8, 8, 8, 8, 8, 8, 16, 8,
+    8, 8, 8, 8, 8, 8, 16, 8, 8, 8, 8, 8, 8, 8, 16, 8
+];
+
+/// Extra T-cycles spent by a conditional jump, call or return when its
+/// condition holds
+fn branch_cycles(op: u8) -> u32 {
+    match op {
+        0x20 | 0x28 | 0x30 | 0x38 => 4,
+        0xC2 | 0xCA | 0xD2 | 0xDA => 4,
+        0xC4 | 0xCC | 0xD4 | 0xDC => 12,
+        0xC0 | 0xC8 | 0xD0 | 0xD8 => 12,
+        _ => 0,
+    }
+}
+
+/// What an instruction does, with any immediate operands already pulled out
+/// of the instruction stream. Each variant lines up with the `Ops` or
+/// `PrefixOps` method that carries it out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operation {
+    Nop,
+    Ccf,
+    Daa,
+    Stop,
+    Halt,
+    Di,
+    Ei,
+    Ret,
+    RetF(FlagRegisterValue, bool),
+    Reti,
+    Rst(u16),
+    LdRR(GeneralRegister, GeneralRegister),
+    LdRD8(GeneralRegister, u8),
+    LdRrD16(CombinedRegister, u16),
+    LdMemRrR(CombinedRegister, GeneralRegister),
+    LdiMemRrR(CombinedRegister, GeneralRegister),
+    LddMemRrR(CombinedRegister, GeneralRegister),
+    LdRMemRr(GeneralRegister, CombinedRegister),
+    LdiRMemRr(GeneralRegister, CombinedRegister),
+    LddRMemRr(GeneralRegister, CombinedRegister),
+    LdMemRrD8(CombinedRegister, u8),
+    LdMemA8A(u8),
+    LdAMemA8(u8),
+    LdMemA16A(u16),
+    LdAMemA16(u16),
+    LdMemRA(GeneralRegister),
+    LdAMemR(GeneralRegister),
+    LdSpD16(u16),
+    LdMemA16Sp(u16),
+    LdSpRr(CombinedRegister),
+    LdRrSpS8(CombinedRegister, i8),
+    AddR(GeneralRegister),
+    AddD8(u8),
+    AddMemRr(CombinedRegister),
+    AddRr(CombinedRegister),
+    AddSp,
+    AddSpS8(i8),
+    AdcR(GeneralRegister),
+    AdcD8(u8),
+    AdcMemRr(CombinedRegister),
+    SubR(GeneralRegister),
+    SubD8(u8),
+    SubMemRr(CombinedRegister),
+    SbcR(GeneralRegister),
+    SbcD8(u8),
+    SbcMemRr(CombinedRegister),
+    AndR(GeneralRegister),
+    AndD8(u8),
+    AndMemRr(CombinedRegister),
+    XorR(GeneralRegister),
+    XorD8(u8),
+    XorMemRr(CombinedRegister),
+    OrR(GeneralRegister),
+    OrD8(u8),
+    OrMemRr(CombinedRegister),
+    CpR(GeneralRegister),
+    CpD8(u8),
+    CpMemRr(CombinedRegister),
+    IncR(GeneralRegister),
+    IncMemRr(CombinedRegister),
+    IncRr(CombinedRegister),
+    IncSp,
+    DecR(GeneralRegister),
+    DecMemRr(CombinedRegister),
+    DecRr(CombinedRegister),
+    DecSp,
+    PushRr(CombinedRegister),
+    PopRr(CombinedRegister),
+    Jp(Option<FlagRegisterValue>, bool, u16),
+    Jr(Option<FlagRegisterValue>, bool, i8),
+    JpRr(CombinedRegister),
+    Call(u16),
+    CallFA16(FlagRegisterValue, bool, u16),
+    Scf,
+    Cpl,
+    Rlca,
+    Rla,
+    Rrca,
+    Rra,
+    RlcR(GeneralRegister),
+    RlcMemRr(CombinedRegister),
+    RlR(GeneralRegister),
+    RlMemRr(CombinedRegister),
+    RrcR(GeneralRegister),
+    RrcMemRr(CombinedRegister),
+    RrR(GeneralRegister),
+    RrMemRr(CombinedRegister),
+    SlaR(GeneralRegister),
+    SlaMemRr(CombinedRegister),
+    SraR(GeneralRegister),
+    SraMemRr(CombinedRegister),
+    SwapR(GeneralRegister),
+    SwapMemRr(CombinedRegister),
+    SrlR(GeneralRegister),
+    SrlMemRr(CombinedRegister),
+    BitR(u8, GeneralRegister),
+    BitMemRr(u8, CombinedRegister),
+    ResR(u8, GeneralRegister),
+    ResMemRr(u8, CombinedRegister),
+    SetR(u8, GeneralRegister),
+    SetMemRr(u8, CombinedRegister),
+    Illegal,
+}
+
+/// A decoded instruction along with its size and timing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Instruction {
+    /// The opcode byte, or the byte following 0xCB for prefixed instructions
+    pub opcode: u8,
+    pub prefixed: bool,
+    pub operation: Operation,
+    /// Size in bytes, including the prefix and any operands
+    pub length: usize,
+    /// T-cycles taken when a conditional branch is not taken
+    pub cycles: u32,
+    /// Extra T-cycles spent when a conditional branch is taken
+    pub branch_cycles: u32,
+}
+
+impl Instruction {
+    /// Decodes the instruction starting at `location` without side effects,
+    /// so it is just as usable for a disassembler as for the CPU
+    pub fn decode<B: Bus>(bus: &B, location: usize) -> Self {
+        Self::decode_opcode(bus, bus.read(location), location)
+    }
+
+    /// Decodes `opcode` as though it sat at `location`, reading any operands
+    /// from the bytes after it. The HALT bug is the only time these differ.
+    pub fn decode_opcode<B: Bus>(bus: &B, opcode: u8, location: usize) -> Self {
+        if opcode == 0xCB {
+            let op = bus.get_next_u8(location);
+
+            return Instruction {
+                opcode: op,
+                prefixed: true,
+                operation: decode_prefixed(op),
+                length: 2,
+                cycles: PREFIX_OPCODE_CYCLES[op as usize],
+                branch_cycles: 0,
+            };
+        }
+
+        Instruction {
+            opcode,
+            prefixed: false,
+            operation: decode_base(bus, opcode, location),
+            length: OPCODE_LENGTHS[opcode as usize],
+            cycles: OPCODE_CYCLES[opcode as usize],
+            branch_cycles: branch_cycles(opcode),
+        }
+    }
+}
+
+/// Decodes `count` instructions one after another, starting at `location`
+pub fn disassemble<B: Bus>(bus: &B, location: usize, count: usize) -> Vec<(usize, Instruction)> {
+    let mut location = location;
+    let mut instructions = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        let instruction = Instruction::decode(bus, location);
+        instructions.push((location, instruction));
+        location = (location + instruction.length) & 0xffff;
+    }
+
+    instructions
+}
+
+fn decode_base<B: Bus>(bus: &B, opcode: u8, location: usize) -> Operation {
+    match opcode {
+        0x00 => Operation::Nop,
+        0x01 => Operation::LdRrD16(CombinedRegister::BC, bus.get_next_u16(location)),
+        0x02 => Operation::LdMemRrR(CombinedRegister::BC, GeneralRegister::A),
+        0x03 => Operation::IncRr(CombinedRegister::BC),
+        0x04 => Operation::IncR(GeneralRegister::B),
+        0x05 => Operation::DecR(GeneralRegister::B),
+        0x06 => Operation::LdRD8(GeneralRegister::B, bus.get_next_u8(location)),
+        0x07 => Operation::Rlca,
+        0x08 => Operation::LdMemA16Sp(bus.get_next_u16(location)),
+        0x09 => Operation::AddRr(CombinedRegister::BC),
+        0x0A => Operation::LdRMemRr(GeneralRegister::A, CombinedRegister::BC),
+        0x0B => Operation::DecRr(CombinedRegister::BC),
+        0x0C => Operation::IncR(GeneralRegister::C),
+        0x0D => Operation::DecR(GeneralRegister::C),
+        0x0E => Operation::LdRD8(GeneralRegister::C, bus.get_next_u8(location)),
+        0x0F => Operation::Rrca,
+
+        0x10 => Operation::Stop,
+        0x11 => Operation::LdRrD16(CombinedRegister::DE, bus.get_next_u16(location)),
+        0x12 => Operation::LdMemRrR(CombinedRegister::DE, GeneralRegister::A),
+        0x13 => Operation::IncRr(CombinedRegister::DE),
+        0x14 => Operation::IncR(GeneralRegister::D),
+        0x15 => Operation::DecR(GeneralRegister::D),
+        0x16 => Operation::LdRD8(GeneralRegister::D, bus.get_next_u8(location)),
+        0x17 => Operation::Rla,
+        0x18 => Operation::Jr(None, false, bus.get_next_u8(location) as i8),
+        0x19 => Operation::AddRr(CombinedRegister::DE),
+        0x1A => Operation::LdRMemRr(GeneralRegister::A, CombinedRegister::DE),
+        0x1B => Operation::DecRr(CombinedRegister::DE),
+        0x1C => Operation::IncR(GeneralRegister::E),
+        0x1D => Operation::DecR(GeneralRegister::E),
+        0x1E => Operation::LdRD8(GeneralRegister::E, bus.get_next_u8(location)),
+        0x1F => Operation::Rra,
+
+        0x20 => Operation::Jr(
+            Some(FlagRegisterValue::ZERO),
+            false,
+            bus.get_next_u8(location) as i8,
+        ),
+        0x21 => Operation::LdRrD16(CombinedRegister::HL, bus.get_next_u16(location)),
+        0x22 => Operation::LdiMemRrR(CombinedRegister::HL, GeneralRegister::A),
+        0x23 => Operation::IncRr(CombinedRegister::HL),
+        0x24 => Operation::IncR(GeneralRegister::H),
+        0x25 => Operation::DecR(GeneralRegister::H),
+        0x26 => Operation::LdRD8(GeneralRegister::H, bus.get_next_u8(location)),
+        0x27 => Operation::Daa,
+        0x28 => Operation::Jr(
+            Some(FlagRegisterValue::ZERO),
+            true,
+            bus.get_next_u8(location) as i8,
+        ),
+        0x29 => Operation::AddRr(CombinedRegister::HL),
+        0x2A => Operation::LdiRMemRr(GeneralRegister::A, CombinedRegister::HL),
+        0x2B => Operation::DecRr(CombinedRegister::HL),
+        0x2C => Operation::IncR(GeneralRegister::L),
+        0x2D => Operation::DecR(GeneralRegister::L),
+        0x2E => Operation::LdRD8(GeneralRegister::L, bus.get_next_u8(location)),
+        0x2F => Operation::Cpl,
+
+        0x30 => Operation::Jr(
+            Some(FlagRegisterValue::CARRY),
+            false,
+            bus.get_next_u8(location) as i8,
+        ),
+        0x31 => Operation::LdSpD16(bus.get_next_u16(location)),
+        0x32 => Operation::LddMemRrR(CombinedRegister::HL, GeneralRegister::A),
+        0x33 => Operation::IncSp,
+        0x34 => Operation::IncMemRr(CombinedRegister::HL),
+        0x35 => Operation::DecMemRr(CombinedRegister::HL),
+        0x36 => Operation::LdMemRrD8(CombinedRegister::HL, bus.get_next_u8(location)),
+        0x37 => Operation::Scf,
+        0x38 => Operation::Jr(
+            Some(FlagRegisterValue::CARRY),
+            true,
+            bus.get_next_u8(location) as i8,
+        ),
+        0x39 => Operation::AddSp,
+        0x3A => Operation::LddRMemRr(GeneralRegister::A, CombinedRegister::HL),
+        0x3B => Operation::DecSp,
+        0x3C => Operation::IncR(GeneralRegister::A),
+        0x3D => Operation::DecR(GeneralRegister::A),
+        0x3E => Operation::LdRD8(GeneralRegister::A, bus.get_next_u8(location)),
+        0x3F => Operation::Ccf,
+
+        0x40 => Operation::LdRR(GeneralRegister::B, GeneralRegister::B),
+        0x41 => Operation::LdRR(GeneralRegister::B, GeneralRegister::C),
+        0x42 => Operation::LdRR(GeneralRegister::B, GeneralRegister::D),
+        0x43 => Operation::LdRR(GeneralRegister::B, GeneralRegister::E),
+        0x44 => Operation::LdRR(GeneralRegister::B, GeneralRegister::H),
+        0x45 => Operation::LdRR(GeneralRegister::B, GeneralRegister::L),
+        0x46 => Operation::LdRMemRr(GeneralRegister::B, CombinedRegister::HL),
+        0x47 => Operation::LdRR(GeneralRegister::B, GeneralRegister::A),
+        0x48 => Operation::LdRR(GeneralRegister::C, GeneralRegister::B),
+        0x49 => Operation::LdRR(GeneralRegister::C, GeneralRegister::C),
+        0x4A => Operation::LdRR(GeneralRegister::C, GeneralRegister::D),
+        0x4B => Operation::LdRR(GeneralRegister::C, GeneralRegister::E),
+        0x4C => Operation::LdRR(GeneralRegister::C, GeneralRegister::H),
+        0x4D => Operation::LdRR(GeneralRegister::C, GeneralRegister::L),
+        0x4E => Operation::LdRMemRr(GeneralRegister::C, CombinedRegister::HL),
+        0x4F => Operation::LdRR(GeneralRegister::C, GeneralRegister::A),
+
+        0x50 => Operation::LdRR(GeneralRegister::D, GeneralRegister::B),
+        0x51 => Operation::LdRR(GeneralRegister::D, GeneralRegister::C),
+        0x52 => Operation::LdRR(GeneralRegister::D, GeneralRegister::D),
+        0x53 => Operation::LdRR(GeneralRegister::D, GeneralRegister::E),
+        0x54 => Operation::LdRR(GeneralRegister::D, GeneralRegister::H),
+        0x55 => Operation::LdRR(GeneralRegister::D, GeneralRegister::L),
+        0x56 => Operation::LdRMemRr(GeneralRegister::D, CombinedRegister::HL),
+        0x57 => Operation::LdRR(GeneralRegister::D, GeneralRegister::A),
+        0x58 => Operation::LdRR(GeneralRegister::E, GeneralRegister::B),
+        0x59 => Operation::LdRR(GeneralRegister::E, GeneralRegister::C),
+        0x5A => Operation::LdRR(GeneralRegister::E, GeneralRegister::D),
+        0x5B => Operation::LdRR(GeneralRegister::E, GeneralRegister::E),
+        0x5C => Operation::LdRR(GeneralRegister::E, GeneralRegister::H),
+        0x5D => Operation::LdRR(GeneralRegister::E, GeneralRegister::L),
+        0x5E => Operation::LdRMemRr(GeneralRegister::E, CombinedRegister::HL),
+        0x5F => Operation::LdRR(GeneralRegister::E, GeneralRegister::A),
+
+        0x60 => Operation::LdRR(GeneralRegister::H, GeneralRegister::B),
+        0x61 => Operation::LdRR(GeneralRegister::H, GeneralRegister::C),
+        0x62 => Operation::LdRR(GeneralRegister::H, GeneralRegister::D),
+        0x63 => Operation::LdRR(GeneralRegister::H, GeneralRegister::E),
+        0x64 => Operation::LdRR(GeneralRegister::H, GeneralRegister::H),
+        0x65 => Operation::LdRR(GeneralRegister::H, GeneralRegister::L),
+        0x66 => Operation::LdRMemRr(GeneralRegister::H, CombinedRegister::HL),
+        0x67 => Operation::LdRR(GeneralRegister::H, GeneralRegister::A),
+        0x68 => Operation::LdRR(GeneralRegister::L, GeneralRegister::B),
+        0x69 => Operation::LdRR(GeneralRegister::L, GeneralRegister::C),
+        0x6A => Operation::LdRR(GeneralRegister::L, GeneralRegister::D),
+        0x6B => Operation::LdRR(GeneralRegister::L, GeneralRegister::E),
+        0x6C => Operation::LdRR(GeneralRegister::L, GeneralRegister::H),
+        0x6D => Operation::LdRR(GeneralRegister::L, GeneralRegister::L),
+        0x6E => Operation::LdRMemRr(GeneralRegister::L, CombinedRegister::HL),
+        0x6F => Operation::LdRR(GeneralRegister::L, GeneralRegister::A),
+
+        0x70 => Operation::LdMemRrR(CombinedRegister::HL, GeneralRegister::B),
+        0x71 => Operation::LdMemRrR(CombinedRegister::HL, GeneralRegister::C),
+        0x72 => Operation::LdMemRrR(CombinedRegister::HL, GeneralRegister::D),
+        0x73 => Operation::LdMemRrR(CombinedRegister::HL, GeneralRegister::E),
+        0x74 => Operation::LdMemRrR(CombinedRegister::HL, GeneralRegister::H),
+        0x75 => Operation::LdMemRrR(CombinedRegister::HL, GeneralRegister::L),
+        0x76 => Operation::Halt,
+        0x77 => Operation::LdMemRrR(CombinedRegister::HL, GeneralRegister::A),
+        0x78 => Operation::LdRR(GeneralRegister::A, GeneralRegister::B),
+        0x79 => Operation::LdRR(GeneralRegister::A, GeneralRegister::C),
+        0x7A => Operation::LdRR(GeneralRegister::A, GeneralRegister::D),
+        0x7B => Operation::LdRR(GeneralRegister::A, GeneralRegister::E),
+        0x7C => Operation::LdRR(GeneralRegister::A, GeneralRegister::H),
+        0x7D => Operation::LdRR(GeneralRegister::A, GeneralRegister::L),
+        0x7E => Operation::LdRMemRr(GeneralRegister::A, CombinedRegister::HL),
+        0x7F => Operation::LdRR(GeneralRegister::A, GeneralRegister::A),
+
+        0x80 => Operation::AddR(GeneralRegister::B),
+        0x81 => Operation::AddR(GeneralRegister::C),
+        0x82 => Operation::AddR(GeneralRegister::D),
+        0x83 => Operation::AddR(GeneralRegister::E),
+        0x84 => Operation::AddR(GeneralRegister::H),
+        0x85 => Operation::AddR(GeneralRegister::L),
+        0x86 => Operation::AddMemRr(CombinedRegister::HL),
+        0x87 => Operation::AddR(GeneralRegister::A),
+        0x88 => Operation::AdcR(GeneralRegister::B),
+        0x89 => Operation::AdcR(GeneralRegister::C),
+        0x8A => Operation::AdcR(GeneralRegister::D),
+        0x8B => Operation::AdcR(GeneralRegister::E),
+        0x8C => Operation::AdcR(GeneralRegister::H),
+        0x8D => Operation::AdcR(GeneralRegister::L),
+        0x8E => Operation::AdcMemRr(CombinedRegister::HL),
+        0x8F => Operation::AdcR(GeneralRegister::A),
+
+        0x90 => Operation::SubR(GeneralRegister::B),
+        0x91 => Operation::SubR(GeneralRegister::C),
+        0x92 => Operation::SubR(GeneralRegister::D),
+        0x93 => Operation::SubR(GeneralRegister::E),
+        0x94 => Operation::SubR(GeneralRegister::H),
+        0x95 => Operation::SubR(GeneralRegister::L),
+        0x96 => Operation::SubMemRr(CombinedRegister::HL),
+        0x97 => Operation::SubR(GeneralRegister::A),
+        0x98 => Operation::SbcR(GeneralRegister::B),
+        0x99 => Operation::SbcR(GeneralRegister::C),
+        0x9A => Operation::SbcR(GeneralRegister::D),
+        0x9B => Operation::SbcR(GeneralRegister::E),
+        0x9C => Operation::SbcR(GeneralRegister::H),
+        0x9D => Operation::SbcR(GeneralRegister::L),
+        0x9E => Operation::SbcMemRr(CombinedRegister::HL),
+        0x9F => Operation::SbcR(GeneralRegister::A),
+
+        0xA0 => Operation::AndR(GeneralRegister::B),
+        0xA1 => Operation::AndR(GeneralRegister::C),
+        0xA2 => Operation::AndR(GeneralRegister::D),
+        0xA3 => Operation::AndR(GeneralRegister::E),
+        0xA4 => Operation::AndR(GeneralRegister::H),
+        0xA5 => Operation::AndR(GeneralRegister::L),
+        0xA6 => Operation::AndMemRr(CombinedRegister::HL),
+        0xA7 => Operation::AndR(GeneralRegister::A),
+        0xA8 => Operation::XorR(GeneralRegister::B),
+        0xA9 => Operation::XorR(GeneralRegister::C),
+        0xAA => Operation::XorR(GeneralRegister::D),
+        0xAB => Operation::XorR(GeneralRegister::E),
+        0xAC => Operation::XorR(GeneralRegister::H),
+        0xAD => Operation::XorR(GeneralRegister::L),
+        0xAE => Operation::XorMemRr(CombinedRegister::HL),
+        0xAF => Operation::XorR(GeneralRegister::A),
+
+        0xB0 => Operation::OrR(GeneralRegister::B),
+        0xB1 => Operation::OrR(GeneralRegister::C),
+        0xB2 => Operation::OrR(GeneralRegister::D),
+        0xB3 => Operation::OrR(GeneralRegister::E),
+        0xB4 => Operation::OrR(GeneralRegister::H),
+        0xB5 => Operation::OrR(GeneralRegister::L),
+        0xB6 => Operation::OrMemRr(CombinedRegister::HL),
+        0xB7 => Operation::OrR(GeneralRegister::A),
+        0xB8 => Operation::CpR(GeneralRegister::B),
+        0xB9 => Operation::CpR(GeneralRegister::C),
+        0xBA => Operation::CpR(GeneralRegister::D),
+        0xBB => Operation::CpR(GeneralRegister::E),
+        0xBC => Operation::CpR(GeneralRegister::H),
+        0xBD => Operation::CpR(GeneralRegister::L),
+        0xBE => Operation::CpMemRr(CombinedRegister::HL),
+        0xBF => Operation::CpR(GeneralRegister::A),
+
+        0xC0 => Operation::RetF(FlagRegisterValue::ZERO, false),
+        0xC1 => Operation::PopRr(CombinedRegister::BC),
+        0xC2 => Operation::Jp(
+            Some(FlagRegisterValue::ZERO),
+            false,
+            bus.get_next_u16(location),
+        ),
+        0xC3 => Operation::Jp(None, false, bus.get_next_u16(location)),
+        0xC4 => Operation::CallFA16(FlagRegisterValue::ZERO, false, bus.get_next_u16(location)),
+        0xC5 => Operation::PushRr(CombinedRegister::BC),
+        0xC6 => Operation::AddD8(bus.get_next_u8(location)),
+        0xC7 => Operation::Rst(0x00),
+        0xC8 => Operation::RetF(FlagRegisterValue::ZERO, true),
+        0xC9 => Operation::Ret,
+        0xCA => Operation::Jp(
+            Some(FlagRegisterValue::ZERO),
+            true,
+            bus.get_next_u16(location),
+        ),
+        0xCB => unreachable!("CB prefixed instructions are decoded by `decode_prefixed`"),
+        0xCC => Operation::CallFA16(FlagRegisterValue::ZERO, true, bus.get_next_u16(location)),
+        0xCD => Operation::Call(bus.get_next_u16(location)),
+        0xCE => Operation::AdcD8(bus.get_next_u8(location)),
+        0xCF => Operation::Rst(0x08),
+
+        0xD0 => Operation::RetF(FlagRegisterValue::CARRY, false),
+        0xD1 => Operation::PopRr(CombinedRegister::DE),
+        0xD2 => Operation::Jp(
+            Some(FlagRegisterValue::CARRY),
+            false,
+            bus.get_next_u16(location),
+        ),
+        0xD3 => Operation::Illegal,
+        0xD4 => Operation::CallFA16(FlagRegisterValue::CARRY, false, bus.get_next_u16(location)),
+        0xD5 => Operation::PushRr(CombinedRegister::DE),
+        0xD6 => Operation::SubD8(bus.get_next_u8(location)),
+        0xD7 => Operation::Rst(0x10),
+        0xD8 => Operation::RetF(FlagRegisterValue::CARRY, true),
+        0xD9 => Operation::Reti,
+        0xDA => Operation::Jp(
+            Some(FlagRegisterValue::CARRY),
+            true,
+            bus.get_next_u16(location),
+        ),
+        0xDB => Operation::Illegal,
+        0xDC => Operation::CallFA16(FlagRegisterValue::CARRY, true, bus.get_next_u16(location)),
+        0xDD => Operation::Illegal,
+        0xDE => Operation::SbcD8(bus.get_next_u8(location)),
+        0xDF => Operation::Rst(0x18),
+
+        0xE0 => Operation::LdMemA8A(bus.get_next_u8(location)),
+        0xE1 => Operation::PopRr(CombinedRegister::HL),
+        0xE2 => Operation::LdMemRA(GeneralRegister::C),
+        0xE3 => Operation::Illegal,
+        0xE4 => Operation::Illegal,
+        0xE5 => Operation::PushRr(CombinedRegister::HL),
+        0xE6 => Operation::AndD8(bus.get_next_u8(location)),
+        0xE7 => Operation::Rst(0x20),
+        0xE8 => Operation::AddSpS8(bus.get_next_u8(location) as i8),
+        0xE9 => Operation::JpRr(CombinedRegister::HL),
+        0xEA => Operation::LdMemA16A(bus.get_next_u16(location)),
+        0xEB => Operation::Illegal,
+        0xEC => Operation::Illegal,
+        0xED => Operation::Illegal,
+        0xEE => Operation::XorD8(bus.get_next_u8(location)),
+        0xEF => Operation::Rst(0x28),
+
+        0xF0 => Operation::LdAMemA8(bus.get_next_u8(location)),
+        0xF1 => Operation::PopRr(CombinedRegister::AF),
+        0xF2 => Operation::LdAMemR(GeneralRegister::C),
+        0xF3 => Operation::Di,
+        0xF4 => Operation::Illegal,
+        0xF5 => Operation::PushRr(CombinedRegister::AF),
+        0xF6 => Operation::OrD8(bus.get_next_u8(location)),
+        0xF7 => Operation::Rst(0x30),
+        0xF8 => Operation::LdRrSpS8(CombinedRegister::HL, bus.get_next_u8(location) as i8),
+        0xF9 => Operation::LdSpRr(CombinedRegister::HL),
+        0xFA => Operation::LdAMemA16(bus.get_next_u16(location)),
+        0xFB => Operation::Ei,
+        0xFC => Operation::Illegal,
+        0xFD => Operation::Illegal,
+        0xFE => Operation::CpD8(bus.get_next_u8(location)),
+        0xFF => Operation::Rst(0x38),
+    }
+}
+
+fn decode_prefixed(opcode: u8) -> Operation {
+    match opcode {
+        0x00 => Operation::RlcR(GeneralRegister::B),
+        0x01 => Operation::RlcR(GeneralRegister::C),
+        0x02 => Operation::RlcR(GeneralRegister::D),
+        0x03 => Operation::RlcR(GeneralRegister::E),
+        0x04 => Operation::RlcR(GeneralRegister::H),
+        0x05 => Operation::RlcR(GeneralRegister::L),
+        0x06 => Operation::RlcMemRr(CombinedRegister::HL),
+        0x07 => Operation::RlcR(GeneralRegister::A),
+        0x08 => Operation::RrcR(GeneralRegister::B),
+        0x09 => Operation::RrcR(GeneralRegister::C),
+        0x0A => Operation::RrcR(GeneralRegister::D),
+        0x0B => Operation::RrcR(GeneralRegister::E),
+        0x0C => Operation::RrcR(GeneralRegister::H),
+        0x0D => Operation::RrcR(GeneralRegister::L),
+        0x0E => Operation::RrcMemRr(CombinedRegister::HL),
+        0x0F => Operation::RrcR(GeneralRegister::A),
+
+        0x10 => Operation::RlR(GeneralRegister::B),
+        0x11 => Operation::RlR(GeneralRegister::C),
+        0x12 => Operation::RlR(GeneralRegister::D),
+        0x13 => Operation::RlR(GeneralRegister::E),
+        0x14 => Operation::RlR(GeneralRegister::H),
+        0x15 => Operation::RlR(GeneralRegister::L),
+        0x16 => Operation::RlMemRr(CombinedRegister::HL),
+        0x17 => Operation::RlR(GeneralRegister::A),
+        0x18 => Operation::RrR(GeneralRegister::B),
+        0x19 => Operation::RrR(GeneralRegister::C),
+        0x1A => Operation::RrR(GeneralRegister::D),
+        0x1B => Operation::RrR(GeneralRegister::E),
+        0x1C => Operation::RrR(GeneralRegister::H),
+        0x1D => Operation::RrR(GeneralRegister::L),
+        0x1E => Operation::RrMemRr(CombinedRegister::HL),
+        0x1F => Operation::RrR(GeneralRegister::A),
+
+        0x20 => Operation::SlaR(GeneralRegister::B),
+        0x21 => Operation::SlaR(GeneralRegister::C),
+        0x22 => Operation::SlaR(GeneralRegister::D),
+        0x23 => Operation::SlaR(GeneralRegister::E),
+        0x24 => Operation::SlaR(GeneralRegister::H),
+        0x25 => Operation::SlaR(GeneralRegister::L),
+        0x26 => Operation::SlaMemRr(CombinedRegister::HL),
+        0x27 => Operation::SlaR(GeneralRegister::A),
+        0x28 => Operation::SraR(GeneralRegister::B),
+        0x29 => Operation::SraR(GeneralRegister::C),
+        0x2A => Operation::SraR(GeneralRegister::D),
+        0x2B => Operation::SraR(GeneralRegister::E),
+        0x2C => Operation::SraR(GeneralRegister::H),
+        0x2D => Operation::SraR(GeneralRegister::L),
+        0x2E => Operation::SraMemRr(CombinedRegister::HL),
+        0x2F => Operation::SraR(GeneralRegister::A),
+
+        0x30 => Operation::SwapR(GeneralRegister::B),
+        0x31 => Operation::SwapR(GeneralRegister::C),
+        0x32 => Operation::SwapR(GeneralRegister::D),
+        0x33 => Operation::SwapR(GeneralRegister::E),
+        0x34 => Operation::SwapR(GeneralRegister::H),
+        0x35 => Operation::SwapR(GeneralRegister::L),
+        0x36 => Operation::SwapMemRr(CombinedRegister::HL),
+        0x37 => Operation::SwapR(GeneralRegister::A),
+        0x38 => Operation::SrlR(GeneralRegister::B),
+        0x39 => Operation::SrlR(GeneralRegister::C),
+        0x3A => Operation::SrlR(GeneralRegister::D),
+        0x3B => Operation::SrlR(GeneralRegister::E),
+        0x3C => Operation::SrlR(GeneralRegister::H),
+        0x3D => Operation::SrlR(GeneralRegister::L),
+        0x3E => Operation::SrlMemRr(CombinedRegister::HL),
+        0x3F => Operation::SrlR(GeneralRegister::A),
+
+        0x40 => Operation::BitR(0, GeneralRegister::B),
+        0x41 => Operation::BitR(0, GeneralRegister::C),
+        0x42 => Operation::BitR(0, GeneralRegister::D),
+        0x43 => Operation::BitR(0, GeneralRegister::E),
+        0x44 => Operation::BitR(0, GeneralRegister::H),
+        0x45 => Operation::BitR(0, GeneralRegister::L),
+        0x46 => Operation::BitMemRr(0, CombinedRegister::HL),
+        0x47 => Operation::BitR(0, GeneralRegister::A),
+        0x48 => Operation::BitR(1, GeneralRegister::B),
+        0x49 => Operation::BitR(1, GeneralRegister::C),
+        0x4A => Operation::BitR(1, GeneralRegister::D),
+        0x4B => Operation::BitR(1, GeneralRegister::E),
+        0x4C => Operation::BitR(1, GeneralRegister::H),
+        0x4D => Operation::BitR(1, GeneralRegister::L),
+        0x4E => Operation::BitMemRr(1, CombinedRegister::HL),
+        0x4F => Operation::BitR(1, GeneralRegister::A),
+
+        0x50 => Operation::BitR(2, GeneralRegister::B),
+        0x51 => Operation::BitR(2, GeneralRegister::C),
+        0x52 => Operation::BitR(2, GeneralRegister::D),
+        0x53 => Operation::BitR(2, GeneralRegister::E),
+        0x54 => Operation::BitR(2, GeneralRegister::H),
+        0x55 => Operation::BitR(2, GeneralRegister::L),
+        0x56 => Operation::BitMemRr(2, CombinedRegister::HL),
+        0x57 => Operation::BitR(2, GeneralRegister::A),
+        0x58 => Operation::BitR(3, GeneralRegister::B),
+        0x59 => Operation::BitR(3, GeneralRegister::C),
+        0x5A => Operation::BitR(3, GeneralRegister::D),
+        0x5B => Operation::BitR(3, GeneralRegister::E),
+        0x5C => Operation::BitR(3, GeneralRegister::H),
+        0x5D => Operation::BitR(3, GeneralRegister::L),
+        0x5E => Operation::BitMemRr(3, CombinedRegister::HL),
+        0x5F => Operation::BitR(3, GeneralRegister::A),
+
+        0x60 => Operation::BitR(4, GeneralRegister::B),
+        0x61 => Operation::BitR(4, GeneralRegister::C),
+        0x62 => Operation::BitR(4, GeneralRegister::D),
+        0x63 => Operation::BitR(4, GeneralRegister::E),
+        0x64 => Operation::BitR(4, GeneralRegister::H),
+        0x65 => Operation::BitR(4, GeneralRegister::L),
+        0x66 => Operation::BitMemRr(4, CombinedRegister::HL),
+        0x67 => Operation::BitR(4, GeneralRegister::A),
+        0x68 => Operation::BitR(5, GeneralRegister::B),
+        0x69 => Operation::BitR(5, GeneralRegister::C),
+        0x6A => Operation::BitR(5, GeneralRegister::D),
+        0x6B => Operation::BitR(5, GeneralRegister::E),
+        0x6C => Operation::BitR(5, GeneralRegister::H),
+        0x6D => Operation::BitR(5, GeneralRegister::L),
+        0x6E => Operation::BitMemRr(5, CombinedRegister::HL),
+        0x6F => Operation::BitR(5, GeneralRegister::A),
+
+        0x70 => Operation::BitR(6, GeneralRegister::B),
+        0x71 => Operation::BitR(6, GeneralRegister::C),
+        0x72 => Operation::BitR(6, GeneralRegister::D),
+        0x73 => Operation::BitR(6, GeneralRegister::E),
+        0x74 => Operation::BitR(6, GeneralRegister::H),
+        0x75 => Operation::BitR(6, GeneralRegister::L),
+        0x76 => Operation::BitMemRr(6, CombinedRegister::HL),
+        0x77 => Operation::BitR(6, GeneralRegister::A),
+        0x78 => Operation::BitR(7, GeneralRegister::B),
+        0x79 => Operation::BitR(7, GeneralRegister::C),
+        0x7A => Operation::BitR(7, GeneralRegister::D),
+        0x7B => Operation::BitR(7, GeneralRegister::E),
+        0x7C => Operation::BitR(7, GeneralRegister::H),
+        0x7D => Operation::BitR(7, GeneralRegister::L),
+        0x7E => Operation::BitMemRr(7, CombinedRegister::HL),
+        0x7F => Operation::BitR(7, GeneralRegister::A),
+
+        0x80 => Operation::ResR(0, GeneralRegister::B),
+        0x81 => Operation::ResR(0, GeneralRegister::C),
+        0x82 => Operation::ResR(0, GeneralRegister::D),
+        0x83 => Operation::ResR(0, GeneralRegister::E),
+        0x84 => Operation::ResR(0, GeneralRegister::H),
+        0x85 => Operation::ResR(0, GeneralRegister::L),
+        0x86 => Operation::ResMemRr(0, CombinedRegister::HL),
+        0x87 => Operation::ResR(0, GeneralRegister::A),
+        0x88 => Operation::ResR(1, GeneralRegister::B),
+        0x89 => Operation::ResR(1, GeneralRegister::C),
+        0x8A => Operation::ResR(1, GeneralRegister::D),
+        0x8B => Operation::ResR(1, GeneralRegister::E),
+        0x8C => Operation::ResR(1, GeneralRegister::H),
+        0x8D => Operation::ResR(1, GeneralRegister::L),
+        0x8E => Operation::ResMemRr(1, CombinedRegister::HL),
+        0x8F => Operation::ResR(1, GeneralRegister::A),
+
+        0x90 => Operation::ResR(2, GeneralRegister::B),
+        0x91 => Operation::ResR(2, GeneralRegister::C),
+        0x92 => Operation::ResR(2, GeneralRegister::D),
+        0x93 => Operation::ResR(2, GeneralRegister::E),
+        0x94 => Operation::ResR(2, GeneralRegister::H),
+        0x95 => Operation::ResR(2, GeneralRegister::L),
+        0x96 => Operation::ResMemRr(2, CombinedRegister::HL),
+        0x97 => Operation::ResR(2, GeneralRegister::A),
+        0x98 => Operation::ResR(3, GeneralRegister::B),
+        0x99 => Operation::ResR(3, GeneralRegister::C),
+        0x9A => Operation::ResR(3, GeneralRegister::D),
+        0x9B => Operation::ResR(3, GeneralRegister::E),
+        0x9C => Operation::ResR(3, GeneralRegister::H),
+        0x9D => Operation::ResR(3, GeneralRegister::L),
+        0x9E => Operation::ResMemRr(3, CombinedRegister::HL),
+        0x9F => Operation::ResR(3, GeneralRegister::A),
+
+        0xA0 => Operation::ResR(4, GeneralRegister::B),
+        0xA1 => Operation::ResR(4, GeneralRegister::C),
+        0xA2 => Operation::ResR(4, GeneralRegister::D),
+        0xA3 => Operation::ResR(4, GeneralRegister::E),
+        0xA4 => Operation::ResR(4, GeneralRegister::H),
+        0xA5 => Operation::ResR(4, GeneralRegister::L),
+        0xA6 => Operation::ResMemRr(4, CombinedRegister::HL),
+        0xA7 => Operation::ResR(4, GeneralRegister::A),
+        0xA8 => Operation::ResR(5, GeneralRegister::B),
+        0xA9 => Operation::ResR(5, GeneralRegister::C),
+        0xAA => Operation::ResR(5, GeneralRegister::D),
+        0xAB => Operation::ResR(5, GeneralRegister::E),
+        0xAC => Operation::ResR(5, GeneralRegister::H),
+        0xAD => Operation::ResR(5, GeneralRegister::L),
+        0xAE => Operation::ResMemRr(5, CombinedRegister::HL),
+        0xAF => Operation::ResR(5, GeneralRegister::A),
+
+        0xB0 => Operation::ResR(6, GeneralRegister::B),
+        0xB1 => Operation::ResR(6, GeneralRegister::C),
+        0xB2 => Operation::ResR(6, GeneralRegister::D),
+        0xB3 => Operation::ResR(6, GeneralRegister::E),
+        0xB4 => Operation::ResR(6, GeneralRegister::H),
+        0xB5 => Operation::ResR(6, GeneralRegister::L),
+        0xB6 => Operation::ResMemRr(6, CombinedRegister::HL),
+        0xB7 => Operation::ResR(6, GeneralRegister::A),
+        0xB8 => Operation::ResR(7, GeneralRegister::B),
+        0xB9 => Operation::ResR(7, GeneralRegister::C),
+        0xBA => Operation::ResR(7, GeneralRegister::D),
+        0xBB => Operation::ResR(7, GeneralRegister::E),
+        0xBC => Operation::ResR(7, GeneralRegister::H),
+        0xBD => Operation::ResR(7, GeneralRegister::L),
+        0xBE => Operation::ResMemRr(7, CombinedRegister::HL),
+        0xBF => Operation::ResR(7, GeneralRegister::A),
+
+        0xC0 => Operation::SetR(0, GeneralRegister::B),
+        0xC1 => Operation::SetR(0, GeneralRegister::C),
+        0xC2 => Operation::SetR(0, GeneralRegister::D),
+        0xC3 => Operation::SetR(0, GeneralRegister::E),
+        0xC4 => Operation::SetR(0, GeneralRegister::H),
+        0xC5 => Operation::SetR(0, GeneralRegister::L),
+        0xC6 => Operation::SetMemRr(0, CombinedRegister::HL),
+        0xC7 => Operation::SetR(0, GeneralRegister::A),
+        0xC8 => Operation::SetR(1, GeneralRegister::B),
+        0xC9 => Operation::SetR(1, GeneralRegister::C),
+        0xCA => Operation::SetR(1, GeneralRegister::D),
+        0xCB => Operation::SetR(1, GeneralRegister::E),
+        0xCC => Operation::SetR(1, GeneralRegister::H),
+        0xCD => Operation::SetR(1, GeneralRegister::L),
+        0xCE => Operation::SetMemRr(1, CombinedRegister::HL),
+        0xCF => Operation::SetR(1, GeneralRegister::A),
+
+        0xD0 => Operation::SetR(2, GeneralRegister::B),
+        0xD1 => Operation::SetR(2, GeneralRegister::C),
+        0xD2 => Operation::SetR(2, GeneralRegister::D),
+        0xD3 => Operation::SetR(2, GeneralRegister::E),
+        0xD4 => Operation::SetR(2, GeneralRegister::H),
+        0xD5 => Operation::SetR(2, GeneralRegister::L),
+        0xD6 => Operation::SetMemRr(2, CombinedRegister::HL),
+        0xD7 => Operation::SetR(2, GeneralRegister::A),
+        0xD8 => Operation::SetR(3, GeneralRegister::B),
+        0xD9 => Operation::SetR(3, GeneralRegister::C),
+        0xDA => Operation::SetR(3, GeneralRegister::D),
+        0xDB => Operation::SetR(3, GeneralRegister::E),
+        0xDC => Operation::SetR(3, GeneralRegister::H),
+        0xDD => Operation::SetR(3, GeneralRegister::L),
+        0xDE => Operation::SetMemRr(3, CombinedRegister::HL),
+        0xDF => Operation::SetR(3, GeneralRegister::A),
+
+        0xE0 => Operation::SetR(4, GeneralRegister::B),
+        0xE1 => Operation::SetR(4, GeneralRegister::C),
+        0xE2 => Operation::SetR(4, GeneralRegister::D),
+        0xE3 => Operation::SetR(4, GeneralRegister::E),
+        0xE4 => Operation::SetR(4, GeneralRegister::H),
+        0xE5 => Operation::SetR(4, GeneralRegister::L),
+        0xE6 => Operation::SetMemRr(4, CombinedRegister::HL),
+        0xE7 => Operation::SetR(4, GeneralRegister::A),
+        0xE8 => Operation::SetR(5, GeneralRegister::B),
+        0xE9 => Operation::SetR(5, GeneralRegister::C),
+        0xEA => Operation::SetR(5, GeneralRegister::D),
+        0xEB => Operation::SetR(5, GeneralRegister::E),
+        0xEC => Operation::SetR(5, GeneralRegister::H),
+        0xED => Operation::SetR(5, GeneralRegister::L),
+        0xEE => Operation::SetMemRr(5, CombinedRegister::HL),
+        0xEF => Operation::SetR(5, GeneralRegister::A),
+
+        0xF0 => Operation::SetR(6, GeneralRegister::B),
+        0xF1 => Operation::SetR(6, GeneralRegister::C),
+        0xF2 => Operation::SetR(6, GeneralRegister::D),
+        0xF3 => Operation::SetR(6, GeneralRegister::E),
+        0xF4 => Operation::SetR(6, GeneralRegister::H),
+        0xF5 => Operation::SetR(6, GeneralRegister::L),
+        0xF6 => Operation::SetMemRr(6, CombinedRegister::HL),
+        0xF7 => Operation::SetR(6, GeneralRegister::A),
+        0xF8 => Operation::SetR(7, GeneralRegister::B),
+        0xF9 => Operation::SetR(7, GeneralRegister::C),
+        0xFA => Operation::SetR(7, GeneralRegister::D),
+        0xFB => Operation::SetR(7, GeneralRegister::E),
+        0xFC => Operation::SetR(7, GeneralRegister::H),
+        0xFD => Operation::SetR(7, GeneralRegister::L),
+        0xFE => Operation::SetMemRr(7, CombinedRegister::HL),
+        0xFF => Operation::SetR(7, GeneralRegister::A),
+    }
+}
+
+impl Operation {
+    /// The assembler name of the instruction, without its operands
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Operation::Nop => "NOP",
+            Operation::Ccf => "CCF",
+            Operation::Daa => "DAA",
+            Operation::Stop => "STOP",
+            Operation::Halt => "HALT",
+            Operation::Di => "DI",
+            Operation::Ei => "EI",
+            Operation::Ret | Operation::RetF(..) => "RET",
+            Operation::Reti => "RETI",
+            Operation::Rst(..) => "RST",
+            Operation::LdRR(..)
+            | Operation::LdRD8(..)
+            | Operation::LdRrD16(..)
+            | Operation::LdMemRrR(..)
+            | Operation::LdiMemRrR(..)
+            | Operation::LddMemRrR(..)
+            | Operation::LdRMemRr(..)
+            | Operation::LdiRMemRr(..)
+            | Operation::LddRMemRr(..)
+            | Operation::LdMemRrD8(..)
+            | Operation::LdMemA16A(..)
+            | Operation::LdAMemA16(..)
+            | Operation::LdMemRA(..)
+            | Operation::LdAMemR(..)
+            | Operation::LdSpD16(..)
+            | Operation::LdMemA16Sp(..)
+            | Operation::LdSpRr(..)
+            | Operation::LdRrSpS8(..) => "LD",
+            Operation::LdMemA8A(..) | Operation::LdAMemA8(..) => "LDH",
+            Operation::AddR(..)
+            | Operation::AddD8(..)
+            | Operation::AddMemRr(..)
+            | Operation::AddRr(..)
+            | Operation::AddSp
+            | Operation::AddSpS8(..) => "ADD",
+            Operation::AdcR(..) | Operation::AdcD8(..) | Operation::AdcMemRr(..) => "ADC",
+            Operation::SubR(..) | Operation::SubD8(..) | Operation::SubMemRr(..) => "SUB",
+            Operation::SbcR(..) | Operation::SbcD8(..) | Operation::SbcMemRr(..) => "SBC",
+            Operation::AndR(..) | Operation::AndD8(..) | Operation::AndMemRr(..) => "AND",
+            Operation::XorR(..) | Operation::XorD8(..) | Operation::XorMemRr(..) => "XOR",
+            Operation::OrR(..) | Operation::OrD8(..) | Operation::OrMemRr(..) => "OR",
+            Operation::CpR(..) | Operation::CpD8(..) | Operation::CpMemRr(..) => "CP",
+            Operation::IncR(..)
+            | Operation::IncMemRr(..)
+            | Operation::IncRr(..)
+            | Operation::IncSp => "INC",
+            Operation::DecR(..)
+            | Operation::DecMemRr(..)
+            | Operation::DecRr(..)
+            | Operation::DecSp => "DEC",
+            Operation::PushRr(..) => "PUSH",
+            Operation::PopRr(..) => "POP",
+            Operation::Jp(..) | Operation::JpRr(..) => "JP",
+            Operation::Jr(..) => "JR",
+            Operation::Call(..) | Operation::CallFA16(..) => "CALL",
+            Operation::Scf => "SCF",
+            Operation::Cpl => "CPL",
+            Operation::Rlca => "RLCA",
+            Operation::Rla => "RLA",
+            Operation::Rrca => "RRCA",
+            Operation::Rra => "RRA",
+            Operation::RlcR(..) | Operation::RlcMemRr(..) => "RLC",
+            Operation::RlR(..) | Operation::RlMemRr(..) => "RL",
+            Operation::RrcR(..) | Operation::RrcMemRr(..) => "RRC",
+            Operation::RrR(..) | Operation::RrMemRr(..) => "RR",
+            Operation::SlaR(..) | Operation::SlaMemRr(..) => "SLA",
+            Operation::SraR(..) | Operation::SraMemRr(..) => "SRA",
+            Operation::SwapR(..) | Operation::SwapMemRr(..) => "SWAP",
+            Operation::SrlR(..) | Operation::SrlMemRr(..) => "SRL",
+            Operation::BitR(..) | Operation::BitMemRr(..) => "BIT",
+            Operation::ResR(..) | Operation::ResMemRr(..) => "RES",
+            Operation::SetR(..) | Operation::SetMemRr(..) => "SET",
+            Operation::Illegal => "???",
+        }
+    }
+}
+
+fn condition(flag: FlagRegisterValue, truthy: bool) -> &'static str {
+    match (flag == FlagRegisterValue::CARRY, truthy) {
+        (false, true) => "Z",
+        (false, false) => "NZ",
+        (true, true) => "C",
+        (true, false) => "NC",
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Operation::Nop => write!(f, "NOP"),
+            Operation::Ccf => write!(f, "CCF"),
+            Operation::Daa => write!(f, "DAA"),
+            Operation::Stop => write!(f, "STOP"),
+            Operation::Halt => write!(f, "HALT"),
+            Operation::Di => write!(f, "DI"),
+            Operation::Ei => write!(f, "EI"),
+            Operation::Ret => write!(f, "RET"),
+            Operation::RetF(flag, truthy) => write!(f, "RET {}", condition(flag, truthy)),
+            Operation::Reti => write!(f, "RETI"),
+            Operation::Rst(address) => write!(f, "RST ${:02x}", address),
+            Operation::LdRR(to, from) => write!(f, "LD {:?}, {:?}", to, from),
+            Operation::LdRD8(register, value) => write!(f, "LD {:?}, ${:02x}", register, value),
+            Operation::LdRrD16(register, value) => write!(f, "LD {:?}, ${:04x}", register, value),
+            Operation::LdMemRrR(to_address, from) => write!(f, "LD ({:?}), {:?}", to_address, from),
+            Operation::LdiMemRrR(to_address, from) => {
+                write!(f, "LD ({:?}+), {:?}", to_address, from)
+            }
+            Operation::LddMemRrR(to_address, from) => {
+                write!(f, "LD ({:?}-), {:?}", to_address, from)
+            }
+            Operation::LdRMemRr(to, from_address) => write!(f, "LD {:?}, ({:?})", to, from_address),
+            Operation::LdiRMemRr(to, from_address) => {
+                write!(f, "LD {:?}, ({:?}+)", to, from_address)
+            }
+            Operation::LddRMemRr(to, from_address) => {
+                write!(f, "LD {:?}, ({:?}-)", to, from_address)
+            }
+            Operation::LdMemRrD8(address, value) => write!(f, "LD ({:?}), ${:02x}", address, value),
+            Operation::LdMemA8A(offset) => write!(f, "LDH (${:02x}), A", offset),
+            Operation::LdAMemA8(offset) => write!(f, "LDH A, (${:02x})", offset),
+            Operation::LdMemA16A(location) => write!(f, "LD (${:04x}), A", location),
+            Operation::LdAMemA16(location) => write!(f, "LD A, (${:04x})", location),
+            Operation::LdMemRA(register) => write!(f, "LD ($ff00+{:?}), A", register),
+            Operation::LdAMemR(register) => write!(f, "LD A, ($ff00+{:?})", register),
+            Operation::LdSpD16(value) => write!(f, "LD SP, ${:04x}", value),
+            Operation::LdMemA16Sp(location) => write!(f, "LD (${:04x}), SP", location),
+            Operation::LdSpRr(register) => write!(f, "LD SP, {:?}", register),
+            Operation::LdRrSpS8(register, offset) => write!(f, "LD {:?}, SP{:+}", register, offset),
+            Operation::AddR(register) => write!(f, "ADD {:?}", register),
+            Operation::AddD8(value) => write!(f, "ADD ${:02x}", value),
+            Operation::AddMemRr(register) => write!(f, "ADD ({:?})", register),
+            Operation::AddRr(register) => write!(f, "ADD HL, {:?}", register),
+            Operation::AddSp => write!(f, "ADD HL, SP"),
+            Operation::AddSpS8(offset) => write!(f, "ADD SP, {:+}", offset),
+            Operation::AdcR(register) => write!(f, "ADC {:?}", register),
+            Operation::AdcD8(value) => write!(f, "ADC ${:02x}", value),
+            Operation::AdcMemRr(register) => write!(f, "ADC ({:?})", register),
+            Operation::SubR(register) => write!(f, "SUB {:?}", register),
+            Operation::SubD8(value) => write!(f, "SUB ${:02x}", value),
+            Operation::SubMemRr(register) => write!(f, "SUB ({:?})", register),
+            Operation::SbcR(register) => write!(f, "SBC {:?}", register),
+            Operation::SbcD8(value) => write!(f, "SBC ${:02x}", value),
+            Operation::SbcMemRr(register) => write!(f, "SBC ({:?})", register),
+            Operation::AndR(register) => write!(f, "AND {:?}", register),
+            Operation::AndD8(value) => write!(f, "AND ${:02x}", value),
+            Operation::AndMemRr(register) => write!(f, "AND ({:?})", register),
+            Operation::XorR(register) => write!(f, "XOR {:?}", register),
+            Operation::XorD8(value) => write!(f, "XOR ${:02x}", value),
+            Operation::XorMemRr(register) => write!(f, "XOR ({:?})", register),
+            Operation::OrR(register) => write!(f, "OR {:?}", register),
+            Operation::OrD8(value) => write!(f, "OR ${:02x}", value),
+            Operation::OrMemRr(register) => write!(f, "OR ({:?})", register),
+            Operation::CpR(register) => write!(f, "CP {:?}", register),
+            Operation::CpD8(value) => write!(f, "CP ${:02x}", value),
+            Operation::CpMemRr(register) => write!(f, "CP ({:?})", register),
+            Operation::IncR(register) => write!(f, "INC {:?}", register),
+            Operation::IncMemRr(register) => write!(f, "INC ({:?})", register),
+            Operation::IncRr(register) => write!(f, "INC {:?}", register),
+            Operation::IncSp => write!(f, "INC SP"),
+            Operation::DecR(register) => write!(f, "DEC {:?}", register),
+            Operation::DecMemRr(register) => write!(f, "DEC ({:?})", register),
+            Operation::DecRr(register) => write!(f, "DEC {:?}", register),
+            Operation::DecSp => write!(f, "DEC SP"),
+            Operation::PushRr(register) => write!(f, "PUSH {:?}", register),
+            Operation::PopRr(register) => write!(f, "POP {:?}", register),
+            Operation::Jp(Some(flag), truthy, location) => {
+                write!(f, "JP {}, ${:04x}", condition(flag, truthy), location)
+            }
+            Operation::Jp(None, _, location) => write!(f, "JP ${:04x}", location),
+            Operation::Jr(Some(flag), truthy, offset) => {
+                write!(f, "JR {}, {:+}", condition(flag, truthy), offset)
+            }
+            Operation::Jr(None, _, offset) => write!(f, "JR {:+}", offset),
+            Operation::JpRr(register) => write!(f, "JP {:?}", register),
+            Operation::Call(location) => write!(f, "CALL ${:04x}", location),
+            Operation::CallFA16(flag, truthy, location) => {
+                write!(f, "CALL {}, ${:04x}", condition(flag, truthy), location)
+            }
+            Operation::Scf => write!(f, "SCF"),
+            Operation::Cpl => write!(f, "CPL"),
+            Operation::Rlca => write!(f, "RLCA"),
+            Operation::Rla => write!(f, "RLA"),
+            Operation::Rrca => write!(f, "RRCA"),
+            Operation::Rra => write!(f, "RRA"),
+            Operation::RlcR(register) => write!(f, "RLC {:?}", register),
+            Operation::RlcMemRr(register) => write!(f, "RLC ({:?})", register),
+            Operation::RlR(register) => write!(f, "RL {:?}", register),
+            Operation::RlMemRr(register) => write!(f, "RL ({:?})", register),
+            Operation::RrcR(register) => write!(f, "RRC {:?}", register),
+            Operation::RrcMemRr(register) => write!(f, "RRC ({:?})", register),
+            Operation::RrR(register) => write!(f, "RR {:?}", register),
+            Operation::RrMemRr(register) => write!(f, "RR ({:?})", register),
+            Operation::SlaR(register) => write!(f, "SLA {:?}", register),
+            Operation::SlaMemRr(register) => write!(f, "SLA ({:?})", register),
+            Operation::SraR(register) => write!(f, "SRA {:?}", register),
+            Operation::SraMemRr(register) => write!(f, "SRA ({:?})", register),
+            Operation::SwapR(register) => write!(f, "SWAP {:?}", register),
+            Operation::SwapMemRr(register) => write!(f, "SWAP ({:?})", register),
+            Operation::SrlR(register) => write!(f, "SRL {:?}", register),
+            Operation::SrlMemRr(register) => write!(f, "SRL ({:?})", register),
+            Operation::BitR(bit, register) => write!(f, "BIT {}, {:?}", bit, register),
+            Operation::BitMemRr(bit, register) => write!(f, "BIT {}, ({:?})", bit, register),
+            Operation::ResR(bit, register) => write!(f, "RES {}, {:?}", bit, register),
+            Operation::ResMemRr(bit, register) => write!(f, "RES {}, ({:?})", bit, register),
+            Operation::SetR(bit, register) => write!(f, "SET {}, {:?}", bit, register),
+            Operation::SetMemRr(bit, register) => write!(f, "SET {}, ({:?})", bit, register),
+            Operation::Illegal => write!(f, "???"),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.operation.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bus::FlatMemory;
+    use crate::cpu_registers::{CombinedRegister, GeneralRegister};
+    use crate::flag_register::FlagRegisterValue;
+    use crate::instruction::{disassemble, Instruction, Operation};
+
+    #[test]
+    fn decodes_immediate_operands() {
+        let memory = FlatMemory::with_program(&[0x01, 0x34, 0x12]);
+        let instruction = Instruction::decode(&memory, 0);
+
+        assert_eq!(
+            instruction.operation,
+            Operation::LdRrD16(CombinedRegister::BC, 0x1234)
+        );
+        assert_eq!(instruction.length, 3);
+        assert_eq!(instruction.cycles, 12);
+        assert_eq!(instruction.to_string(), "LD BC, $1234");
+    }
+
+    #[test]
+    fn decodes_prefixed_instructions() {
+        let memory = FlatMemory::with_program(&[0xCB, 0x7E]);
+        let instruction = Instruction::decode(&memory, 0);
+
+        assert!(instruction.prefixed);
+        assert_eq!(instruction.opcode, 0x7E);
+        assert_eq!(
+            instruction.operation,
+            Operation::BitMemRr(7, CombinedRegister::HL)
+        );
+        assert_eq!(instruction.length, 2);
+        assert_eq!(instruction.cycles, 12);
+        assert_eq!(instruction.operation.mnemonic(), "BIT");
+    }
+
+    #[test]
+    fn conditional_branches_carry_their_extra_cycles() {
+        let memory = FlatMemory::with_program(&[0x20, 0xFE]);
+        let instruction = Instruction::decode(&memory, 0);
+
+        assert_eq!(
+            instruction.operation,
+            Operation::Jr(Some(FlagRegisterValue::ZERO), false, -2)
+        );
+        assert_eq!(instruction.cycles, 8);
+        assert_eq!(instruction.branch_cycles, 4);
+        assert_eq!(instruction.to_string(), "JR NZ, -2");
+    }
+
+    #[test]
+    fn disassembles_consecutive_instructions() {
+        let memory = FlatMemory::with_program(&[0x3E, 0x42, 0xE0, 0x80, 0xCB, 0x37, 0xC9]);
+        let listing: Vec<(usize, String)> = disassemble(&memory, 0, 4)
+            .iter()
+            .map(|(location, instruction)| (*location, instruction.to_string()))
+            .collect();
+
+        assert_eq!(
+            listing,
+            vec![
+                (0, "LD A, $42".to_string()),
+                (2, "LDH ($80), A".to_string()),
+                (4, "SWAP A".to_string()),
+                (6, "RET".to_string()),
+            ]
+        );
+        assert_eq!(
+            Instruction::decode(&memory, 0).operation,
+            Operation::LdRD8(GeneralRegister::A, 0x42)
+        );
+    }
+}
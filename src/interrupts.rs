@@ -0,0 +1,61 @@
+use bitflags::bitflags;
+
+pub const INTERRUPT_FLAG_LOCATION: usize = 0xff0f;
+pub const INTERRUPT_ENABLE_LOCATION: usize = 0xffff;
+
+// T-cycles spent pushing PC and jumping to the handler
+pub const INTERRUPT_DISPATCH_CYCLES: u32 = 20;
+
+bitflags! {
+    #[derive(Default)]
+    pub struct Interrupt: u8 {
+        const VBLANK   = 1 << 0;
+        const LCD_STAT = 1 << 1;
+        const TIMER    = 1 << 2;
+        const SERIAL   = 1 << 3;
+        const JOYPAD   = 1 << 4;
+    }
+}
+
+impl Interrupt {
+    /// The interrupt that gets serviced first out of everything set. The
+    /// lowest bit always wins, so VBlank has the highest priority.
+    pub fn highest_priority(&self) -> Option<Interrupt> {
+        let bits = self.bits();
+
+        if bits == 0 {
+            None
+        } else {
+            Interrupt::from_bits(bits & bits.wrapping_neg())
+        }
+    }
+
+    /// Location of the handler for a single interrupt: 0x40, 0x48, 0x50,
+    /// 0x58 or 0x60
+    pub fn vector(&self) -> u16 {
+        0x40 + 8 * self.bits().trailing_zeros() as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interrupts::Interrupt;
+
+    #[test]
+    fn highest_priority() {
+        assert!(Interrupt::empty().highest_priority().is_none());
+        assert!(Interrupt::all().highest_priority() == Some(Interrupt::VBLANK));
+        assert!(
+            (Interrupt::JOYPAD | Interrupt::TIMER).highest_priority() == Some(Interrupt::TIMER)
+        );
+    }
+
+    #[test]
+    fn vector() {
+        assert!(Interrupt::VBLANK.vector() == 0x40);
+        assert!(Interrupt::LCD_STAT.vector() == 0x48);
+        assert!(Interrupt::TIMER.vector() == 0x50);
+        assert!(Interrupt::SERIAL.vector() == 0x58);
+        assert!(Interrupt::JOYPAD.vector() == 0x60);
+    }
+}
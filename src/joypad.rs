@@ -0,0 +1,121 @@
+pub const JOYPAD_LOCATION: usize = 0xff00;
+
+const SELECT_DIRECTIONS: u8 = 0b0001_0000;
+const SELECT_BUTTONS: u8 = 0b0010_0000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    Right,
+    Left,
+    Up,
+    Down,
+    A,
+    B,
+    Select,
+    Start,
+}
+
+impl Button {
+    /// Bit in the low nibble of P1 this button pulls low
+    fn line(&self) -> u8 {
+        match self {
+            Button::Right | Button::A => 1 << 0,
+            Button::Left | Button::B => 1 << 1,
+            Button::Up | Button::Select => 1 << 2,
+            Button::Down | Button::Start => 1 << 3,
+        }
+    }
+
+    /// Bit of P1 that has to be low for this button to be read
+    fn group(&self) -> u8 {
+        match self {
+            Button::Right | Button::Left | Button::Up | Button::Down => SELECT_DIRECTIONS,
+            _ => SELECT_BUTTONS,
+        }
+    }
+}
+
+/// The P1 register. Buttons are active low, and only the groups selected by
+/// clearing bit 4 (directions) or bit 5 (buttons) show up in the low nibble.
+pub struct Joypad {
+    select: u8,
+    directions: u8,
+    buttons: u8,
+}
+
+impl Default for Joypad {
+    fn default() -> Self {
+        Joypad {
+            select: SELECT_DIRECTIONS | SELECT_BUTTONS,
+            directions: 0,
+            buttons: 0,
+        }
+    }
+}
+
+impl Joypad {
+    pub fn read(&self) -> u8 {
+        let mut pressed = 0;
+
+        if self.select & SELECT_DIRECTIONS == 0 {
+            pressed |= self.directions;
+        }
+
+        if self.select & SELECT_BUTTONS == 0 {
+            pressed |= self.buttons;
+        }
+
+        0b1100_0000 | self.select | (!pressed & 0b0000_1111)
+    }
+
+    pub fn write(&mut self, value: u8) {
+        self.select = value & (SELECT_DIRECTIONS | SELECT_BUTTONS);
+    }
+
+    /// Returns true if the press pulled a selected line low, which is what
+    /// raises the joypad interrupt
+    pub fn press(&mut self, button: Button) -> bool {
+        let before = self.read();
+
+        match button.group() {
+            SELECT_DIRECTIONS => self.directions |= button.line(),
+            _ => self.buttons |= button.line(),
+        }
+
+        before & !self.read() & 0b0000_1111 != 0
+    }
+
+    pub fn release(&mut self, button: Button) {
+        match button.group() {
+            SELECT_DIRECTIONS => self.directions &= !button.line(),
+            _ => self.buttons &= !button.line(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::joypad::{Button, Joypad};
+
+    #[test]
+    fn nothing_selected_reads_released() {
+        let mut joypad = Joypad::default();
+
+        assert!(!joypad.press(Button::A));
+        assert_eq!(joypad.read(), 0xff);
+    }
+
+    #[test]
+    fn selected_group_reads_pressed() {
+        let mut joypad = Joypad::default();
+
+        joypad.write(0b0001_0000);
+
+        assert!(joypad.press(Button::Start));
+        assert!(!joypad.press(Button::Up));
+        assert_eq!(joypad.read(), 0b1101_0111);
+
+        joypad.release(Button::Start);
+        assert_eq!(joypad.read(), 0b1101_1111);
+    }
+}
@@ -0,0 +1,27 @@
+pub mod banked_memory;
+pub mod bus;
+pub mod camera;
+pub mod cartridge;
+pub mod cartridge_header;
+pub mod cartridge_type;
+pub mod cpu;
+pub mod cpu_registers;
+pub mod error;
+pub mod flag_register;
+pub mod infrared;
+pub mod instruction;
+pub mod interrupts;
+pub mod joypad;
+pub mod lcdc;
+pub mod licensee;
+pub mod mapper;
+pub mod mbc;
+pub mod ops;
+pub mod pixel;
+pub mod prefix_ops;
+pub mod save_file;
+pub mod tile;
+pub mod tile_dictionary;
+pub mod timer;
+pub mod utils;
+pub mod video;
@@ -1,50 +1,143 @@
 #[macro_use]
 extern crate glium;
 
-mod banked_memory;
-mod cartridge;
-mod cartridge_header;
-mod cartridge_type;
-mod cpu;
-mod cpu_registers;
-mod flag_register;
-mod lcdc;
-mod mbc;
-mod ops;
-mod pixel;
-mod prefix_ops;
 mod render_opengl;
-mod tile;
-mod tile_dictionary;
-mod utils;
-mod video;
 
+use std::convert::TryFrom;
 use std::env;
 use std::fs::File;
-use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::SystemTime;
+
+use oxide_gb::camera::CameraImage;
+use oxide_gb::cartridge::Cartridge;
+use oxide_gb::error::EmulatorError;
+use oxide_gb::infrared::LocalLink;
+use oxide_gb::mbc::MBC;
+use oxide_gb::save_file::SaveFile;
+use oxide_gb::video::Video;
 
 use crate::render_opengl::render;
-use crate::video::Video;
 
-fn read_cartridge() -> impl AsRef<Path> {
-    let mut cartridge_file = None;
+const INFRARED_VARIABLE: &str = "OXIDE_GB_INFRARED";
+const CAMERA_VARIABLE: &str = "OXIDE_GB_CAMERA";
+const SAVE_DIRECTORY_VARIABLE: &str = "OXIDE_GB_SAVE_DIR";
+
+/// The ROM named by the first argument, after the program's own name
+fn read_cartridge() -> Result<PathBuf, EmulatorError> {
+    env::args_os()
+        .nth(1)
+        .map(PathBuf::from)
+        .ok_or(EmulatorError::MissingRom)
+}
+
+/// Links the cartridge's infrared port to another instance on this
+/// machine, given `OXIDE_GB_INFRARED=<our port>:<their port>`
+fn infrared_link() -> Result<Option<LocalLink>, EmulatorError> {
+    let ports = match env::var(INFRARED_VARIABLE) {
+        Ok(ports) => ports,
+        Err(_) => return Ok(None),
+    };
+
+    let parsed = ports
+        .split_once(':')
+        .and_then(|(port, peer_port)| Some((port.parse().ok()?, peer_port.parse().ok()?)));
 
-    for arg in env::args() {
-        cartridge_file = Some(arg);
+    match parsed {
+        Some((port, peer_port)) => LocalLink::new(port, peer_port)
+            .map(Some)
+            .map_err(EmulatorError::InfraredLink),
+        None => {
+            eprintln!(
+                "Ignoring {}={}, expected <port>:<peer port>",
+                INFRARED_VARIABLE, ports
+            );
+            Ok(None)
+        }
     }
+}
 
-    if cartridge_file.is_none() {
-        panic!("Must provide a cartridge file path");
+fn open(path: &Path) -> Result<File, EmulatorError> {
+    File::open(path).map_err(|error| EmulatorError::File {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+/// The picture camera cartridges see, from a PNG named by `OXIDE_GB_CAMERA`
+fn camera_image() -> Result<Option<CameraImage>, EmulatorError> {
+    match env::var_os(CAMERA_VARIABLE) {
+        Some(path) => Ok(Some(CameraImage::from_png(open(Path::new(&path))?)?)),
+        None => Ok(None),
     }
+}
 
-    cartridge_file.unwrap()
+/// Where battery-backed RAM is kept, next to the ROM unless
+/// `OXIDE_GB_SAVE_DIR` names somewhere else
+fn save_file(rom: &Path) -> SaveFile {
+    let directory = env::var_os(SAVE_DIRECTORY_VARIABLE).map(PathBuf::from);
+
+    SaveFile::for_rom(rom, directory.as_deref())
 }
 
-fn main() -> io::Result<()> {
+fn run() -> Result<(), EmulatorError> {
     let _v = Video::default();
 
-    render(File::open(read_cartridge()).unwrap());
+    let rom = read_cartridge()?;
+    let cartridge = Cartridge::try_from(open(&rom)?)?;
+
+    for warning in &cartridge.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+
+    let has_battery = cartridge.header.cartridge_type.has_battery();
+    let mut memory = MBC::try_from(cartridge)?;
+
+    // Without a battery the RAM is gone as soon as the power is
+    let save_file = if has_battery {
+        let mut save_file = save_file(&rom);
+
+        match save_file.load() {
+            Ok(data) => {
+                if let Some(data) = data {
+                    memory.load_save_data(&data);
+                    memory.sync_clock(SystemTime::now());
+                }
+
+                Some(save_file)
+            }
+            // Carry on without saving rather than write over whatever is
+            // there
+            Err(error) => {
+                eprintln!(
+                    "Warning: could not read {}, saving is off: {}",
+                    save_file.path().display(),
+                    error
+                );
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    if let Some(link) = infrared_link()? {
+        memory.set_infrared_link(Box::new(link));
+    }
+
+    if let Some(image) = camera_image()? {
+        memory.set_camera_image(image);
+    }
+
+    render(memory, save_file);
 
     Ok(())
 }
+
+fn main() {
+    if let Err(error) = run() {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
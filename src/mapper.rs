@@ -0,0 +1,193 @@
+pub mod huc1;
+pub mod huc3;
+pub mod mbc1;
+pub mod mbc2;
+pub mod mbc3;
+pub mod mbc5;
+pub mod mbc6;
+pub mod mbc7;
+pub mod mmm01;
+pub mod pocket_camera;
+pub mod rom_only;
+pub mod rtc_save;
+pub mod tama5;
+
+use std::time::SystemTime;
+
+use crate::camera::CameraImage;
+use crate::cartridge::Cartridge;
+use crate::cartridge_type::CartridgeType;
+use crate::error::EmulatorError;
+use crate::infrared::InfraredLink;
+
+use self::huc1::Huc1;
+use self::huc3::Huc3;
+use self::mbc1::Mbc1;
+use self::mbc2::Mbc2;
+use self::mbc3::Mbc3;
+use self::mbc5::Mbc5;
+use self::mbc6::Mbc6;
+use self::mbc7::Mbc7;
+use self::mmm01::Mmm01;
+use self::pocket_camera::PocketCamera;
+use self::rom_only::RomOnly;
+use self::tama5::Tama5;
+
+/// Called with true when a cartridge's rumble motor turns on, and false
+/// when it turns off again
+pub type RumbleListener = Box<dyn FnMut(bool)>;
+
+/// Called with the number of the melody a cartridge's speaker starts
+/// playing
+pub type ToneListener = Box<dyn FnMut(u8)>;
+
+/// The hardware on the cartridge itself. The system bus hands it everything
+/// in 0x0000-0x7fff, where writes go to its control registers, and the
+/// external RAM window at 0xa000-0xbfff. Locations are relative to the start
+/// of each window.
+pub trait Mapper {
+    fn read_rom(&self, location: usize) -> u8;
+    fn write_rom(&mut self, location: usize, value: u8);
+
+    fn read_ram(&self, location: usize) -> u8;
+    fn write_ram(&mut self, location: usize, value: u8);
+
+    /// Advances anything on the cartridge that keeps time by a number of
+    /// T-cycles at normal speed
+    fn tick(&mut self, _cycles: u32) {}
+
+    /// Everything the cartridge keeps around between sessions, in the
+    /// layout it's written to a save file
+    fn save_data(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Restores what `save_data` produced. Anything past the end of the
+    /// cartridge's memory is ignored.
+    fn load_save_data(&mut self, _data: &[u8]) {}
+
+    /// Runs a real-time clock restored by `load_save_data` forward to `now`,
+    /// to cover the time that passed while the emulator wasn't running
+    fn sync_clock(&mut self, _now: SystemTime) {}
+
+    /// Carts without a rumble motor never call the listener
+    fn set_rumble_listener(&mut self, _listener: RumbleListener) {}
+
+    /// Feeds a cartridge's accelerometer, in g along each axis. Positive x
+    /// is tilting to the right and positive y is tilting towards the player.
+    fn set_tilt(&mut self, _x: f32, _y: f32) {}
+
+    /// Points a cartridge's infrared port at something. Until then its
+    /// receiver never sees any light.
+    fn set_infrared_link(&mut self, _link: Box<dyn InfraredLink>) {}
+
+    /// Carts without a speaker never call the listener
+    fn set_tone_listener(&mut self, _listener: ToneListener) {}
+
+    /// Gives a camera cartridge's sensor something to look at. Until then
+    /// it only sees black.
+    fn set_camera_image(&mut self, _image: CameraImage) {}
+}
+
+/// Picks the mapper the header says the cartridge is built around
+pub fn from_cartridge(cartridge: Cartridge) -> Result<Box<dyn Mapper>, EmulatorError> {
+    // MMM01 compilations start with the header of one of their games
+    if mmm01::menu_header(&cartridge.data).is_some() {
+        return Ok(Box::new(Mmm01::from(cartridge)));
+    }
+
+    match cartridge.header.cartridge_type {
+        CartridgeType::RomOnly | CartridgeType::RomRam | CartridgeType::RomRamBattery => {
+            Ok(Box::new(RomOnly::from(cartridge)))
+        }
+        CartridgeType::MBC1 | CartridgeType::MBC1Ram | CartridgeType::MBC1RamBattery => {
+            Ok(Box::new(Mbc1::from(cartridge)))
+        }
+        CartridgeType::MBC2 | CartridgeType::MBC2Battery => Ok(Box::new(Mbc2::from(cartridge))),
+        CartridgeType::MBC3
+        | CartridgeType::MBC3Ram
+        | CartridgeType::MBC3RamBattery
+        | CartridgeType::MBC3TimerBattery
+        | CartridgeType::MBC3TimerRamBattery => Ok(Box::new(Mbc3::from(cartridge))),
+        CartridgeType::MBC5
+        | CartridgeType::MBC5Ram
+        | CartridgeType::MBC5RamBattery
+        | CartridgeType::MBC5Rumble
+        | CartridgeType::MBC5RumbleRam
+        | CartridgeType::MBC5RumbleRamBattery => Ok(Box::new(Mbc5::from(cartridge))),
+        CartridgeType::MBC6 => Ok(Box::new(Mbc6::from(cartridge))),
+        CartridgeType::MBC7SensorRumbleRamBattery => Ok(Box::new(Mbc7::from(cartridge))),
+        CartridgeType::MMM01 | CartridgeType::MMM01Ram | CartridgeType::MMM01RamBattery => {
+            Ok(Box::new(Mmm01::from(cartridge)))
+        }
+        CartridgeType::PocketCamera => Ok(Box::new(PocketCamera::from(cartridge))),
+        CartridgeType::BandaiTama5 => Ok(Box::new(Tama5::from(cartridge))),
+        CartridgeType::HuC1RamBattery => Ok(Box::new(Huc1::from(cartridge))),
+        CartridgeType::HuC3 => Ok(Box::new(Huc3::from(cartridge))),
+        cartridge_type => Err(EmulatorError::UnsupportedCartridge(cartridge_type)),
+    }
+}
+
+/// A ROM where every bank starts with its own number, low byte first
+#[cfg(test)]
+pub(crate) fn numbered_rom(banks: usize) -> crate::banked_memory::BankedMemory {
+    use crate::banked_memory::{BankedMemory, ROM_BANK_SIZE};
+
+    let mut rom = BankedMemory::new(0, ROM_BANK_SIZE, banks);
+
+    for (number, bank) in rom.banks.iter_mut().enumerate() {
+        bank[0] = number as u8;
+        bank[1] = (number >> 8) as u8;
+    }
+
+    rom
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use crate::cartridge::Cartridge;
+    use crate::cartridge_type::CartridgeType;
+    use crate::error::EmulatorError;
+    use crate::mapper;
+
+    fn cartridge_of_type(cartridge_type: u8) -> Cartridge {
+        let mut data = vec![0x00; 0x8000];
+        data[0x147] = cartridge_type;
+
+        Cartridge::try_from(data).unwrap()
+    }
+
+    #[test]
+    fn selects_mapper_from_header() {
+        let mut mapper = mapper::from_cartridge(cartridge_of_type(0x01)).unwrap();
+
+        // Unlike a ROM only cart, MBC1 keeps RAM disabled until asked
+        mapper.write_rom(0x0000, 0x00);
+        assert_eq!(mapper.read_ram(0x0000), 0xff);
+    }
+
+    #[test]
+    fn missing_ram_reads_open_bus() {
+        for cartridge_type in [0x00, 0x03] {
+            let mut mapper = mapper::from_cartridge(cartridge_of_type(cartridge_type)).unwrap();
+
+            mapper.write_rom(0x0000, 0x0a);
+            mapper.write_ram(0x0000, 0x42);
+
+            assert_eq!(mapper.read_ram(0x0000), 0xff);
+            assert!(mapper.save_data().is_empty());
+        }
+    }
+
+    #[test]
+    fn rejects_unsupported_cartridge_types() {
+        let result = mapper::from_cartridge(cartridge_of_type(0x04));
+
+        assert!(matches!(
+            result,
+            Err(EmulatorError::UnsupportedCartridge(CartridgeType::Unknown))
+        ));
+    }
+}
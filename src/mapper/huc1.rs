@@ -0,0 +1,175 @@
+use crate::banked_memory::BankedMemory;
+use crate::cartridge::Cartridge;
+use crate::infrared::{InfraredLink, PolledLink};
+use crate::mapper::Mapper;
+
+// Written to 0x0000-0x1fff to map the infrared port in place of RAM
+pub const INFRARED_SELECT_VALUE: u8 = 0x0e;
+
+// What the infrared port reads with and without light on the receiver
+pub const INFRARED_LIGHT: u8 = 0xc1;
+pub const INFRARED_DARK: u8 = 0xc0;
+
+/// Hudson's HuC1, banked much like an MBC1 but with an infrared LED and
+/// receiver that can be mapped over the RAM. The RAM itself has no enable,
+/// any value other than the infrared select maps it back in.
+pub struct Huc1 {
+    rom: BankedMemory,
+    ram: BankedMemory,
+    rom_bank: usize,
+    ram_bank: usize,
+    infrared_selected: bool,
+    infrared: PolledLink,
+}
+
+impl From<Cartridge> for Huc1 {
+    fn from(cartridge: Cartridge) -> Self {
+        let ram = BankedMemory::of_size(cartridge.header.ram_size);
+
+        Huc1::new(BankedMemory::from(cartridge), ram)
+    }
+}
+
+impl Huc1 {
+    pub fn new(rom: BankedMemory, ram: BankedMemory) -> Self {
+        Huc1 {
+            rom,
+            ram,
+            rom_bank: 1,
+            ram_bank: 0,
+            infrared_selected: false,
+            infrared: PolledLink::default(),
+        }
+    }
+}
+
+impl Mapper for Huc1 {
+    fn read_rom(&self, location: usize) -> u8 {
+        match location {
+            0x0000..=0x3fff => self.rom.value_in_bank(0, location),
+            _ => {
+                let bank = self.rom_bank & (self.rom.banks.len().next_power_of_two() - 1);
+                self.rom.value_in_bank(bank, location - 0x4000)
+            }
+        }
+    }
+
+    fn write_rom(&mut self, location: usize, value: u8) {
+        match location {
+            0x0000..=0x1fff => self.infrared_selected = value & 0x0f == INFRARED_SELECT_VALUE,
+            0x2000..=0x3fff => {
+                self.rom_bank = match value & 0b0011_1111 {
+                    0 => 1,
+                    bank => bank.into(),
+                }
+            }
+            0x4000..=0x5fff => self.ram_bank = usize::from(value & 0b0000_0011),
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, location: usize) -> u8 {
+        if self.infrared_selected {
+            return if self.infrared.receiving() {
+                INFRARED_LIGHT
+            } else {
+                INFRARED_DARK
+            };
+        }
+
+        self.ram.value_in_bank(self.ram_bank, location)
+    }
+
+    fn write_ram(&mut self, location: usize, value: u8) {
+        if !self.infrared_selected {
+            self.ram.set_in_bank(self.ram_bank, location, value);
+        } else {
+            self.infrared.set_led(value & 0b0000_0001 != 0);
+        }
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        // Games only look at the receiver with the port mapped in
+        if self.infrared_selected {
+            self.infrared.tick(cycles);
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.to_bytes()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        self.ram.load_bytes(data);
+    }
+
+    fn set_infrared_link(&mut self, link: Box<dyn InfraredLink>) {
+        self.infrared.connect(link);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::banked_memory::{BankedMemory, ROM_BANK_SIZE};
+    use crate::cartridge_header::RAM_BANK_SIZE;
+    use crate::infrared::{InfraredLink, POLL_CYCLES};
+    use crate::mapper::huc1::{Huc1, INFRARED_DARK, INFRARED_LIGHT, INFRARED_SELECT_VALUE};
+    use crate::mapper::Mapper;
+
+    /// Reflects the LED straight back into the receiver
+    struct Mirror(Rc<RefCell<bool>>);
+
+    impl InfraredLink for Mirror {
+        fn set_led(&mut self, on: bool) {
+            *self.0.borrow_mut() = on;
+        }
+
+        fn receiving(&mut self) -> bool {
+            *self.0.borrow()
+        }
+    }
+
+    fn get_huc1() -> Huc1 {
+        Huc1::new(
+            BankedMemory::new(0, ROM_BANK_SIZE, 64),
+            BankedMemory::new(0, RAM_BANK_SIZE, 4),
+        )
+    }
+
+    #[test]
+    fn ram_needs_no_enable_and_has_four_banks() {
+        let mut mbc = get_huc1();
+
+        mbc.write_rom(0x4000, 0x03);
+        mbc.write_ram(0x0000, 0x42);
+        mbc.write_rom(0x4000, 0x00);
+        assert_eq!(mbc.read_ram(0x0000), 0x00);
+
+        mbc.write_rom(0x4000, 0x03);
+        assert_eq!(mbc.read_ram(0x0000), 0x42);
+    }
+
+    #[test]
+    fn infrared_port_replaces_ram() {
+        let led = Rc::new(RefCell::new(false));
+        let mut mbc = get_huc1();
+
+        mbc.set_infrared_link(Box::new(Mirror(led.clone())));
+        mbc.write_ram(0x0000, 0x42);
+
+        mbc.write_rom(0x0000, INFRARED_SELECT_VALUE);
+        mbc.tick(POLL_CYCLES);
+        assert_eq!(mbc.read_ram(0x0000), INFRARED_DARK);
+
+        mbc.write_ram(0x0000, 0x01);
+        assert!(*led.borrow());
+        mbc.tick(POLL_CYCLES);
+        assert_eq!(mbc.read_ram(0x0000), INFRARED_LIGHT);
+
+        mbc.write_rom(0x0000, 0x0a);
+        assert_eq!(mbc.read_ram(0x0000), 0x42);
+    }
+}
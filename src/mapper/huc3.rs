@@ -0,0 +1,454 @@
+use std::time::SystemTime;
+
+use crate::banked_memory::BankedMemory;
+use crate::cartridge::Cartridge;
+use crate::cartridge_header::RAM_BANK_SIZE;
+use crate::cpu::CLOCK_MHZ;
+use crate::infrared::{InfraredLink, PolledLink};
+use crate::mapper::huc1::{INFRARED_DARK, INFRARED_LIGHT, INFRARED_SELECT_VALUE};
+use crate::mapper::rtc_save::RtcSave;
+use crate::mapper::{Mapper, ToneListener};
+use crate::mbc::RAM_ENABLE_VALUE;
+
+// What 0xa000-0xbfff is mapped to, picked by writing to 0x0000-0x1fff
+const RAM_READ_MODE: u8 = 0x00;
+const RAM_WRITE_MODE: u8 = RAM_ENABLE_VALUE;
+const COMMAND_MODE: u8 = 0x0b;
+const RESPONSE_MODE: u8 = 0x0c;
+const SEMAPHORE_MODE: u8 = 0x0d;
+const INFRARED_MODE: u8 = INFRARED_SELECT_VALUE;
+
+// The top nibble of a command, the bottom one is its argument
+const READ_COMMAND: u8 = 0x1;
+const WRITE_COMMAND: u8 = 0x3;
+const ADDRESS_LOW_COMMAND: u8 = 0x4;
+const ADDRESS_HIGH_COMMAND: u8 = 0x5;
+const EXTENDED_COMMAND: u8 = 0x6;
+
+const COPY_TIME_OUT: u8 = 0x0;
+const COPY_TIME_IN: u8 = 0x1;
+const STATUS: u8 = 0x2;
+const PLAY_TONE: u8 = 0xe;
+
+// Where the time is copied to and from in the clock's memory, as 3 nibbles
+// of minutes into the day then 3 nibbles of days, least significant first
+const TIME_ADDRESS: usize = 0x00;
+// The melody the speaker plays
+const TONE_ADDRESS: usize = 0x27;
+
+const MINUTES_PER_DAY: u16 = 60 * 24;
+
+// The clock appended to the end of a .sav, as the seconds, minutes and days
+// as 32 bit words followed by a 64 bit UNIX timestamp
+const FOOTER_SIZE: usize = 20;
+
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+struct Clock {
+    // Seconds into the current minute, which the game can't see
+    seconds: u8,
+    minutes: u16,
+    days: u16,
+}
+
+impl Clock {
+    fn tick_minute(&mut self) {
+        self.minutes += 1;
+
+        if self.minutes >= MINUTES_PER_DAY {
+            self.minutes = 0;
+            self.days = (self.days + 1) & 0xfff;
+        }
+    }
+
+    fn tick_second(&mut self) {
+        self.seconds += 1;
+
+        if self.seconds == 60 {
+            self.seconds = 0;
+            self.tick_minute();
+        }
+    }
+
+    fn advance(&mut self, seconds: u64) {
+        let elapsed = u64::from(self.seconds)
+            + u64::from(self.minutes) * 60
+            + u64::from(self.days) * u64::from(MINUTES_PER_DAY) * 60
+            + seconds;
+        let minutes = elapsed / 60;
+
+        self.seconds = (elapsed % 60) as u8;
+        self.minutes = (minutes % u64::from(MINUTES_PER_DAY)) as u16;
+        self.days = (minutes / u64::from(MINUTES_PER_DAY) % 0x1000) as u16;
+    }
+
+    fn to_nibbles(self) -> [u8; 6] {
+        let time = u32::from(self.minutes) | (u32::from(self.days) << 12);
+        [0, 1, 2, 3, 4, 5].map(|nibble| (time >> (nibble * 4)) as u8 & 0x0f)
+    }
+
+    fn from_nibbles(nibbles: &[u8]) -> Self {
+        let time = nibbles
+            .iter()
+            .rev()
+            .fold(0, |time, nibble| (time << 4) | u32::from(nibble & 0x0f));
+
+        Clock {
+            seconds: 0,
+            minutes: (time & 0xfff) as u16 % MINUTES_PER_DAY,
+            days: (time >> 12) as u16,
+        }
+    }
+}
+
+/// Hudson's HuC3, with a 7 bit ROM bank, 4 RAM banks and an infrared port
+/// like the HuC1. It also drives a speaker and a clock with 256 nibbles of
+/// its own memory, both run by commands the game sends through the RAM
+/// window.
+pub struct Huc3 {
+    rom: BankedMemory,
+    ram: BankedMemory,
+    rom_bank: usize,
+    ram_bank: usize,
+    mode: u8,
+    clock: Clock,
+    clock_memory: [u8; 0x100],
+    clock_address: u8,
+    // The last command written, which runs once the semaphore is cleared
+    command: u8,
+    response: u8,
+    // T-cycles into the current second
+    cycles: u32,
+    rtc_save: RtcSave,
+    infrared: PolledLink,
+    tone_listener: Option<ToneListener>,
+}
+
+impl From<Cartridge> for Huc3 {
+    fn from(cartridge: Cartridge) -> Self {
+        let ram = BankedMemory::of_size(cartridge.header.ram_size);
+
+        Huc3::new(BankedMemory::from(cartridge), ram)
+    }
+}
+
+impl Huc3 {
+    pub fn new(rom: BankedMemory, ram: BankedMemory) -> Self {
+        Huc3 {
+            rom,
+            ram,
+            rom_bank: 1,
+            ram_bank: 0,
+            mode: RAM_READ_MODE,
+            clock: Clock::default(),
+            clock_memory: [0; 0x100],
+            clock_address: 0,
+            command: 0,
+            response: 0,
+            cycles: 0,
+            rtc_save: RtcSave::default(),
+            infrared: PolledLink::default(),
+            tone_listener: None,
+        }
+    }
+
+    fn run_command(&mut self) {
+        let argument = self.command & 0x0f;
+        let address = usize::from(self.clock_address);
+
+        match self.command >> 4 {
+            READ_COMMAND => {
+                self.response = self.clock_memory[address];
+                self.clock_address = self.clock_address.wrapping_add(1);
+            }
+            WRITE_COMMAND => {
+                self.clock_memory[address] = argument;
+                self.clock_address = self.clock_address.wrapping_add(1);
+            }
+            ADDRESS_LOW_COMMAND => self.clock_address = (self.clock_address & 0xf0) | argument,
+            ADDRESS_HIGH_COMMAND => {
+                self.clock_address = (self.clock_address & 0x0f) | (argument << 4)
+            }
+            EXTENDED_COMMAND => match argument {
+                COPY_TIME_OUT => {
+                    self.clock_memory[TIME_ADDRESS..TIME_ADDRESS + 6]
+                        .copy_from_slice(&self.clock.to_nibbles());
+                }
+                COPY_TIME_IN => {
+                    self.clock =
+                        Clock::from_nibbles(&self.clock_memory[TIME_ADDRESS..TIME_ADDRESS + 6]);
+                    self.cycles = 0;
+                }
+                // Always reports the clock is running fine
+                STATUS => self.response = 0x01,
+                PLAY_TONE => {
+                    if let Some(listener) = &mut self.tone_listener {
+                        listener(self.clock_memory[TONE_ADDRESS]);
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    fn footer(&self) -> Vec<u8> {
+        RtcSave::footer(&[
+            u32::from(self.clock.seconds),
+            u32::from(self.clock.minutes),
+            u32::from(self.clock.days),
+        ])
+    }
+
+    fn load_footer(&mut self, footer: &[u8]) {
+        let words = self.rtc_save.load(footer, 3);
+
+        self.clock = Clock {
+            seconds: (words[0] % 60) as u8,
+            minutes: (words[1] % u32::from(MINUTES_PER_DAY)) as u16,
+            days: (words[2] & 0xfff) as u16,
+        };
+    }
+}
+
+impl Mapper for Huc3 {
+    fn read_rom(&self, location: usize) -> u8 {
+        match location {
+            0x0000..=0x3fff => self.rom.value_in_bank(0, location),
+            _ => {
+                let bank = self.rom_bank & (self.rom.banks.len().next_power_of_two() - 1);
+                self.rom.value_in_bank(bank, location - 0x4000)
+            }
+        }
+    }
+
+    fn write_rom(&mut self, location: usize, value: u8) {
+        match location {
+            0x0000..=0x1fff => self.mode = value & 0x0f,
+            0x2000..=0x3fff => {
+                self.rom_bank = match value & 0b0111_1111 {
+                    0 => 1,
+                    bank => bank.into(),
+                }
+            }
+            0x4000..=0x5fff => self.ram_bank = usize::from(value & 0b0000_0011),
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, location: usize) -> u8 {
+        match self.mode {
+            RAM_READ_MODE | RAM_WRITE_MODE => self.ram.value_in_bank(self.ram_bank, location),
+            // The command that was run, along with what it returned
+            RESPONSE_MODE => (self.command & 0xf0) | self.response,
+            // Commands finish straight away, so the clock is always ready
+            SEMAPHORE_MODE => 0x01,
+            INFRARED_MODE if self.infrared.receiving() => INFRARED_LIGHT,
+            INFRARED_MODE => INFRARED_DARK,
+            _ => 0xff,
+        }
+    }
+
+    fn write_ram(&mut self, location: usize, value: u8) {
+        match self.mode {
+            RAM_WRITE_MODE => self.ram.set_in_bank(self.ram_bank, location, value),
+            COMMAND_MODE => self.command = value & 0x7f,
+            SEMAPHORE_MODE if value & 0b0000_0001 == 0 => self.run_command(),
+            INFRARED_MODE => self.infrared.set_led(value & 0b0000_0001 != 0),
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        if self.mode == INFRARED_MODE {
+            self.infrared.tick(cycles);
+        }
+
+        self.cycles += cycles;
+
+        while self.cycles >= CLOCK_MHZ {
+            self.cycles -= CLOCK_MHZ;
+            self.clock.tick_second();
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        let mut data = self.ram.to_bytes();
+        data.extend(self.footer());
+        data
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        let footer_size = if data.len() % RAM_BANK_SIZE == FOOTER_SIZE {
+            FOOTER_SIZE
+        } else {
+            0
+        };
+        let (ram, footer) = data.split_at(data.len() - footer_size);
+
+        self.ram.load_bytes(ram);
+
+        if footer_size > 0 {
+            self.load_footer(footer);
+        }
+    }
+
+    fn sync_clock(&mut self, now: SystemTime) {
+        if let Some(seconds) = self.rtc_save.elapsed(now) {
+            self.clock.advance(seconds);
+        }
+    }
+
+    fn set_infrared_link(&mut self, link: Box<dyn InfraredLink>) {
+        self.infrared.connect(link);
+    }
+
+    fn set_tone_listener(&mut self, listener: ToneListener) {
+        self.tone_listener = Some(listener);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::banked_memory::{BankedMemory, ROM_BANK_SIZE};
+    use crate::cartridge_header::RAM_BANK_SIZE;
+    use crate::cpu::CLOCK_MHZ;
+    use crate::mapper::huc3::{
+        Clock, Huc3, COMMAND_MODE, RAM_READ_MODE, RAM_WRITE_MODE, RESPONSE_MODE, SEMAPHORE_MODE,
+    };
+    use crate::mapper::Mapper;
+
+    fn get_huc3() -> Huc3 {
+        Huc3::new(
+            BankedMemory::new(0, ROM_BANK_SIZE, 128),
+            BankedMemory::new(0, RAM_BANK_SIZE, 4),
+        )
+    }
+
+    /// Sends one command the way games do, and returns the response
+    fn send(mbc: &mut Huc3, command: u8) -> u8 {
+        mbc.write_rom(0x0000, COMMAND_MODE);
+        mbc.write_ram(0x0000, command);
+        mbc.write_rom(0x0000, SEMAPHORE_MODE);
+        mbc.write_ram(0x0000, 0xfe);
+        mbc.write_rom(0x0000, RESPONSE_MODE);
+        mbc.read_ram(0x0000)
+    }
+
+    fn read_time(mbc: &mut Huc3) -> Vec<u8> {
+        send(mbc, 0x60);
+        send(mbc, 0x40);
+        send(mbc, 0x50);
+        (0..6).map(|_| send(mbc, 0x10) & 0x0f).collect()
+    }
+
+    #[test]
+    fn ram_is_read_only_until_write_mode() {
+        let mut mbc = get_huc3();
+
+        mbc.write_rom(0x0000, RAM_READ_MODE);
+        mbc.write_ram(0x0000, 0x42);
+        assert_eq!(mbc.read_ram(0x0000), 0x00);
+
+        mbc.write_rom(0x0000, RAM_WRITE_MODE);
+        mbc.write_ram(0x0000, 0x42);
+        mbc.write_rom(0x0000, RAM_READ_MODE);
+        assert_eq!(mbc.read_ram(0x0000), 0x42);
+    }
+
+    #[test]
+    fn clock_memory_is_read_and_written_a_nibble_at_a_time() {
+        let mut mbc = get_huc3();
+
+        send(&mut mbc, 0x40);
+        send(&mut mbc, 0x51);
+        send(&mut mbc, 0x3a);
+        send(&mut mbc, 0x35);
+
+        send(&mut mbc, 0x40);
+        assert_eq!(send(&mut mbc, 0x10), 0x1a);
+        assert_eq!(send(&mut mbc, 0x10), 0x15);
+        assert_eq!(mbc.clock_address, 0x12);
+    }
+
+    #[test]
+    fn time_counts_minutes_and_days() {
+        let mut mbc = get_huc3();
+        mbc.clock = Clock {
+            seconds: 59,
+            minutes: 1439,
+            days: 0x123,
+        };
+
+        mbc.tick(CLOCK_MHZ);
+
+        assert_eq!(read_time(&mut mbc), vec![0, 0, 0, 4, 2, 1]);
+    }
+
+    #[test]
+    fn time_can_be_set() {
+        let mut mbc = get_huc3();
+
+        send(&mut mbc, 0x40);
+        send(&mut mbc, 0x50);
+        // 0x05a minutes and 0x002 days
+        for nibble in [0xa, 0x5, 0x0, 0x2, 0x0, 0x0].iter() {
+            send(&mut mbc, 0x30 | nibble);
+        }
+        send(&mut mbc, 0x61);
+
+        assert_eq!(
+            mbc.clock,
+            Clock {
+                seconds: 0,
+                minutes: 0x5a,
+                days: 2
+            }
+        );
+    }
+
+    #[test]
+    fn play_tone_notifies_listener() {
+        let tones = Rc::new(RefCell::new(Vec::new()));
+        let listener_tones = tones.clone();
+        let mut mbc = get_huc3();
+
+        mbc.set_tone_listener(Box::new(move |tone| listener_tones.borrow_mut().push(tone)));
+        send(&mut mbc, 0x47);
+        send(&mut mbc, 0x52);
+        send(&mut mbc, 0x33);
+        send(&mut mbc, 0x6e);
+
+        assert_eq!(*tones.borrow(), vec![3]);
+    }
+
+    #[test]
+    fn clock_is_saved_and_catches_up() {
+        let mut mbc = get_huc3();
+        mbc.clock = Clock {
+            seconds: 30,
+            minutes: 10,
+            days: 5,
+        };
+
+        let data = mbc.save_data();
+        assert_eq!(data.len(), 4 * RAM_BANK_SIZE + 20);
+
+        let mut restored = get_huc3();
+        restored.load_save_data(&data);
+        let saved_at = UNIX_EPOCH + Duration::from_secs(restored.rtc_save.saved_at().unwrap());
+        restored.sync_clock(saved_at + Duration::from_secs(90));
+
+        assert_eq!(
+            restored.clock,
+            Clock {
+                seconds: 0,
+                minutes: 12,
+                days: 5
+            }
+        );
+    }
+}
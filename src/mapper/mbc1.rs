@@ -0,0 +1,242 @@
+use crate::banked_memory::BankedMemory;
+use crate::cartridge::Cartridge;
+use crate::cartridge_header::{NINTENDO_LOGO, NINTENDO_LOGO_END, NINTENDO_LOGO_LOCATION};
+use crate::mapper::Mapper;
+use crate::mbc::RAM_ENABLE_VALUE;
+
+// MBC1M multicarts are always 1 MiB, with a game starting every 16 banks
+const MULTICART_BANKS: usize = 64;
+const MULTICART_GAME_BANKS: usize = 16;
+
+/// The MBC1 banking controller. Two registers pick the banks: BANK1 holds
+/// the low 5 bits of the ROM bank, and BANK2 either the 2 bits above those
+/// or, in mode 1, the RAM bank as well as the bank mapped at 0x0000.
+pub struct Mbc1 {
+    rom: BankedMemory,
+    ram: BankedMemory,
+    ram_enabled: bool,
+    bank1: u8,
+    bank2: u8,
+    mode: u8,
+    // MBC1M carts don't wire up bit 4 of BANK1, so BANK2 sits one bit lower
+    multicart: bool,
+}
+
+impl From<Cartridge> for Mbc1 {
+    fn from(cartridge: Cartridge) -> Self {
+        let ram = BankedMemory::of_size(cartridge.header.ram_size);
+        let rom = BankedMemory::from(cartridge);
+
+        Mbc1::new(rom, ram)
+    }
+}
+
+impl Mapper for Mbc1 {
+    fn read_rom(&self, location: usize) -> u8 {
+        match location {
+            0x0000..=0x3fff => self.rom.value_in_bank(self.low_rom_bank(), location),
+            _ => self
+                .rom
+                .value_in_bank(self.high_rom_bank(), location - 0x4000),
+        }
+    }
+
+    fn write_rom(&mut self, location: usize, value: u8) {
+        match location {
+            // RAM is enabled when the lowest 4 bits written to this range
+            // are equal to 0x0a
+            0x0000..=0x1fff => self.ram_enabled = (value & 0b0000_1111) == RAM_ENABLE_VALUE,
+
+            // The lowest 5 bits, where 0 is bumped up to 1. Only the full 5
+            // bits are checked, so on large ROMs banks 0x20, 0x40 and 0x60
+            // can't be mapped here.
+            0x2000..=0x3fff => {
+                self.bank1 = match value & 0b0001_1111 {
+                    0 => 1,
+                    bank => bank,
+                }
+            }
+
+            0x4000..=0x5fff => self.bank2 = value & 0b0000_0011,
+            _ => self.mode = value & 0b0000_0001,
+        }
+    }
+
+    fn read_ram(&self, location: usize) -> u8 {
+        if !self.ram_enabled {
+            return 0xff;
+        }
+
+        self.ram.value_in_bank(self.ram_bank(), location)
+    }
+
+    fn write_ram(&mut self, location: usize, value: u8) {
+        if self.ram_enabled {
+            self.ram.set_in_bank(self.ram_bank(), location, value);
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.to_bytes()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        self.ram.load_bytes(data);
+    }
+}
+
+impl Mbc1 {
+    pub fn new(rom: BankedMemory, ram: BankedMemory) -> Self {
+        let multicart = is_multicart(&rom);
+
+        Mbc1 {
+            rom,
+            ram,
+            ram_enabled: false,
+            bank1: 1,
+            bank2: 0,
+            mode: 0,
+            multicart,
+        }
+    }
+
+    /// Bits BANK2 contributes to a ROM bank number
+    fn upper_rom_bits(&self) -> usize {
+        let shift = if self.multicart { 4 } else { 5 };
+        usize::from(self.bank2) << shift
+    }
+
+    fn low_rom_bank(&self) -> usize {
+        let bank = match self.mode {
+            0 => 0,
+            _ => self.upper_rom_bits(),
+        };
+
+        self.mask_rom_bank(bank)
+    }
+
+    fn high_rom_bank(&self) -> usize {
+        let lower = if self.multicart {
+            self.bank1 & 0b0000_1111
+        } else {
+            self.bank1
+        };
+
+        self.mask_rom_bank(self.upper_rom_bits() | usize::from(lower))
+    }
+
+    fn ram_bank(&self) -> usize {
+        let bank = match self.mode {
+            0 => 0,
+            _ => usize::from(self.bank2),
+        };
+
+        // Carts with less RAM just don't connect the higher bank lines
+        bank % self.ram.banks.len().max(1)
+    }
+
+    /// ROM sizes are powers of two, so the bank lines past the end of the
+    /// ROM simply aren't connected
+    fn mask_rom_bank(&self, bank: usize) -> usize {
+        bank & (self.rom.banks.len().next_power_of_two() - 1)
+    }
+}
+
+/// MBC1M carts have no header flag of their own, but every game on them
+/// has its own header, Nintendo logo included, at the start of its bank 0
+fn is_multicart(rom: &BankedMemory) -> bool {
+    rom.banks.len() == MULTICART_BANKS
+        && rom.banks[MULTICART_GAME_BANKS].get(NINTENDO_LOGO_LOCATION..=NINTENDO_LOGO_END)
+            == Some(&NINTENDO_LOGO[..])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::banked_memory::BankedMemory;
+    use crate::cartridge_header::{
+        NINTENDO_LOGO, NINTENDO_LOGO_END, NINTENDO_LOGO_LOCATION, RAM_BANK_SIZE,
+    };
+    use crate::mapper::mbc1::Mbc1;
+    use crate::mapper::{numbered_rom, Mapper};
+    use crate::mbc::RAM_ENABLE_VALUE;
+
+    #[test]
+    fn bank_zero_selects_bank_one() {
+        let mut mbc = Mbc1::new(numbered_rom(4), BankedMemory::new(0, 0, 0));
+
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.read_rom(0x4000), 1);
+
+        mbc.write_rom(0x2000, 0x03);
+        assert_eq!(mbc.read_rom(0x4000), 3);
+    }
+
+    #[test]
+    fn rom_bank_is_masked_by_rom_size() {
+        let mut mbc = Mbc1::new(numbered_rom(8), BankedMemory::new(0, 0, 0));
+
+        // 0x12 only has 3 bits that an 8 bank ROM is wired to
+        mbc.write_rom(0x2000, 0x12);
+        assert_eq!(mbc.read_rom(0x4000), 0x02);
+
+        // Zero is checked before masking, so 0x10 maps bank 0 up here
+        mbc.write_rom(0x2000, 0x10);
+        assert_eq!(mbc.read_rom(0x4000), 0x00);
+    }
+
+    #[test]
+    fn bank2_extends_rom_bank_and_remaps_bank_zero_in_mode_1() {
+        let mut mbc = Mbc1::new(numbered_rom(128), BankedMemory::new(0, 0, 0));
+
+        mbc.write_rom(0x2000, 0x01);
+        mbc.write_rom(0x4000, 0x02);
+        assert_eq!(mbc.read_rom(0x4000), 0x41);
+        assert_eq!(mbc.read_rom(0x0000), 0x00);
+
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_rom(0x0000), 0x40);
+    }
+
+    #[test]
+    fn ram_banks_only_switch_in_mode_1() {
+        let mut mbc = Mbc1::new(numbered_rom(4), BankedMemory::new(0, RAM_BANK_SIZE, 4));
+
+        mbc.write_rom(0x0000, RAM_ENABLE_VALUE);
+        mbc.write_rom(0x4000, 0x02);
+        mbc.write_ram(0x0000, 0x42);
+        assert_eq!(mbc.read_ram(0x0000), 0x42);
+
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_ram(0x0000), 0x00);
+
+        mbc.write_rom(0x4000, 0x00);
+        assert_eq!(mbc.read_ram(0x0000), 0x42);
+    }
+
+    #[test]
+    fn disabled_ram_reads_open_bus() {
+        let mut mbc = Mbc1::new(numbered_rom(4), BankedMemory::new(0, RAM_BANK_SIZE, 1));
+
+        mbc.write_ram(0x0000, 0x42);
+        assert_eq!(mbc.read_ram(0x0000), 0xff);
+
+        mbc.write_rom(0x0000, RAM_ENABLE_VALUE);
+        assert_eq!(mbc.read_ram(0x0000), 0x00);
+    }
+
+    #[test]
+    fn multicart_uses_four_bits_of_bank1() {
+        let mut rom = numbered_rom(64);
+        rom.banks[16][NINTENDO_LOGO_LOCATION..=NINTENDO_LOGO_END].copy_from_slice(&NINTENDO_LOGO);
+
+        let mut mbc = Mbc1::new(rom, BankedMemory::new(0, 0, 0));
+
+        mbc.write_rom(0x2000, 0x12);
+        mbc.write_rom(0x4000, 0x01);
+        assert_eq!(mbc.read_rom(0x4000), 0x12);
+
+        mbc.write_rom(0x6000, 0x01);
+        mbc.write_rom(0x4000, 0x03);
+        assert_eq!(mbc.read_rom(0x0000), 0x30);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::banked_memory::BankedMemory;
+use crate::cartridge::Cartridge;
+use crate::mapper::Mapper;
+use crate::mbc::RAM_ENABLE_VALUE;
+
+// The RAM is built into the MBC2 itself, 512 half bytes of it
+const RAM_SIZE: usize = 0x200;
+
+// Bit 8 of the address picks which register a write to 0x0000-0x3fff sets
+const REGISTER_SELECT: usize = 0x0100;
+
+/// The MBC2, with up to 16 ROM banks and its own 512x4 bit RAM. Only the low
+/// nibble of each RAM byte exists, and the 512 bytes repeat all the way
+/// through 0xa000-0xbfff.
+pub struct Mbc2 {
+    rom: BankedMemory,
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    rom_bank: usize,
+}
+
+impl From<Cartridge> for Mbc2 {
+    fn from(cartridge: Cartridge) -> Self {
+        Mbc2::new(BankedMemory::from(cartridge))
+    }
+}
+
+impl Mbc2 {
+    pub fn new(rom: BankedMemory) -> Self {
+        Mbc2 {
+            rom,
+            ram: vec![0x00; RAM_SIZE],
+            ram_enabled: false,
+            rom_bank: 1,
+        }
+    }
+}
+
+impl Mapper for Mbc2 {
+    fn read_rom(&self, location: usize) -> u8 {
+        match location {
+            0x0000..=0x3fff => self.rom.value_in_bank(0, location),
+            _ => {
+                let bank = self.rom_bank & (self.rom.banks.len().next_power_of_two() - 1);
+                self.rom.value_in_bank(bank, location - 0x4000)
+            }
+        }
+    }
+
+    fn write_rom(&mut self, location: usize, value: u8) {
+        match location {
+            0x0000..=0x3fff if location & REGISTER_SELECT == 0 => {
+                self.ram_enabled = (value & 0b0000_1111) == RAM_ENABLE_VALUE
+            }
+            0x0000..=0x3fff => {
+                self.rom_bank = match value & 0b0000_1111 {
+                    0 => 1,
+                    bank => bank.into(),
+                }
+            }
+            // Nothing is mapped at 0x4000-0x7fff
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, location: usize) -> u8 {
+        if !self.ram_enabled {
+            return 0xff;
+        }
+
+        // The upper nibble isn't driven by anything
+        self.ram[location % RAM_SIZE] | 0b1111_0000
+    }
+
+    fn write_ram(&mut self, location: usize, value: u8) {
+        if self.ram_enabled {
+            self.ram[location % RAM_SIZE] = value & 0b0000_1111;
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.clone()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        for (byte, saved) in self.ram.iter_mut().zip(data) {
+            *byte = saved & 0b0000_1111;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mapper::mbc2::Mbc2;
+    use crate::mapper::{numbered_rom, Mapper};
+    use crate::mbc::RAM_ENABLE_VALUE;
+
+    #[test]
+    fn address_bit_8_selects_register() {
+        let mut mbc = Mbc2::new(numbered_rom(16));
+
+        mbc.write_rom(0x2100, 0x05);
+        assert_eq!(mbc.read_rom(0x4000), 5);
+        assert_eq!(mbc.read_ram(0x0000), 0xff);
+
+        // Bit 8 clear, so this enables RAM rather than switching banks
+        mbc.write_rom(0x2000, RAM_ENABLE_VALUE);
+        assert_eq!(mbc.read_rom(0x4000), 5);
+        assert_eq!(mbc.read_ram(0x0000), 0xf0);
+
+        mbc.write_rom(0x0100, 0x00);
+        assert_eq!(mbc.read_rom(0x4000), 1);
+    }
+
+    #[test]
+    fn ram_is_four_bits_wide_and_echoes() {
+        let mut mbc = Mbc2::new(numbered_rom(2));
+
+        mbc.write_rom(0x0000, RAM_ENABLE_VALUE);
+        mbc.write_ram(0x0010, 0xa5);
+
+        assert_eq!(mbc.read_ram(0x0010), 0xf5);
+        assert_eq!(mbc.read_ram(0x0210), 0xf5);
+        assert_eq!(mbc.read_ram(0x1e10), 0xf5);
+        assert_eq!(mbc.save_data()[0x10], 0x05);
+    }
+}
@@ -0,0 +1,468 @@
+use std::time::SystemTime;
+
+use crate::banked_memory::BankedMemory;
+use crate::cartridge::Cartridge;
+use crate::cartridge_header::RAM_BANK_SIZE;
+use crate::cartridge_type::CartridgeType;
+use crate::cpu::CLOCK_MHZ;
+use crate::mapper::rtc_save::RtcSave;
+use crate::mapper::Mapper;
+use crate::mbc::RAM_ENABLE_VALUE;
+
+const SECONDS_REGISTER: u8 = 0x08;
+const MINUTES_REGISTER: u8 = 0x09;
+const HOURS_REGISTER: u8 = 0x0a;
+const DAYS_LOW_REGISTER: u8 = 0x0b;
+const DAYS_HIGH_REGISTER: u8 = 0x0c;
+
+const DAY_HIGH_BIT: u8 = 0b0000_0001;
+const HALT_BIT: u8 = 0b0100_0000;
+const DAY_CARRY_BIT: u8 = 0b1000_0000;
+
+// The RTC state other emulators append to the end of a .sav, as the live and
+// latched registers as 32 bit words followed by a 64 bit UNIX timestamp.
+// Some older ones only wrote a 32 bit timestamp.
+const FOOTER_SIZE: usize = 48;
+const SHORT_FOOTER_SIZE: usize = 44;
+
+/// The registers of the MBC3 real-time clock
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+struct Clock {
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    days: u16,
+    halted: bool,
+    day_carry: bool,
+}
+
+impl Clock {
+    fn read(&self, register: u8) -> u8 {
+        match register {
+            SECONDS_REGISTER => self.seconds,
+            MINUTES_REGISTER => self.minutes,
+            HOURS_REGISTER => self.hours,
+            DAYS_LOW_REGISTER => self.days as u8,
+            _ => {
+                (self.days >> 8) as u8 & DAY_HIGH_BIT
+                    | if self.halted { HALT_BIT } else { 0 }
+                    | if self.day_carry { DAY_CARRY_BIT } else { 0 }
+            }
+        }
+    }
+
+    fn write(&mut self, register: u8, value: u8) {
+        match register {
+            SECONDS_REGISTER => self.seconds = value & 0b0011_1111,
+            MINUTES_REGISTER => self.minutes = value & 0b0011_1111,
+            HOURS_REGISTER => self.hours = value & 0b0001_1111,
+            DAYS_LOW_REGISTER => self.days = (self.days & 0x100) | u16::from(value),
+            _ => {
+                self.days = (self.days & 0xff) | (u16::from(value & DAY_HIGH_BIT) << 8);
+                self.halted = value & HALT_BIT != 0;
+                self.day_carry = value & DAY_CARRY_BIT != 0;
+            }
+        }
+    }
+
+    /// Each counter only carries into the next when it ticks past its
+    /// normal limit. Counters written with an out of range value keep going
+    /// until their bits overflow, and wrap to zero without carrying.
+    fn tick_second(&mut self) {
+        let (seconds, carry) = count(self.seconds, 60, 0b0011_1111);
+        self.seconds = seconds;
+
+        if !carry {
+            return;
+        }
+
+        let (minutes, carry) = count(self.minutes, 60, 0b0011_1111);
+        self.minutes = minutes;
+
+        if !carry {
+            return;
+        }
+
+        let (hours, carry) = count(self.hours, 24, 0b0001_1111);
+        self.hours = hours;
+
+        if !carry {
+            return;
+        }
+
+        self.days = (self.days + 1) & 0x1ff;
+
+        if self.days == 0 {
+            self.day_carry = true;
+        }
+    }
+
+    fn is_in_range(&self) -> bool {
+        self.seconds < 60 && self.minutes < 60 && self.hours < 24
+    }
+
+    /// Runs the clock forward, as it would have while the emulator was closed
+    fn advance(&mut self, mut seconds: u64) {
+        if self.halted {
+            return;
+        }
+
+        // Let any out of range counters wrap around the slow way first
+        while seconds > 0 && !self.is_in_range() {
+            self.tick_second();
+            seconds -= 1;
+        }
+
+        let elapsed = u64::from(self.seconds)
+            + u64::from(self.minutes) * 60
+            + u64::from(self.hours) * 60 * 60
+            + u64::from(self.days) * 60 * 60 * 24
+            + seconds;
+        let days = elapsed / (60 * 60 * 24);
+
+        self.seconds = (elapsed % 60) as u8;
+        self.minutes = (elapsed / 60 % 60) as u8;
+        self.hours = (elapsed / (60 * 60) % 24) as u8;
+        self.days = (days % 0x200) as u16;
+        self.day_carry |= days >= 0x200;
+    }
+
+    fn to_words(self) -> [u32; 5] {
+        [
+            SECONDS_REGISTER,
+            MINUTES_REGISTER,
+            HOURS_REGISTER,
+            DAYS_LOW_REGISTER,
+            DAYS_HIGH_REGISTER,
+        ]
+        .map(|register| self.read(register).into())
+    }
+
+    fn from_words(words: &[u32]) -> Self {
+        let mut clock = Clock::default();
+
+        for (register, word) in (SECONDS_REGISTER..=DAYS_HIGH_REGISTER).zip(words) {
+            clock.write(register, *word as u8);
+        }
+
+        clock
+    }
+}
+
+/// Counts a register up by one, returning the new value and whether it
+/// carried into the next register
+fn count(value: u8, limit: u8, mask: u8) -> (u8, bool) {
+    if value == limit - 1 {
+        (0, true)
+    } else {
+        ((value + 1) & mask, false)
+    }
+}
+
+/// The MBC3, with a 7 bit ROM bank, 4 RAM banks and on some carts a
+/// real-time clock. The clock's registers are mapped in place of RAM by
+/// selecting banks 0x08-0x0c, and reads see a copy of them latched by
+/// writing 0x00 then 0x01 to 0x6000-0x7fff.
+pub struct Mbc3 {
+    rom: BankedMemory,
+    ram: BankedMemory,
+    // Enables both the RAM and the clock
+    ram_enabled: bool,
+    rom_bank: usize,
+    // A RAM bank, or one of the clock registers
+    ram_select: u8,
+    latch_armed: bool,
+    has_clock: bool,
+    clock: Clock,
+    latched: Clock,
+    // T-cycles into the current second
+    cycles: u32,
+    rtc_save: RtcSave,
+}
+
+impl From<Cartridge> for Mbc3 {
+    fn from(cartridge: Cartridge) -> Self {
+        let has_clock = matches!(
+            cartridge.header.cartridge_type,
+            CartridgeType::MBC3TimerBattery | CartridgeType::MBC3TimerRamBattery
+        );
+        let ram = BankedMemory::of_size(cartridge.header.ram_size);
+
+        Mbc3::new(BankedMemory::from(cartridge), ram, has_clock)
+    }
+}
+
+impl Mbc3 {
+    pub fn new(rom: BankedMemory, ram: BankedMemory, has_clock: bool) -> Self {
+        Mbc3 {
+            rom,
+            ram,
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_select: 0,
+            latch_armed: false,
+            has_clock,
+            clock: Clock::default(),
+            latched: Clock::default(),
+            cycles: 0,
+            rtc_save: RtcSave::default(),
+        }
+    }
+
+    fn footer(&self) -> Vec<u8> {
+        let mut words = self.clock.to_words().to_vec();
+        words.extend_from_slice(&self.latched.to_words());
+
+        RtcSave::footer(&words)
+    }
+
+    fn load_footer(&mut self, footer: &[u8]) {
+        let words = self.rtc_save.load(footer, 10);
+
+        self.clock = Clock::from_words(&words[..5]);
+        self.latched = Clock::from_words(&words[5..]);
+    }
+}
+
+impl Mapper for Mbc3 {
+    fn read_rom(&self, location: usize) -> u8 {
+        match location {
+            0x0000..=0x3fff => self.rom.value_in_bank(0, location),
+            _ => {
+                let bank = self.rom_bank & (self.rom.banks.len().next_power_of_two() - 1);
+                self.rom.value_in_bank(bank, location - 0x4000)
+            }
+        }
+    }
+
+    fn write_rom(&mut self, location: usize, value: u8) {
+        match location {
+            0x0000..=0x1fff => self.ram_enabled = (value & 0b0000_1111) == RAM_ENABLE_VALUE,
+            0x2000..=0x3fff => {
+                self.rom_bank = match value & 0b0111_1111 {
+                    0 => 1,
+                    bank => bank.into(),
+                }
+            }
+            0x4000..=0x5fff => self.ram_select = value & 0b0000_1111,
+            _ => {
+                if self.latch_armed && value == 0x01 {
+                    self.latched = self.clock;
+                }
+
+                self.latch_armed = value == 0x00;
+            }
+        }
+    }
+
+    fn read_ram(&self, location: usize) -> u8 {
+        if !self.ram_enabled {
+            return 0xff;
+        }
+
+        match self.ram_select {
+            0x00..=0x03 => self
+                .ram
+                .value_in_bank(usize::from(self.ram_select), location),
+            SECONDS_REGISTER..=DAYS_HIGH_REGISTER if self.has_clock => {
+                self.latched.read(self.ram_select)
+            }
+            _ => 0xff,
+        }
+    }
+
+    fn write_ram(&mut self, location: usize, value: u8) {
+        if !self.ram_enabled {
+            return;
+        }
+
+        match self.ram_select {
+            0x00..=0x03 => self
+                .ram
+                .set_in_bank(usize::from(self.ram_select), location, value),
+            SECONDS_REGISTER..=DAYS_HIGH_REGISTER if self.has_clock => {
+                // Writing the seconds also restarts the current second
+                if self.ram_select == SECONDS_REGISTER {
+                    self.cycles = 0;
+                }
+
+                self.clock.write(self.ram_select, value);
+            }
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        if !self.has_clock || self.clock.halted {
+            return;
+        }
+
+        self.cycles += cycles;
+
+        while self.cycles >= CLOCK_MHZ {
+            self.cycles -= CLOCK_MHZ;
+            self.clock.tick_second();
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        let mut data = self.ram.to_bytes();
+
+        if self.has_clock {
+            data.extend(self.footer());
+        }
+
+        data
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        // The footer is whatever is left over past a whole number of banks
+        let footer_size = match data.len() % RAM_BANK_SIZE {
+            FOOTER_SIZE => FOOTER_SIZE,
+            SHORT_FOOTER_SIZE => SHORT_FOOTER_SIZE,
+            _ => 0,
+        };
+        let (ram, footer) = data.split_at(data.len() - footer_size);
+
+        self.ram.load_bytes(ram);
+
+        if self.has_clock && footer_size > 0 {
+            self.load_footer(footer);
+        }
+    }
+
+    fn sync_clock(&mut self, now: SystemTime) {
+        if let Some(seconds) = self.rtc_save.elapsed(now) {
+            self.clock.advance(seconds);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::banked_memory::{BankedMemory, ROM_BANK_SIZE};
+    use crate::cartridge_header::RAM_BANK_SIZE;
+    use crate::cpu::CLOCK_MHZ;
+    use crate::mapper::mbc3::{Clock, Mbc3};
+    use crate::mapper::Mapper;
+    use crate::mbc::RAM_ENABLE_VALUE;
+
+    fn get_mbc3() -> Mbc3 {
+        let mut mbc = Mbc3::new(
+            BankedMemory::new(0, ROM_BANK_SIZE, 128),
+            BankedMemory::new(0, RAM_BANK_SIZE, 4),
+            true,
+        );
+
+        mbc.write_rom(0x0000, RAM_ENABLE_VALUE);
+        mbc
+    }
+
+    fn latch(mbc: &mut Mbc3) {
+        mbc.write_rom(0x6000, 0x00);
+        mbc.write_rom(0x6000, 0x01);
+    }
+
+    fn read_register(mbc: &mut Mbc3, register: u8) -> u8 {
+        mbc.write_rom(0x4000, register);
+        mbc.read_ram(0x0000)
+    }
+
+    fn write_register(mbc: &mut Mbc3, register: u8, value: u8) {
+        mbc.write_rom(0x4000, register);
+        mbc.write_ram(0x0000, value);
+    }
+
+    #[test]
+    fn rom_bank_is_seven_bits() {
+        let mut mbc = get_mbc3();
+        mbc.rom.banks[0x45][0] = 0x42;
+
+        mbc.write_rom(0x2000, 0xc5);
+        assert_eq!(mbc.read_rom(0x4000), 0x42);
+    }
+
+    #[test]
+    fn reads_see_latched_registers() {
+        let mut mbc = get_mbc3();
+
+        mbc.tick(CLOCK_MHZ * 3);
+        assert_eq!(read_register(&mut mbc, 0x08), 0);
+
+        latch(&mut mbc);
+        mbc.tick(CLOCK_MHZ);
+        assert_eq!(read_register(&mut mbc, 0x08), 3);
+    }
+
+    #[test]
+    fn counters_carry_into_days() {
+        let mut mbc = get_mbc3();
+
+        write_register(&mut mbc, 0x08, 59);
+        write_register(&mut mbc, 0x09, 59);
+        write_register(&mut mbc, 0x0a, 23);
+        write_register(&mut mbc, 0x0b, 0xff);
+        write_register(&mut mbc, 0x0c, 0x01);
+
+        mbc.tick(CLOCK_MHZ);
+        latch(&mut mbc);
+
+        assert_eq!(read_register(&mut mbc, 0x0a), 0);
+        assert_eq!(read_register(&mut mbc, 0x0b), 0);
+        assert_eq!(read_register(&mut mbc, 0x0c), 0x80);
+    }
+
+    #[test]
+    fn out_of_range_seconds_wrap_without_carrying() {
+        let mut mbc = get_mbc3();
+
+        write_register(&mut mbc, 0x08, 63);
+        mbc.tick(CLOCK_MHZ);
+        latch(&mut mbc);
+
+        assert_eq!(read_register(&mut mbc, 0x08), 0);
+        assert_eq!(read_register(&mut mbc, 0x09), 0);
+    }
+
+    #[test]
+    fn halt_stops_the_clock() {
+        let mut mbc = get_mbc3();
+
+        write_register(&mut mbc, 0x0c, 0x40);
+        mbc.tick(CLOCK_MHZ * 2);
+        latch(&mut mbc);
+
+        assert_eq!(read_register(&mut mbc, 0x08), 0);
+    }
+
+    #[test]
+    fn footer_round_trips_and_catches_up() {
+        let mut mbc = get_mbc3();
+
+        write_register(&mut mbc, 0x09, 30);
+        write_register(&mut mbc, 0x00, 0x42);
+
+        let data = mbc.save_data();
+        assert_eq!(data.len(), RAM_BANK_SIZE * 4 + 48);
+
+        let mut loaded = get_mbc3();
+        loaded.load_save_data(&data);
+        assert_eq!(loaded.clock, mbc.clock);
+        assert_eq!(loaded.read_ram(0x0000), 0x42);
+
+        let saved_at = loaded.rtc_save.saved_at().unwrap();
+        loaded.sync_clock(UNIX_EPOCH + Duration::from_secs(saved_at + 60 * 60 + 5));
+
+        assert_eq!(
+            loaded.clock,
+            Clock {
+                seconds: 5,
+                minutes: 30,
+                hours: 1,
+                ..Clock::default()
+            }
+        );
+    }
+}
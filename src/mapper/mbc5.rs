@@ -0,0 +1,181 @@
+use crate::banked_memory::BankedMemory;
+use crate::cartridge::Cartridge;
+use crate::cartridge_type::CartridgeType;
+use crate::mapper::{Mapper, RumbleListener};
+use crate::mbc::RAM_ENABLE_VALUE;
+
+// On rumble carts this bit of the RAM bank register drives the motor
+const RUMBLE_BIT: u8 = 0b0000_1000;
+
+/// The MBC5, with a 9 bit ROM bank split over two registers and up to 16
+/// RAM banks. Unlike the earlier MBCs, bank 0 can be mapped at 0x4000 too.
+pub struct Mbc5 {
+    rom: BankedMemory,
+    ram: BankedMemory,
+    ram_enabled: bool,
+    rom_bank: usize,
+    ram_bank: usize,
+    has_rumble: bool,
+    rumbling: bool,
+    rumble_listener: Option<RumbleListener>,
+}
+
+impl From<Cartridge> for Mbc5 {
+    fn from(cartridge: Cartridge) -> Self {
+        let has_rumble = matches!(
+            cartridge.header.cartridge_type,
+            CartridgeType::MBC5Rumble
+                | CartridgeType::MBC5RumbleRam
+                | CartridgeType::MBC5RumbleRamBattery
+        );
+        let ram = BankedMemory::of_size(cartridge.header.ram_size);
+
+        Mbc5::new(BankedMemory::from(cartridge), ram, has_rumble)
+    }
+}
+
+impl Mbc5 {
+    pub fn new(rom: BankedMemory, ram: BankedMemory, has_rumble: bool) -> Self {
+        Mbc5 {
+            rom,
+            ram,
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            has_rumble,
+            rumbling: false,
+            rumble_listener: None,
+        }
+    }
+
+    fn set_rumbling(&mut self, rumbling: bool) {
+        if rumbling == self.rumbling {
+            return;
+        }
+
+        self.rumbling = rumbling;
+
+        if let Some(listener) = &mut self.rumble_listener {
+            listener(rumbling);
+        }
+    }
+}
+
+impl Mapper for Mbc5 {
+    fn read_rom(&self, location: usize) -> u8 {
+        match location {
+            0x0000..=0x3fff => self.rom.value_in_bank(0, location),
+            _ => {
+                let bank = self.rom_bank & (self.rom.banks.len().next_power_of_two() - 1);
+                self.rom.value_in_bank(bank, location - 0x4000)
+            }
+        }
+    }
+
+    fn write_rom(&mut self, location: usize, value: u8) {
+        match location {
+            0x0000..=0x1fff => self.ram_enabled = (value & 0b0000_1111) == RAM_ENABLE_VALUE,
+            // The low 8 bits of the bank, then the 9th bit
+            0x2000..=0x2fff => self.rom_bank = (self.rom_bank & 0x100) | usize::from(value),
+            0x3000..=0x3fff => {
+                self.rom_bank = (self.rom_bank & 0xff) | (usize::from(value & 0b0000_0001) << 8)
+            }
+            0x4000..=0x5fff if self.has_rumble => {
+                self.ram_bank = usize::from(value & 0b0000_0111);
+                self.set_rumbling(value & RUMBLE_BIT != 0);
+            }
+            0x4000..=0x5fff => self.ram_bank = usize::from(value & 0b0000_1111),
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, location: usize) -> u8 {
+        if !self.ram_enabled {
+            return 0xff;
+        }
+
+        self.ram.value_in_bank(self.ram_bank, location)
+    }
+
+    fn write_ram(&mut self, location: usize, value: u8) {
+        if self.ram_enabled {
+            self.ram.set_in_bank(self.ram_bank, location, value);
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.to_bytes()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        self.ram.load_bytes(data);
+    }
+
+    fn set_rumble_listener(&mut self, listener: RumbleListener) {
+        self.rumble_listener = Some(listener);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::banked_memory::BankedMemory;
+    use crate::cartridge_header::RAM_BANK_SIZE;
+    use crate::mapper::mbc5::Mbc5;
+    use crate::mapper::{numbered_rom, Mapper};
+    use crate::mbc::RAM_ENABLE_VALUE;
+
+    #[test]
+    fn rom_bank_is_nine_bits_and_zero_is_allowed() {
+        let mut mbc = Mbc5::new(numbered_rom(512), BankedMemory::new(0, 0, 0), false);
+
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.read_rom(0x4000), 0x00);
+
+        mbc.write_rom(0x2000, 0x23);
+        mbc.write_rom(0x3000, 0x01);
+        assert_eq!(mbc.read_rom(0x4000), 0x23);
+        assert_eq!(mbc.read_rom(0x4001), 0x01);
+    }
+
+    #[test]
+    fn sixteen_ram_banks() {
+        let mut mbc = Mbc5::new(
+            numbered_rom(2),
+            BankedMemory::new(0, RAM_BANK_SIZE, 16),
+            false,
+        );
+
+        mbc.write_rom(0x0000, RAM_ENABLE_VALUE);
+        mbc.write_rom(0x4000, 0x0f);
+        mbc.write_ram(0x0000, 0x42);
+
+        mbc.write_rom(0x4000, 0x07);
+        assert_eq!(mbc.read_ram(0x0000), 0x00);
+
+        mbc.write_rom(0x4000, 0x0f);
+        assert_eq!(mbc.read_ram(0x0000), 0x42);
+    }
+
+    #[test]
+    fn rumble_bit_notifies_listener() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let listener_events = events.clone();
+        let mut mbc = Mbc5::new(
+            numbered_rom(2),
+            BankedMemory::new(0, RAM_BANK_SIZE, 8),
+            true,
+        );
+
+        mbc.set_rumble_listener(Box::new(move |on| listener_events.borrow_mut().push(on)));
+
+        mbc.write_rom(0x4000, 0x09);
+        mbc.write_rom(0x4000, 0x0a);
+        mbc.write_rom(0x4000, 0x02);
+
+        assert_eq!(*events.borrow(), vec![true, false]);
+        assert_eq!(mbc.ram_bank, 2);
+    }
+}
@@ -0,0 +1,359 @@
+use crate::banked_memory::BankedMemory;
+use crate::cartridge::Cartridge;
+use crate::mapper::Mapper;
+use crate::mbc::RAM_ENABLE_VALUE;
+
+// ROM and flash are switched in 8 KiB halves of the usual window, and RAM
+// in 4 KiB halves
+const HALF_BANK_SIZE: usize = 0x2000;
+const RAM_HALF_BANK_SIZE: usize = 0x1000;
+const RAM_HALF_BANKS: usize = 8;
+
+// Written to a window's select register to map flash instead of ROM
+const FLASH_SELECT_VALUE: u8 = 0x08;
+
+// A 1 MiB Macronix MX29F008
+const FLASH_SIZE: usize = 0x100000;
+const FLASH_MANUFACTURER_ID: u8 = 0xc2;
+const FLASH_DEVICE_ID: u8 = 0x81;
+
+// Commands are unlocked by writing these to these addresses first
+const UNLOCK_ADDRESS_1: usize = 0x5555;
+const UNLOCK_ADDRESS_2: usize = 0x2aaa;
+const UNLOCK_VALUE_1: u8 = 0xaa;
+const UNLOCK_VALUE_2: u8 = 0x55;
+
+const PROGRAM_COMMAND: u8 = 0xa0;
+const ERASE_COMMAND: u8 = 0x80;
+const IDENTIFY_COMMAND: u8 = 0x90;
+const RESET_COMMAND: u8 = 0xf0;
+// Sent after a second unlock, following the erase command
+const ERASE_SECTOR_COMMAND: u8 = 0x30;
+const ERASE_CHIP_COMMAND: u8 = 0x10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FlashState {
+    Ready,
+    // Partway through an unlock, for either a command or an erase
+    Unlocking { erase: bool },
+    Unlocked { erase: bool },
+    Program,
+    EraseSetup,
+    Identify,
+}
+
+/// The flash chip, programmed with the usual unlock sequences. Programming
+/// can only clear bits, so anything that's been written has to be erased
+/// before it can be written again.
+struct Flash {
+    data: Vec<u8>,
+    state: FlashState,
+}
+
+impl Flash {
+    fn read(&self, address: usize) -> u8 {
+        if self.state == FlashState::Identify {
+            return match address & 0xff {
+                0x00 => FLASH_MANUFACTURER_ID,
+                0x01 => FLASH_DEVICE_ID,
+                _ => 0xff,
+            };
+        }
+
+        self.data[address % FLASH_SIZE]
+    }
+
+    fn write(&mut self, address: usize, value: u8) {
+        let command_address = address & 0x7fff;
+
+        self.state = match (self.state, command_address, value) {
+            (FlashState::Program, _, _) => {
+                self.data[address % FLASH_SIZE] &= value;
+                FlashState::Ready
+            }
+            (_, _, RESET_COMMAND) => FlashState::Ready,
+            (FlashState::Ready, UNLOCK_ADDRESS_1, UNLOCK_VALUE_1)
+            | (FlashState::Identify, UNLOCK_ADDRESS_1, UNLOCK_VALUE_1) => {
+                FlashState::Unlocking { erase: false }
+            }
+            (FlashState::EraseSetup, UNLOCK_ADDRESS_1, UNLOCK_VALUE_1) => {
+                FlashState::Unlocking { erase: true }
+            }
+            (FlashState::Unlocking { erase }, UNLOCK_ADDRESS_2, UNLOCK_VALUE_2) => {
+                FlashState::Unlocked { erase }
+            }
+            (FlashState::Unlocked { erase: false }, UNLOCK_ADDRESS_1, command) => match command {
+                PROGRAM_COMMAND => FlashState::Program,
+                ERASE_COMMAND => FlashState::EraseSetup,
+                IDENTIFY_COMMAND => FlashState::Identify,
+                _ => FlashState::Ready,
+            },
+            (FlashState::Unlocked { erase: true }, _, ERASE_SECTOR_COMMAND) => {
+                // Each sector is one of the 8 KiB banks
+                let sector = address % FLASH_SIZE / HALF_BANK_SIZE * HALF_BANK_SIZE;
+                self.data[sector..sector + HALF_BANK_SIZE].fill(0xff);
+                FlashState::Ready
+            }
+            (FlashState::Unlocked { erase: true }, UNLOCK_ADDRESS_1, ERASE_CHIP_COMMAND) => {
+                self.data.fill(0xff);
+                FlashState::Ready
+            }
+            (FlashState::Identify, _, _) => FlashState::Identify,
+            _ => FlashState::Ready,
+        };
+    }
+}
+
+/// The MBC6, from Net de Get. The switchable ROM area is split into two 8
+/// KiB windows, each of which can map either ROM or the cartridge's flash,
+/// and the RAM area into two 4 KiB windows with their own banks.
+pub struct Mbc6 {
+    rom: BankedMemory,
+    ram: BankedMemory,
+    flash: Flash,
+    ram_enabled: bool,
+    flash_enabled: bool,
+    flash_write_enabled: bool,
+    // Each of these is for the A and B windows
+    ram_banks: [usize; 2],
+    rom_banks: [usize; 2],
+    flash_selected: [bool; 2],
+}
+
+impl From<Cartridge> for Mbc6 {
+    fn from(cartridge: Cartridge) -> Self {
+        Mbc6::new(BankedMemory::from(cartridge))
+    }
+}
+
+impl Mbc6 {
+    pub fn new(rom: BankedMemory) -> Self {
+        Mbc6 {
+            rom,
+            ram: BankedMemory::new(0, RAM_HALF_BANK_SIZE, RAM_HALF_BANKS),
+            flash: Flash {
+                data: vec![0xff; FLASH_SIZE],
+                state: FlashState::Ready,
+            },
+            ram_enabled: false,
+            flash_enabled: false,
+            flash_write_enabled: false,
+            ram_banks: [0, 0],
+            rom_banks: [0, 0],
+            flash_selected: [false, false],
+        }
+    }
+
+    /// Where a location in one of the ROM windows falls in the flash
+    fn flash_address(&self, window: usize, location: usize) -> usize {
+        self.rom_banks[window] * HALF_BANK_SIZE + location % HALF_BANK_SIZE
+    }
+
+    fn read_half_bank(&self, bank: usize, location: usize) -> u8 {
+        let bank = bank
+            & (self.rom.banks.len() * 2)
+                .next_power_of_two()
+                .wrapping_sub(1);
+        self.rom
+            .value_in_bank(bank / 2, (bank % 2) * HALF_BANK_SIZE + location)
+    }
+}
+
+impl Mapper for Mbc6 {
+    fn read_rom(&self, location: usize) -> u8 {
+        if location < 0x4000 {
+            return self.rom.value_in_bank(0, location);
+        }
+
+        let window = (location - 0x4000) / HALF_BANK_SIZE;
+
+        if self.flash_selected[window] {
+            if !self.flash_enabled {
+                return 0xff;
+            }
+
+            self.flash.read(self.flash_address(window, location))
+        } else {
+            self.read_half_bank(self.rom_banks[window], location % HALF_BANK_SIZE)
+        }
+    }
+
+    fn write_rom(&mut self, location: usize, value: u8) {
+        match location {
+            0x0000..=0x03ff => self.ram_enabled = (value & 0b0000_1111) == RAM_ENABLE_VALUE,
+            0x0400..=0x07ff => self.ram_banks[0] = usize::from(value & 0b0000_0111),
+            0x0800..=0x0bff => self.ram_banks[1] = usize::from(value & 0b0000_0111),
+            0x0c00..=0x0fff => self.flash_enabled = value & 0b0000_0001 != 0,
+            0x1000 => self.flash_write_enabled = value & 0b0000_0001 != 0,
+            0x2000..=0x27ff => self.rom_banks[0] = usize::from(value & 0b0111_1111),
+            0x2800..=0x2fff => self.flash_selected[0] = value == FLASH_SELECT_VALUE,
+            0x3000..=0x37ff => self.rom_banks[1] = usize::from(value & 0b0111_1111),
+            0x3800..=0x3fff => self.flash_selected[1] = value == FLASH_SELECT_VALUE,
+            0x4000..=0x7fff => {
+                let window = (location - 0x4000) / HALF_BANK_SIZE;
+
+                if self.flash_selected[window] && self.flash_enabled && self.flash_write_enabled {
+                    let address = self.flash_address(window, location);
+                    self.flash.write(address, value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, location: usize) -> u8 {
+        if !self.ram_enabled {
+            return 0xff;
+        }
+
+        let bank = self.ram_banks[location / RAM_HALF_BANK_SIZE];
+        self.ram.value_in_bank(bank, location % RAM_HALF_BANK_SIZE)
+    }
+
+    fn write_ram(&mut self, location: usize, value: u8) {
+        if self.ram_enabled {
+            let bank = self.ram_banks[location / RAM_HALF_BANK_SIZE];
+            self.ram
+                .set_in_bank(bank, location % RAM_HALF_BANK_SIZE, value);
+        }
+    }
+
+    /// The RAM followed by the whole of the flash
+    fn save_data(&self) -> Vec<u8> {
+        let mut data = self.ram.to_bytes();
+        data.extend_from_slice(&self.flash.data);
+        data
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        let ram_size = RAM_HALF_BANK_SIZE * RAM_HALF_BANKS;
+        let (ram, flash) = data.split_at(data.len().min(ram_size));
+        let flash = &flash[..flash.len().min(FLASH_SIZE)];
+
+        self.ram.load_bytes(ram);
+        self.flash.data[..flash.len()].copy_from_slice(flash);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::banked_memory::{BankedMemory, ROM_BANK_SIZE};
+    use crate::mapper::mbc6::{Mbc6, FLASH_SIZE};
+    use crate::mapper::Mapper;
+    use crate::mbc::RAM_ENABLE_VALUE;
+
+    fn get_mbc6() -> Mbc6 {
+        let mut rom = BankedMemory::new(0, ROM_BANK_SIZE, 64);
+
+        // Number each 8 KiB half
+        for (number, bank) in rom.banks.iter_mut().enumerate() {
+            bank[0] = (number * 2) as u8;
+            bank[0x2000] = (number * 2 + 1) as u8;
+        }
+
+        Mbc6::new(rom)
+    }
+
+    /// Maps flash banks 2 and 1 into windows A and B, which between them
+    /// hold the unlock addresses
+    fn with_flash() -> Mbc6 {
+        let mut mbc = get_mbc6();
+
+        mbc.write_rom(0x0c00, 0x01);
+        mbc.write_rom(0x1000, 0x01);
+        mbc.write_rom(0x2000, 0x02);
+        mbc.write_rom(0x2800, 0x08);
+        mbc.write_rom(0x3000, 0x01);
+        mbc.write_rom(0x3800, 0x08);
+        mbc
+    }
+
+    fn flash_command(mbc: &mut Mbc6, command: u8) {
+        mbc.write_rom(0x5555, 0xaa);
+        mbc.write_rom(0x6aaa, 0x55);
+        mbc.write_rom(0x5555, command);
+    }
+
+    #[test]
+    fn two_independent_rom_windows() {
+        let mut mbc = get_mbc6();
+
+        mbc.write_rom(0x2000, 0x05);
+        mbc.write_rom(0x3000, 0x22);
+
+        assert_eq!(mbc.read_rom(0x4000), 0x05);
+        assert_eq!(mbc.read_rom(0x6000), 0x22);
+    }
+
+    #[test]
+    fn two_independent_ram_windows() {
+        let mut mbc = get_mbc6();
+
+        mbc.write_rom(0x0000, RAM_ENABLE_VALUE);
+        mbc.write_rom(0x0400, 0x03);
+        mbc.write_rom(0x0800, 0x03);
+        mbc.write_ram(0x0000, 0x42);
+
+        assert_eq!(mbc.read_ram(0x1000), 0x42);
+
+        mbc.write_rom(0x0800, 0x04);
+        assert_eq!(mbc.read_ram(0x1000), 0x00);
+    }
+
+    #[test]
+    fn flash_is_programmed_and_erased() {
+        let mut mbc = with_flash();
+
+        flash_command(&mut mbc, 0xa0);
+        mbc.write_rom(0x6010, 0x3c);
+        assert_eq!(mbc.read_rom(0x6010), 0x3c);
+
+        // Programming only clears bits
+        flash_command(&mut mbc, 0xa0);
+        mbc.write_rom(0x6010, 0xf0);
+        assert_eq!(mbc.read_rom(0x6010), 0x30);
+
+        // Without a command, writes do nothing
+        mbc.write_rom(0x6011, 0x00);
+        assert_eq!(mbc.read_rom(0x6011), 0xff);
+
+        flash_command(&mut mbc, 0x80);
+        mbc.write_rom(0x5555, 0xaa);
+        mbc.write_rom(0x6aaa, 0x55);
+        mbc.write_rom(0x6000, 0x30);
+        assert_eq!(mbc.read_rom(0x6010), 0xff);
+    }
+
+    #[test]
+    fn flash_identifies_itself() {
+        let mut mbc = with_flash();
+
+        flash_command(&mut mbc, 0x90);
+        assert_eq!(mbc.read_rom(0x4000), 0xc2);
+        assert_eq!(mbc.read_rom(0x4001), 0x81);
+
+        mbc.write_rom(0x4000, 0xf0);
+        assert_eq!(mbc.read_rom(0x4000), 0xff);
+    }
+
+    #[test]
+    fn flash_needs_write_enable_and_is_saved() {
+        let mut mbc = with_flash();
+        mbc.write_rom(0x1000, 0x00);
+
+        flash_command(&mut mbc, 0xa0);
+        mbc.write_rom(0x6000, 0x00);
+        assert_eq!(mbc.read_rom(0x6000), 0xff);
+
+        mbc.write_rom(0x1000, 0x01);
+        flash_command(&mut mbc, 0xa0);
+        mbc.write_rom(0x6000, 0x12);
+
+        let data = mbc.save_data();
+        assert_eq!(data.len(), 0x8000 + FLASH_SIZE);
+
+        let mut restored = with_flash();
+        restored.load_save_data(&data);
+        assert_eq!(restored.read_rom(0x6000), 0x12);
+    }
+}
@@ -0,0 +1,424 @@
+use crate::banked_memory::BankedMemory;
+use crate::cartridge::Cartridge;
+use crate::mapper::Mapper;
+use crate::mbc::RAM_ENABLE_VALUE;
+
+// The second of the two RAM enables, written to 0x4000-0x5fff
+const RAM_ENABLE_2_VALUE: u8 = 0x40;
+
+const ERASE_VALUE: u8 = 0x55;
+const LATCH_VALUE: u8 = 0xaa;
+
+// What the accelerometer reads when level, and roughly how far 1g moves it
+const ACCELEROMETER_CENTER: f32 = 0x81d0 as f32;
+const ACCELEROMETER_GRAVITY: f32 = 0x70 as f32;
+// What the latched values read between erasing and latching
+const ACCELEROMETER_ERASED: u16 = 0x8000;
+
+// Bits of the EEPROM register
+const CHIP_SELECT: u8 = 0b1000_0000;
+const CLOCK: u8 = 0b0100_0000;
+const DATA_IN: u8 = 0b0000_0010;
+const DATA_OUT: u8 = 0b0000_0001;
+
+// The 93LC56 holds 128 words of 16 bits
+const EEPROM_WORDS: usize = 128;
+// Bits following the start bit: a 2 bit opcode and an 8 bit address
+const COMMAND_BITS: u8 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EepromState {
+    /// Waiting for a start bit
+    Idle,
+    Command {
+        bits: u16,
+        count: u8,
+    },
+    /// Shifting out words, starting at `address` and carrying on into the
+    /// ones after it for as long as the clock keeps going
+    Reading {
+        address: usize,
+        bit: u8,
+    },
+    /// Shifting in a word, for one address or every one of them
+    Writing {
+        address: Option<usize>,
+        bits: u16,
+        count: u8,
+    },
+    /// The command has run, and anything else is ignored until CS drops
+    Done,
+}
+
+/// A 93LC56 serial EEPROM, which the game bit-bangs through a single
+/// register. Bits are shifted in and out on the rising edge of the clock
+/// while chip select is held high.
+struct Eeprom {
+    words: Vec<u16>,
+    write_enabled: bool,
+    register: u8,
+    data_out: bool,
+    state: EepromState,
+}
+
+impl Default for Eeprom {
+    fn default() -> Self {
+        Eeprom {
+            words: vec![0xffff; EEPROM_WORDS],
+            write_enabled: false,
+            register: 0,
+            data_out: true,
+            state: EepromState::Idle,
+        }
+    }
+}
+
+impl Eeprom {
+    fn read(&self) -> u8 {
+        let data_out = if self.data_out { DATA_OUT } else { 0 };
+        (self.register & (CHIP_SELECT | CLOCK | DATA_IN)) | data_out
+    }
+
+    fn write(&mut self, value: u8) {
+        let rising_edge = self.register & CLOCK == 0 && value & CLOCK != 0;
+        self.register = value;
+
+        if value & CHIP_SELECT == 0 {
+            // Dropping CS ends any command, and the chip reports it's ready
+            self.state = EepromState::Idle;
+            self.data_out = true;
+        } else if rising_edge {
+            self.clock_in(value & DATA_IN != 0);
+        }
+    }
+
+    fn clock_in(&mut self, bit: bool) {
+        self.state = match self.state {
+            EepromState::Idle if bit => EepromState::Command { bits: 0, count: 0 },
+            EepromState::Idle => EepromState::Idle,
+            EepromState::Command { bits, count } => {
+                let bits = (bits << 1) | bit as u16;
+
+                if count + 1 == COMMAND_BITS {
+                    self.run(bits)
+                } else {
+                    EepromState::Command {
+                        bits,
+                        count: count + 1,
+                    }
+                }
+            }
+            EepromState::Reading { address, bit } => {
+                self.data_out = self.words[address] & (0x8000 >> bit) != 0;
+
+                if bit == 15 {
+                    EepromState::Reading {
+                        address: (address + 1) % EEPROM_WORDS,
+                        bit: 0,
+                    }
+                } else {
+                    EepromState::Reading {
+                        address,
+                        bit: bit + 1,
+                    }
+                }
+            }
+            EepromState::Writing {
+                address,
+                bits,
+                count,
+            } => {
+                let bits = (bits << 1) | bit as u16;
+
+                if count + 1 < 16 {
+                    EepromState::Writing {
+                        address,
+                        bits,
+                        count: count + 1,
+                    }
+                } else {
+                    self.store(address, bits);
+                    EepromState::Done
+                }
+            }
+            EepromState::Done => EepromState::Done,
+        };
+    }
+
+    /// Starts the command made up of the 10 bits after the start bit
+    fn run(&mut self, command: u16) -> EepromState {
+        // The top address bit isn't used by the 128 word part
+        let address = usize::from(command & 0x7f);
+
+        match command >> 8 {
+            0b10 => {
+                // A dummy zero comes out before the data
+                self.data_out = false;
+                EepromState::Reading { address, bit: 0 }
+            }
+            0b01 => EepromState::Writing {
+                address: Some(address),
+                bits: 0,
+                count: 0,
+            },
+            0b11 => {
+                self.store(Some(address), 0xffff);
+                EepromState::Done
+            }
+            _ => match (command >> 6) & 0b11 {
+                0b11 => {
+                    self.write_enabled = true;
+                    EepromState::Done
+                }
+                0b00 => {
+                    self.write_enabled = false;
+                    EepromState::Done
+                }
+                0b10 => {
+                    self.store(None, 0xffff);
+                    EepromState::Done
+                }
+                _ => EepromState::Writing {
+                    address: None,
+                    bits: 0,
+                    count: 0,
+                },
+            },
+        }
+    }
+
+    /// Writes one word, or all of them, if writes have been enabled
+    fn store(&mut self, address: Option<usize>, value: u16) {
+        if !self.write_enabled {
+            return;
+        }
+
+        match address {
+            Some(address) => self.words[address] = value,
+            None => self.words.iter_mut().for_each(|word| *word = value),
+        }
+    }
+}
+
+/// The MBC7, used by Kirby Tilt 'n' Tumble. On top of ROM banking it has a
+/// two axis accelerometer, and a serial EEPROM in place of RAM. Both sit in
+/// 0xa000-0xafff, where bits 4-7 of the address pick the register.
+pub struct Mbc7 {
+    rom: BankedMemory,
+    rom_bank: usize,
+    ram_enabled: bool,
+    ram_enabled_2: bool,
+    tilt_x: f32,
+    tilt_y: f32,
+    latched_x: u16,
+    latched_y: u16,
+    // Latching only works after the previous values have been erased
+    latch_armed: bool,
+    eeprom: Eeprom,
+}
+
+impl From<Cartridge> for Mbc7 {
+    fn from(cartridge: Cartridge) -> Self {
+        Mbc7::new(BankedMemory::from(cartridge))
+    }
+}
+
+impl Mbc7 {
+    pub fn new(rom: BankedMemory) -> Self {
+        Mbc7 {
+            rom,
+            rom_bank: 1,
+            ram_enabled: false,
+            ram_enabled_2: false,
+            tilt_x: 0.0,
+            tilt_y: 0.0,
+            latched_x: ACCELEROMETER_ERASED,
+            latched_y: ACCELEROMETER_ERASED,
+            latch_armed: false,
+            eeprom: Eeprom::default(),
+        }
+    }
+
+    fn latch(&mut self) {
+        let reading = |tilt: f32| (ACCELEROMETER_CENTER + tilt * ACCELEROMETER_GRAVITY) as u16;
+
+        self.latched_x = reading(self.tilt_x);
+        self.latched_y = reading(self.tilt_y);
+        self.latch_armed = false;
+    }
+}
+
+impl Mapper for Mbc7 {
+    fn read_rom(&self, location: usize) -> u8 {
+        match location {
+            0x0000..=0x3fff => self.rom.value_in_bank(0, location),
+            _ => {
+                let bank = self.rom_bank & (self.rom.banks.len().next_power_of_two() - 1);
+                self.rom.value_in_bank(bank, location - 0x4000)
+            }
+        }
+    }
+
+    fn write_rom(&mut self, location: usize, value: u8) {
+        match location {
+            0x0000..=0x1fff => self.ram_enabled = value == RAM_ENABLE_VALUE,
+            0x2000..=0x3fff => self.rom_bank = usize::from(value & 0b0111_1111),
+            0x4000..=0x5fff => self.ram_enabled_2 = value == RAM_ENABLE_2_VALUE,
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, location: usize) -> u8 {
+        if !self.ram_enabled || !self.ram_enabled_2 || location >= 0x1000 {
+            return 0xff;
+        }
+
+        match (location >> 4) & 0xf {
+            0x2 => self.latched_x as u8,
+            0x3 => (self.latched_x >> 8) as u8,
+            0x4 => self.latched_y as u8,
+            0x5 => (self.latched_y >> 8) as u8,
+            // Would be a Z axis, but there's nothing there
+            0x6 => 0x00,
+            0x8 => self.eeprom.read(),
+            _ => 0xff,
+        }
+    }
+
+    fn write_ram(&mut self, location: usize, value: u8) {
+        if !self.ram_enabled || !self.ram_enabled_2 || location >= 0x1000 {
+            return;
+        }
+
+        match (location >> 4) & 0xf {
+            0x0 if value == ERASE_VALUE => {
+                self.latched_x = ACCELEROMETER_ERASED;
+                self.latched_y = ACCELEROMETER_ERASED;
+                self.latch_armed = true;
+            }
+            0x1 if value == LATCH_VALUE && self.latch_armed => self.latch(),
+            0x8 => self.eeprom.write(value),
+            _ => {}
+        }
+    }
+
+    /// The EEPROM contents, with each word stored little endian
+    fn save_data(&self) -> Vec<u8> {
+        self.eeprom
+            .words
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        for (word, bytes) in self.eeprom.words.iter_mut().zip(data.chunks_exact(2)) {
+            *word = u16::from_le_bytes([bytes[0], bytes[1]]);
+        }
+    }
+
+    fn set_tilt(&mut self, x: f32, y: f32) {
+        self.tilt_x = x;
+        self.tilt_y = y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::banked_memory::{BankedMemory, ROM_BANK_SIZE};
+    use crate::mapper::mbc7::{Mbc7, CHIP_SELECT, CLOCK, DATA_IN};
+    use crate::mapper::Mapper;
+    use crate::mbc::RAM_ENABLE_VALUE;
+
+    const EEPROM: usize = 0x0080;
+
+    fn get_mbc7() -> Mbc7 {
+        let mut mbc = Mbc7::new(BankedMemory::new(0, ROM_BANK_SIZE, 4));
+
+        mbc.write_rom(0x0000, RAM_ENABLE_VALUE);
+        mbc.write_rom(0x4000, 0x40);
+        mbc
+    }
+
+    /// Clocks bits into the EEPROM, returning what it put out on each edge
+    fn send(mbc: &mut Mbc7, bits: &[u8]) -> Vec<u8> {
+        bits.iter()
+            .map(|bit| {
+                let data = if *bit == 1 { DATA_IN } else { 0 };
+
+                mbc.write_ram(EEPROM, CHIP_SELECT | data);
+                mbc.write_ram(EEPROM, CHIP_SELECT | CLOCK | data);
+                mbc.read_ram(EEPROM) & 1
+            })
+            .collect()
+    }
+
+    fn end_command(mbc: &mut Mbc7) {
+        mbc.write_ram(EEPROM, 0x00);
+    }
+
+    #[test]
+    fn accelerometer_latches_after_erase() {
+        let mut mbc = get_mbc7();
+
+        mbc.set_tilt(1.0, 0.0);
+
+        // Latching without erasing first does nothing
+        mbc.write_ram(0x0010, 0xaa);
+        assert_eq!(mbc.read_ram(0x0030), 0x80);
+
+        mbc.write_ram(0x0000, 0x55);
+        mbc.write_ram(0x0010, 0xaa);
+
+        assert_eq!(mbc.read_ram(0x0020), 0x40);
+        assert_eq!(mbc.read_ram(0x0030), 0x82);
+        assert_eq!(mbc.read_ram(0x0040), 0xd0);
+        assert_eq!(mbc.read_ram(0x0050), 0x81);
+    }
+
+    #[test]
+    fn registers_need_both_enables() {
+        let mut mbc = get_mbc7();
+
+        mbc.write_rom(0x4000, 0x00);
+        assert_eq!(mbc.read_ram(0x0050), 0xff);
+    }
+
+    #[test]
+    fn eeprom_writes_and_reads_back_a_word() {
+        let mut mbc = get_mbc7();
+
+        // EWEN
+        send(&mut mbc, &[1, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0]);
+        end_command(&mut mbc);
+
+        // WRITE 0x1234 to word 3
+        send(&mut mbc, &[1, 0, 1, 0, 0, 0, 0, 0, 0, 1, 1]);
+        send(&mut mbc, &[0, 0, 0, 1, 0, 0, 1, 0, 0, 0, 1, 1, 0, 1, 0, 0]);
+        end_command(&mut mbc);
+
+        // READ word 3, which starts with a dummy zero
+        let out = send(&mut mbc, &[1, 1, 0, 0, 0, 0, 0, 0, 0, 1, 1]);
+        assert_eq!(out[10], 0);
+
+        let word = send(&mut mbc, &[0; 16]);
+        assert_eq!(word, vec![0, 0, 0, 1, 0, 0, 1, 0, 0, 0, 1, 1, 0, 1, 0, 0]);
+        end_command(&mut mbc);
+
+        assert_eq!(&mbc.save_data()[6..8], &[0x34, 0x12]);
+    }
+
+    #[test]
+    fn eeprom_ignores_writes_until_enabled() {
+        let mut mbc = get_mbc7();
+
+        // ERAL is ignored, as is writing word 0
+        send(&mut mbc, &[1, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+        end_command(&mut mbc);
+        send(&mut mbc, &[1, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0]);
+        send(&mut mbc, &[0; 16]);
+        end_command(&mut mbc);
+
+        assert!(mbc.save_data().iter().all(|byte| *byte == 0xff));
+    }
+}
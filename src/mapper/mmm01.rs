@@ -0,0 +1,273 @@
+use crate::banked_memory::BankedMemory;
+use crate::cartridge::Cartridge;
+use crate::cartridge_header::CartridgeHeader;
+use crate::cartridge_type::CartridgeType;
+use crate::mapper::Mapper;
+use crate::mbc::RAM_ENABLE_VALUE;
+
+// The menu sits in the last 32 KiB of the ROM, and so does the header that
+// says the cartridge is an MMM01. The one at the start belongs to a game.
+const MENU_SIZE: usize = 0x8000;
+
+// Written to 0x0000-0x1fff along with the RAM enable to leave the menu
+const MAP_BIT: u8 = 0b0100_0000;
+// Written to 0x4000-0x5fff, stops the game changing the banking mode
+const MODE_LOCK_BIT: u8 = 0b0100_0000;
+
+/// The header of the menu, for images dumped with it at the end
+pub fn menu_header(data: &[u8]) -> Option<CartridgeHeader> {
+    let menu = data
+        .len()
+        .checked_sub(MENU_SIZE)
+        .map(|start| &data[start..])?;
+
+    CartridgeHeader::from_binary(menu).ok().filter(|header| {
+        matches!(
+            header.cartridge_type,
+            CartridgeType::MMM01 | CartridgeType::MMM01Ram | CartridgeType::MMM01RamBattery
+        )
+    })
+}
+
+/// The MMM01, which puts several MBC1 style games on one cartridge. It
+/// starts out unmapped, running the menu from the end of the ROM with every
+/// register writable. Once the menu picks a game it sets the outer ROM and
+/// RAM banks, masks off which of the inner bank bits the game still gets to
+/// change, and maps itself, after which only the game's own banking works.
+pub struct Mmm01 {
+    rom: BankedMemory,
+    ram: BankedMemory,
+    mapped: bool,
+    ram_enabled: bool,
+    // Bits 0-4 of the ROM bank, which the game switches
+    rom_bank_low: u8,
+    // Bits 5-6 and 7-8, which pick the game
+    rom_bank_mid: u8,
+    rom_bank_high: u8,
+    // Bits of rom_bank_low that belong to the menu once mapped
+    rom_bank_mask: u8,
+    ram_bank_low: u8,
+    ram_bank_high: u8,
+    ram_bank_mask: u8,
+    mode: u8,
+    mode_locked: bool,
+}
+
+impl From<Cartridge> for Mmm01 {
+    fn from(cartridge: Cartridge) -> Self {
+        let ram_size = menu_header(&cartridge.data)
+            .map_or(cartridge.header.ram_size, |header| header.ram_size);
+        let ram = BankedMemory::of_size(ram_size);
+
+        Mmm01::new(BankedMemory::from(cartridge), ram)
+    }
+}
+
+impl Mmm01 {
+    pub fn new(rom: BankedMemory, ram: BankedMemory) -> Self {
+        Mmm01 {
+            rom,
+            ram,
+            mapped: false,
+            ram_enabled: false,
+            rom_bank_low: 0,
+            rom_bank_mid: 0,
+            rom_bank_high: 0,
+            rom_bank_mask: 0,
+            ram_bank_low: 0,
+            ram_bank_high: 0,
+            ram_bank_mask: 0,
+            mode: 0,
+            mode_locked: false,
+        }
+    }
+
+    /// Only the bits the menu hasn't masked off can be changed once mapped
+    fn masked_write(&self, current: u8, value: u8, mask: u8) -> u8 {
+        if self.mapped {
+            (current & mask) | (value & !mask)
+        } else {
+            value
+        }
+    }
+
+    fn rom_size_mask(&self) -> usize {
+        self.rom.banks.len().next_power_of_two() - 1
+    }
+
+    fn outer_rom_bank(&self) -> usize {
+        (usize::from(self.rom_bank_high) << 7) | (usize::from(self.rom_bank_mid) << 5)
+    }
+
+    fn low_rom_bank(&self) -> usize {
+        if !self.mapped {
+            // Every bank line is held high, bar the one for 0x4000
+            return 0x1fe & self.rom_size_mask();
+        }
+
+        let bank = self.outer_rom_bank() | usize::from(self.rom_bank_low & self.rom_bank_mask);
+        bank & self.rom_size_mask()
+    }
+
+    fn high_rom_bank(&self) -> usize {
+        if !self.mapped {
+            return 0x1ff & self.rom_size_mask();
+        }
+
+        // Like the MBC1, bank 0 becomes 1, but only the game's bits count
+        let mut low = self.rom_bank_low;
+
+        if low & !self.rom_bank_mask == 0 {
+            low |= 1;
+        }
+
+        (self.outer_rom_bank() | usize::from(low)) & self.rom_size_mask()
+    }
+
+    fn ram_bank(&self) -> usize {
+        let low = if self.mode == 1 {
+            self.ram_bank_low
+        } else {
+            self.ram_bank_low & self.ram_bank_mask
+        };
+        let bank = usize::from((self.ram_bank_high << 2) | low);
+
+        bank & (self.ram.banks.len().next_power_of_two() - 1)
+    }
+}
+
+impl Mapper for Mmm01 {
+    fn read_rom(&self, location: usize) -> u8 {
+        match location {
+            0x0000..=0x3fff => self.rom.value_in_bank(self.low_rom_bank(), location),
+            _ => self
+                .rom
+                .value_in_bank(self.high_rom_bank(), location - 0x4000),
+        }
+    }
+
+    fn write_rom(&mut self, location: usize, value: u8) {
+        match location {
+            0x0000..=0x1fff => {
+                self.ram_enabled = (value & 0b0000_1111) == RAM_ENABLE_VALUE;
+
+                if !self.mapped {
+                    self.ram_bank_mask = (value >> 4) & 0b0000_0011;
+                    self.mapped = value & MAP_BIT != 0;
+                }
+            }
+            0x2000..=0x3fff => {
+                self.rom_bank_low =
+                    self.masked_write(self.rom_bank_low, value & 0b0001_1111, self.rom_bank_mask);
+
+                if !self.mapped {
+                    self.rom_bank_mid = (value >> 5) & 0b0000_0011;
+                }
+            }
+            0x4000..=0x5fff => {
+                self.ram_bank_low =
+                    self.masked_write(self.ram_bank_low, value & 0b0000_0011, self.ram_bank_mask);
+
+                if !self.mapped {
+                    self.ram_bank_high = (value >> 2) & 0b0000_0011;
+                    self.rom_bank_high = (value >> 4) & 0b0000_0011;
+                    self.mode_locked = value & MODE_LOCK_BIT != 0;
+                }
+            }
+            _ => {
+                if !self.mode_locked {
+                    self.mode = value & 0b0000_0001;
+                }
+
+                // The mask covers bits 1-4 of the ROM bank
+                if !self.mapped {
+                    self.rom_bank_mask = ((value >> 2) & 0b0000_1111) << 1;
+                }
+            }
+        }
+    }
+
+    fn read_ram(&self, location: usize) -> u8 {
+        if !self.ram_enabled {
+            return 0xff;
+        }
+
+        self.ram.value_in_bank(self.ram_bank(), location)
+    }
+
+    fn write_ram(&mut self, location: usize, value: u8) {
+        if self.ram_enabled {
+            self.ram.set_in_bank(self.ram_bank(), location, value);
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.to_bytes()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        self.ram.load_bytes(data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::banked_memory::{BankedMemory, ROM_BANK_SIZE};
+    use crate::cartridge_header::RAM_BANK_SIZE;
+    use crate::mapper::mmm01::{menu_header, Mmm01, MENU_SIZE};
+    use crate::mapper::{numbered_rom, Mapper};
+    use crate::mbc::RAM_ENABLE_VALUE;
+
+    #[test]
+    fn boots_from_the_last_32_kib() {
+        let mbc = Mmm01::new(numbered_rom(32), BankedMemory::new(0, 0, 0));
+
+        assert_eq!(mbc.read_rom(0x0000), 30);
+        assert_eq!(mbc.read_rom(0x4000), 31);
+    }
+
+    #[test]
+    fn menu_maps_a_game_and_locks_its_outer_bank() {
+        let mut mbc = Mmm01::new(numbered_rom(32), BankedMemory::new(0, 0, 0));
+
+        // An 8 bank game starting at bank 8, so the menu keeps bits 3-4
+        mbc.write_rom(0x2000, 0x08);
+        mbc.write_rom(0x6000, 0b0011_0000);
+        mbc.write_rom(0x0000, 0x40);
+
+        assert_eq!(mbc.read_rom(0x0000), 8);
+        assert_eq!(mbc.read_rom(0x4000), 9);
+
+        mbc.write_rom(0x2000, 0x03);
+        assert_eq!(mbc.read_rom(0x4000), 11);
+
+        // Bank 0 of the game is bumped to 1, and the outer bits stay put
+        mbc.write_rom(0x2000, 0x10);
+        assert_eq!(mbc.read_rom(0x4000), 9);
+        mbc.write_rom(0x4000, 0x30);
+        assert_eq!(mbc.read_rom(0x0000), 8);
+    }
+
+    #[test]
+    fn ram_bank_high_bits_are_set_by_the_menu() {
+        let mut mbc = Mmm01::new(numbered_rom(4), BankedMemory::new(0, RAM_BANK_SIZE, 16));
+
+        mbc.write_rom(0x4000, 0b0000_1000);
+        mbc.write_rom(0x0000, 0x40 | RAM_ENABLE_VALUE);
+        mbc.write_ram(0x0000, 0x42);
+
+        mbc.write_rom(0x4000, 0x00);
+        assert_eq!(mbc.ram_bank(), 8);
+        assert_eq!(mbc.read_ram(0x0000), 0x42);
+    }
+
+    #[test]
+    fn finds_the_menu_header_at_the_end() {
+        let mut data = vec![0x00; 4 * ROM_BANK_SIZE];
+        data[0x147] = 0x01;
+        data[4 * ROM_BANK_SIZE - MENU_SIZE + 0x147] = 0x0b;
+
+        assert!(menu_header(&data).is_some());
+        assert!(menu_header(&data[..MENU_SIZE]).is_none());
+    }
+}
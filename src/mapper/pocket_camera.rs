@@ -0,0 +1,337 @@
+use crate::banked_memory::BankedMemory;
+use crate::camera::{CameraImage, SENSOR_HEIGHT, SENSOR_WIDTH};
+use crate::cartridge::Cartridge;
+use crate::mapper::Mapper;
+use crate::mbc::RAM_ENABLE_VALUE;
+
+// Set in the RAM bank register to map the sensor's registers instead
+const REGISTERS_SELECT_BIT: u8 = 0b0001_0000;
+
+const CONTROL_REGISTER: usize = 0x00;
+const EDGE_MODE_REGISTER: usize = 0x01;
+const EXPOSURE_HIGH_REGISTER: usize = 0x02;
+const EXPOSURE_LOW_REGISTER: usize = 0x03;
+const EDGE_RATIO_REGISTER: usize = 0x04;
+// 4x4 cells of 3 thresholds each, which dither the picture to 4 shades
+const DITHER_MATRIX_REGISTER: usize = 0x06;
+const REGISTER_COUNT: usize = 0x36;
+
+const CAPTURE_BIT: u8 = 0b0000_0001;
+// In the edge mode register
+const NEGATIVE_BIT: u8 = 0b1000_0000;
+const HORIZONTAL_EDGE_BIT: u8 = 0b0010_0000;
+const VERTICAL_EDGE_BIT: u8 = 0b0100_0000;
+// In the edge ratio register
+const INVERT_BIT: u8 = 0b0000_1000;
+
+// How strongly edges are enhanced, in quarters
+const EDGE_RATIOS: [i32; 8] = [2, 3, 4, 5, 8, 12, 16, 20];
+
+// Where a finished photo is written, as 16x14 tiles in RAM bank 0
+const PHOTO_LOCATION: usize = 0x0100;
+
+/// The Pocket Camera, an MBC with 64 ROM banks and 16 RAM banks, plus the
+/// registers of its M64282FP sensor. Taking a photo exposes the sensor to
+/// whatever image the host has supplied, runs it through the edge
+/// enhancement and dithering the game has set up, and writes the result to
+/// RAM as tiles once the capture time has passed.
+pub struct PocketCamera {
+    rom: BankedMemory,
+    ram: BankedMemory,
+    ram_enabled: bool,
+    rom_bank: usize,
+    ram_bank: usize,
+    registers_selected: bool,
+    registers: [u8; REGISTER_COUNT],
+    image: Option<CameraImage>,
+    // T-cycles until the photo being taken is ready
+    capture_cycles: u32,
+}
+
+impl From<Cartridge> for PocketCamera {
+    fn from(cartridge: Cartridge) -> Self {
+        let ram = BankedMemory::of_size(cartridge.header.ram_size);
+
+        PocketCamera::new(BankedMemory::from(cartridge), ram)
+    }
+}
+
+impl PocketCamera {
+    pub fn new(rom: BankedMemory, ram: BankedMemory) -> Self {
+        PocketCamera {
+            rom,
+            ram,
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            registers_selected: false,
+            registers: [0; REGISTER_COUNT],
+            image: None,
+            capture_cycles: 0,
+        }
+    }
+
+    fn exposure(&self) -> u32 {
+        u32::from(self.registers[EXPOSURE_HIGH_REGISTER]) << 8
+            | u32::from(self.registers[EXPOSURE_LOW_REGISTER])
+    }
+
+    /// How long a capture takes, which mostly depends on the exposure time
+    fn capture_time(&self) -> u32 {
+        let negative_time = if self.registers[EDGE_MODE_REGISTER] & NEGATIVE_BIT == 0 {
+            512
+        } else {
+            0
+        };
+
+        // In M-cycles
+        (32446 + negative_time + 16 * self.exposure()) * 4
+    }
+
+    /// What the sensor reads at a pixel with the current exposure, where an
+    /// exposure of 0x1000 sees the image as it is
+    fn sensor_value(&self, x: usize, y: usize) -> i32 {
+        let brightness = self
+            .image
+            .as_ref()
+            .map_or(0, |image| image.sensor_pixel(x, y));
+
+        (u32::from(brightness) * self.exposure() / 0x1000).min(0xff) as i32
+    }
+
+    fn processed_value(&self, x: usize, y: usize) -> i32 {
+        let mode = self.registers[EDGE_MODE_REGISTER];
+        let ratio = EDGE_RATIOS[usize::from(self.registers[EDGE_RATIO_REGISTER] >> 4 & 0b111)];
+        let value = self.sensor_value(x, y);
+        let mut neighbours = Vec::new();
+
+        if mode & HORIZONTAL_EDGE_BIT != 0 {
+            neighbours.push(self.sensor_value(x.saturating_sub(1), y));
+            neighbours.push(self.sensor_value((x + 1).min(SENSOR_WIDTH - 1), y));
+        }
+
+        if mode & VERTICAL_EDGE_BIT != 0 {
+            neighbours.push(self.sensor_value(x, y.saturating_sub(1)));
+            neighbours.push(self.sensor_value(x, (y + 1).min(SENSOR_HEIGHT - 1)));
+        }
+
+        // Pushes each pixel away from the ones around it
+        let difference: i32 = neighbours.iter().map(|neighbour| value - neighbour).sum();
+        let value = (value + difference * ratio / 4).clamp(0, 0xff);
+
+        if self.registers[EDGE_RATIO_REGISTER] & INVERT_BIT != 0 {
+            0xff - value
+        } else {
+            value
+        }
+    }
+
+    /// The shade from 0 for white to 3 for black, going by the thresholds
+    /// for this pixel's cell of the dithering matrix
+    fn shade(&self, x: usize, y: usize) -> u8 {
+        let value = self.processed_value(x, y);
+        let cell = DITHER_MATRIX_REGISTER + ((y % 4) * 4 + x % 4) * 3;
+        let thresholds = &self.registers[cell..cell + 3];
+
+        thresholds
+            .iter()
+            .position(|threshold| value < i32::from(*threshold))
+            .map_or(0, |level| 3 - level as u8)
+    }
+
+    fn take_photo(&mut self) {
+        for y in 0..SENSOR_HEIGHT {
+            for x in 0..SENSOR_WIDTH {
+                let shade = self.shade(x, y);
+                let tile = (y / 8) * (SENSOR_WIDTH / 8) + x / 8;
+                let location = PHOTO_LOCATION + tile * 16 + (y % 8) * 2;
+                let bit = 0x80 >> (x % 8);
+
+                for plane in 0..2 {
+                    let byte = self.ram.value_in_bank(0, location + plane);
+                    let byte = if shade >> plane & 1 != 0 {
+                        byte | bit
+                    } else {
+                        byte & !bit
+                    };
+
+                    self.ram.set_in_bank(0, location + plane, byte);
+                }
+            }
+        }
+    }
+}
+
+impl Mapper for PocketCamera {
+    fn read_rom(&self, location: usize) -> u8 {
+        match location {
+            0x0000..=0x3fff => self.rom.value_in_bank(0, location),
+            _ => {
+                let bank = self.rom_bank & (self.rom.banks.len().next_power_of_two() - 1);
+                self.rom.value_in_bank(bank, location - 0x4000)
+            }
+        }
+    }
+
+    fn write_rom(&mut self, location: usize, value: u8) {
+        match location {
+            0x0000..=0x1fff => self.ram_enabled = (value & 0b0000_1111) == RAM_ENABLE_VALUE,
+            0x2000..=0x3fff => self.rom_bank = usize::from(value & 0b0011_1111),
+            0x4000..=0x5fff => {
+                self.registers_selected = value & REGISTERS_SELECT_BIT != 0;
+                self.ram_bank = usize::from(value & 0b0000_1111);
+            }
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, location: usize) -> u8 {
+        if self.registers_selected {
+            // Only the control register can be read back, to see whether
+            // the photo is done
+            return match location & 0x7f {
+                CONTROL_REGISTER => self.registers[CONTROL_REGISTER],
+                _ => 0x00,
+            };
+        }
+
+        // Unlike other MBCs the RAM can be read while it's disabled, only
+        // writes need enabling
+        self.ram.value_in_bank(self.ram_bank, location)
+    }
+
+    fn write_ram(&mut self, location: usize, value: u8) {
+        if self.registers_selected {
+            let register = location & 0x7f;
+
+            if register == CONTROL_REGISTER {
+                self.registers[CONTROL_REGISTER] = value & 0b0000_0111;
+
+                if value & CAPTURE_BIT != 0 {
+                    self.capture_cycles = self.capture_time();
+                }
+            } else if register < REGISTER_COUNT {
+                self.registers[register] = value;
+            }
+        } else if self.ram_enabled {
+            self.ram.set_in_bank(self.ram_bank, location, value);
+        }
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        if self.registers[CONTROL_REGISTER] & CAPTURE_BIT == 0 {
+            return;
+        }
+
+        self.capture_cycles = self.capture_cycles.saturating_sub(cycles);
+
+        if self.capture_cycles == 0 {
+            self.take_photo();
+            self.registers[CONTROL_REGISTER] &= !CAPTURE_BIT;
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.to_bytes()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        self.ram.load_bytes(data);
+    }
+
+    fn set_camera_image(&mut self, image: CameraImage) {
+        self.image = Some(image);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::banked_memory::{BankedMemory, ROM_BANK_SIZE};
+    use crate::camera::CameraImage;
+    use crate::cartridge_header::RAM_BANK_SIZE;
+    use crate::mapper::pocket_camera::PocketCamera;
+    use crate::mapper::Mapper;
+    use crate::mbc::RAM_ENABLE_VALUE;
+
+    fn get_camera() -> PocketCamera {
+        let mut camera = PocketCamera::new(
+            BankedMemory::new(0, ROM_BANK_SIZE, 64),
+            BankedMemory::new(0, RAM_BANK_SIZE, 16),
+        );
+
+        camera.write_rom(0x0000, RAM_ENABLE_VALUE);
+        camera.write_rom(0x4000, 0x10);
+
+        // Thresholds of 0x40, 0x80 and 0xc0 everywhere, at full exposure
+        camera.write_ram(0x0002, 0x10);
+        camera.write_ram(0x0003, 0x00);
+        for cell in 0..16 {
+            camera.write_ram(0x0006 + cell * 3, 0x40);
+            camera.write_ram(0x0007 + cell * 3, 0x80);
+            camera.write_ram(0x0008 + cell * 3, 0xc0);
+        }
+
+        camera
+    }
+
+    fn take_photo(camera: &mut PocketCamera) {
+        camera.write_ram(0x0000, 0x01);
+        assert_eq!(camera.read_ram(0x0000) & 0x01, 0x01);
+
+        camera.tick(camera.capture_cycles);
+        assert_eq!(camera.read_ram(0x0000) & 0x01, 0x00);
+
+        camera.write_rom(0x4000, 0x00);
+    }
+
+    #[test]
+    fn photo_is_dithered_into_tiles() {
+        let mut camera = get_camera();
+        // Black on the left, white on the right
+        camera.set_camera_image(CameraImage::new(2, 1, vec![0x00, 0xff]).unwrap());
+
+        take_photo(&mut camera);
+
+        // The first tile is black, and the last in the first row is white
+        assert_eq!(camera.read_ram(0x0100), 0xff);
+        assert_eq!(camera.read_ram(0x0101), 0xff);
+        assert_eq!(camera.read_ram(0x0100 + 15 * 16), 0x00);
+        assert_eq!(camera.read_ram(0x0101 + 15 * 16), 0x00);
+    }
+
+    #[test]
+    fn middle_grey_gives_middle_shades() {
+        let mut camera = get_camera();
+        camera.set_camera_image(CameraImage::new(1, 1, vec![0x90]).unwrap());
+
+        take_photo(&mut camera);
+
+        // Shade 1, so only the low bit plane
+        assert_eq!(camera.read_ram(0x0100), 0xff);
+        assert_eq!(camera.read_ram(0x0101), 0x00);
+    }
+
+    #[test]
+    fn edge_enhancement_and_invert() {
+        let mut camera = get_camera();
+        camera.set_camera_image(CameraImage::new(1, 1, vec![0x90]).unwrap());
+        // Enhancing a flat image changes nothing, inverting makes it shade 2
+        camera.write_ram(0x0001, 0x60);
+        camera.write_ram(0x0004, 0x78);
+
+        take_photo(&mut camera);
+
+        assert_eq!(camera.read_ram(0x0100), 0x00);
+        assert_eq!(camera.read_ram(0x0101), 0xff);
+    }
+
+    #[test]
+    fn capture_waits_for_the_exposure() {
+        let mut camera = get_camera();
+
+        camera.write_ram(0x0000, 0x01);
+        camera.tick(1000);
+
+        assert_eq!(camera.read_ram(0x0000), 0x01);
+    }
+}
@@ -0,0 +1,47 @@
+use crate::banked_memory::{BankedMemory, ROM_BANK_SIZE};
+use crate::cartridge::Cartridge;
+use crate::mapper::Mapper;
+
+/// A cartridge with no banking controller. 32 KiB of ROM is mapped straight
+/// in, along with up to 8 KiB of RAM on the carts that have it.
+pub struct RomOnly {
+    rom: BankedMemory,
+    ram: BankedMemory,
+}
+
+impl From<Cartridge> for RomOnly {
+    fn from(cartridge: Cartridge) -> Self {
+        let ram = BankedMemory::of_size(cartridge.header.ram_size);
+
+        RomOnly {
+            rom: BankedMemory::from(cartridge),
+            ram,
+        }
+    }
+}
+
+impl Mapper for RomOnly {
+    fn read_rom(&self, location: usize) -> u8 {
+        self.rom
+            .value_in_bank(location / ROM_BANK_SIZE, location % ROM_BANK_SIZE)
+    }
+
+    // There are no registers to write to
+    fn write_rom(&mut self, _location: usize, _value: u8) {}
+
+    fn read_ram(&self, location: usize) -> u8 {
+        self.ram.value_in_bank(0, location)
+    }
+
+    fn write_ram(&mut self, location: usize, value: u8) {
+        self.ram.set_in_bank(0, location, value);
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.to_bytes()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        self.ram.load_bytes(data);
+    }
+}
@@ -0,0 +1,88 @@
+use std::convert::TryInto;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// Real-time clocks keep running while the emulator isn't, so their saves
+/// end in a footer of 32-bit little endian words for the clock's state and
+/// then the UNIX time it was written at. This keeps that time from a loaded
+/// save until the clock has caught up with it.
+#[derive(Default)]
+pub struct RtcSave {
+    saved_at: Option<u64>,
+}
+
+impl RtcSave {
+    /// The clock's `words`, stamped with the current time
+    pub fn footer(words: &[u32]) -> Vec<u8> {
+        let mut footer = Vec::with_capacity(words.len() * 4 + 8);
+
+        for word in words {
+            footer.extend_from_slice(&word.to_le_bytes());
+        }
+
+        footer.extend_from_slice(&unix_seconds(SystemTime::now()).to_le_bytes());
+        footer
+    }
+
+    /// Reads `count` words back out of a footer. Some emulators only write a
+    /// 32-bit timestamp, so anything up to 8 bytes after the words is taken
+    /// as one.
+    pub fn load(&mut self, footer: &[u8], count: usize) -> Vec<u32> {
+        let (words, timestamp) = footer.split_at(count * 4);
+        let timestamp = &timestamp[..timestamp.len().min(8)];
+
+        let mut bytes = [0; 8];
+        bytes[..timestamp.len()].copy_from_slice(timestamp);
+        self.saved_at = Some(u64::from_le_bytes(bytes));
+
+        words
+            .chunks(4)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+            .collect()
+    }
+
+    /// When the loaded save was written, until the clock is synced with it
+    pub fn saved_at(&self) -> Option<u64> {
+        self.saved_at
+    }
+
+    /// Seconds the clock has to be run forward by to reach `now`, which
+    /// only needs doing once after a save is loaded
+    pub fn elapsed(&mut self, now: SystemTime) -> Option<u64> {
+        self.saved_at
+            .take()
+            .map(|saved_at| unix_seconds(now).saturating_sub(saved_at))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::mapper::rtc_save::RtcSave;
+
+    #[test]
+    fn footer_round_trips_and_catches_up_once() {
+        let footer = RtcSave::footer(&[1, 2, 3]);
+        let mut save = RtcSave::default();
+
+        assert_eq!(save.load(&footer, 3), vec![1, 2, 3]);
+
+        let saved_at = UNIX_EPOCH + Duration::from_secs(save.saved_at().unwrap());
+        assert_eq!(save.elapsed(saved_at + Duration::from_secs(90)), Some(90));
+        assert_eq!(save.elapsed(saved_at + Duration::from_secs(90)), None);
+    }
+
+    #[test]
+    fn reads_short_timestamps() {
+        let mut save = RtcSave::default();
+
+        save.load(&[0, 0, 0, 0, 0x10, 0x00, 0x00, 0x00], 1);
+
+        assert_eq!(save.saved_at(), Some(0x10));
+    }
+}
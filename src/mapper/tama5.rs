@@ -0,0 +1,419 @@
+use std::time::SystemTime;
+
+use crate::banked_memory::BankedMemory;
+use crate::cartridge::Cartridge;
+use crate::cpu::CLOCK_MHZ;
+use crate::mapper::rtc_save::RtcSave;
+use crate::mapper::Mapper;
+
+// The registers behind the window at 0xa000, picked by writing to 0xa001
+const ROM_BANK_LOW_REGISTER: u8 = 0x0;
+const ROM_BANK_HIGH_REGISTER: u8 = 0x1;
+const DATA_LOW_REGISTER: u8 = 0x4;
+const DATA_HIGH_REGISTER: u8 = 0x5;
+// Bit 0 is the top bit of the address, and bits 1-3 the command
+const COMMAND_REGISTER: u8 = 0x6;
+// Writing the bottom of the address runs the command
+const ADDRESS_LOW_REGISTER: u8 = 0x7;
+const STATUS_REGISTER: u8 = 0xa;
+const RESULT_LOW_REGISTER: u8 = 0xc;
+const RESULT_HIGH_REGISTER: u8 = 0xd;
+
+const WRITE_RAM_COMMAND: u8 = 0x0;
+const READ_RAM_COMMAND: u8 = 0x1;
+const WRITE_CLOCK_COMMAND: u8 = 0x2;
+const READ_CLOCK_COMMAND: u8 = 0x3;
+
+// The registers only drive the low nibble
+const UNUSED_BITS: u8 = 0xf0;
+// What the status register reads once the chip is ready for commands
+const READY: u8 = 0xf1;
+
+const RAM_SIZE: usize = 0x20;
+
+// The clock appended to the end of a .sav, as one 32 bit word per register
+// followed by a 64 bit UNIX timestamp
+const CLOCK_REGISTERS: u8 = 13;
+const FOOTER_SIZE: usize = 4 * CLOCK_REGISTERS as usize + 8;
+
+/// The calendar clock, which the game reads and sets a BCD digit at a time
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Clock {
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    day_of_week: u8,
+    day: u8,
+    month: u8,
+    // Two digits, with every fourth year a leap year
+    year: u8,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock {
+            seconds: 0,
+            minutes: 0,
+            hours: 0,
+            day_of_week: 0,
+            day: 1,
+            month: 1,
+            year: 0,
+        }
+    }
+}
+
+impl Clock {
+    fn days_in_month(&self) -> u8 {
+        match self.month {
+            2 if self.year.is_multiple_of(4) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+
+    /// The register's value and whether it holds the tens digit
+    fn field(&mut self, register: u8) -> Option<(&mut u8, bool)> {
+        match register {
+            0x0 | 0x1 => Some((&mut self.seconds, register == 0x1)),
+            0x2 | 0x3 => Some((&mut self.minutes, register == 0x3)),
+            0x4 | 0x5 => Some((&mut self.hours, register == 0x5)),
+            0x6 => Some((&mut self.day_of_week, false)),
+            0x7 | 0x8 => Some((&mut self.day, register == 0x8)),
+            0x9 | 0xa => Some((&mut self.month, register == 0xa)),
+            0xb | 0xc => Some((&mut self.year, register == 0xc)),
+            _ => None,
+        }
+    }
+
+    fn read(&self, register: u8) -> u8 {
+        let mut clock = *self;
+
+        match clock.field(register) {
+            Some((value, true)) => *value / 10,
+            Some((value, false)) => *value % 10,
+            None => 0,
+        }
+    }
+
+    fn write(&mut self, register: u8, digit: u8) {
+        let digit = digit.min(9);
+
+        match self.field(register) {
+            Some((value, true)) => *value = digit * 10 + *value % 10,
+            Some((value, false)) => *value = *value / 10 * 10 + digit,
+            None => {}
+        }
+    }
+
+    fn tick_day(&mut self) {
+        self.day_of_week = (self.day_of_week + 1) % 7;
+        self.day += 1;
+
+        if self.day > self.days_in_month() {
+            self.day = 1;
+            self.month += 1;
+
+            if self.month > 12 {
+                self.month = 1;
+                self.year = (self.year + 1) % 100;
+            }
+        }
+    }
+
+    fn tick_second(&mut self) {
+        self.seconds += 1;
+
+        if self.seconds < 60 {
+            return;
+        }
+
+        self.seconds = 0;
+        self.minutes += 1;
+
+        if self.minutes < 60 {
+            return;
+        }
+
+        self.minutes = 0;
+        self.hours += 1;
+
+        if self.hours < 24 {
+            return;
+        }
+
+        self.hours = 0;
+        self.tick_day();
+    }
+
+    /// Runs the clock forward, as it would have while the emulator was closed
+    fn advance(&mut self, seconds: u64) {
+        for _ in 0..seconds / (60 * 60 * 24) {
+            self.tick_day();
+        }
+
+        for _ in 0..seconds % (60 * 60 * 24) {
+            self.tick_second();
+        }
+    }
+}
+
+/// Bandai's TAMA5, from Tamagotchi 3. Everything, even the ROM bank, is
+/// set through a pair of registers at 0xa000-0xa001: the second picks one
+/// of the chip's registers and the first reads or writes its low nibble.
+/// Commands reach 32 bytes of RAM and a calendar clock.
+pub struct Tama5 {
+    rom: BankedMemory,
+    ram: [u8; RAM_SIZE],
+    clock: Clock,
+    register: u8,
+    rom_bank: usize,
+    data: u8,
+    command: u8,
+    result: u8,
+    // T-cycles into the current second
+    cycles: u32,
+    rtc_save: RtcSave,
+}
+
+impl From<Cartridge> for Tama5 {
+    fn from(cartridge: Cartridge) -> Self {
+        Tama5::new(BankedMemory::from(cartridge))
+    }
+}
+
+impl Tama5 {
+    pub fn new(rom: BankedMemory) -> Self {
+        Tama5 {
+            rom,
+            ram: [0; RAM_SIZE],
+            clock: Clock::default(),
+            register: 0,
+            rom_bank: 1,
+            data: 0,
+            command: 0,
+            result: 0,
+            cycles: 0,
+            rtc_save: RtcSave::default(),
+        }
+    }
+
+    fn run_command(&mut self, address_low: u8) {
+        let address = usize::from((self.command & 0b0000_0001) << 4 | address_low);
+
+        match self.command >> 1 {
+            WRITE_RAM_COMMAND => self.ram[address] = self.data,
+            READ_RAM_COMMAND => self.result = self.ram[address],
+            WRITE_CLOCK_COMMAND => {
+                self.clock.write(address_low, self.data & 0x0f);
+                self.cycles = 0;
+            }
+            READ_CLOCK_COMMAND => self.result = self.clock.read(address_low),
+            _ => {}
+        }
+    }
+
+    fn footer(&self) -> Vec<u8> {
+        let words: Vec<u32> = (0..CLOCK_REGISTERS)
+            .map(|register| u32::from(self.clock.read(register)))
+            .collect();
+
+        RtcSave::footer(&words)
+    }
+
+    fn load_footer(&mut self, footer: &[u8]) {
+        let words = self.rtc_save.load(footer, CLOCK_REGISTERS.into());
+
+        for (register, digit) in (0..CLOCK_REGISTERS).zip(words) {
+            self.clock.write(register, digit as u8);
+        }
+    }
+}
+
+impl Mapper for Tama5 {
+    fn read_rom(&self, location: usize) -> u8 {
+        match location {
+            0x0000..=0x3fff => self.rom.value_in_bank(0, location),
+            _ => {
+                let bank = self.rom_bank & (self.rom.banks.len().next_power_of_two() - 1);
+                self.rom.value_in_bank(bank, location - 0x4000)
+            }
+        }
+    }
+
+    // Nothing is mapped to the usual registers
+    fn write_rom(&mut self, _location: usize, _value: u8) {}
+
+    fn read_ram(&self, location: usize) -> u8 {
+        if location & 1 != 0 {
+            return 0xff;
+        }
+
+        match self.register {
+            STATUS_REGISTER => READY,
+            RESULT_LOW_REGISTER => UNUSED_BITS | (self.result & 0x0f),
+            RESULT_HIGH_REGISTER => UNUSED_BITS | (self.result >> 4),
+            _ => 0xff,
+        }
+    }
+
+    fn write_ram(&mut self, location: usize, value: u8) {
+        let value = value & 0x0f;
+
+        if location & 1 != 0 {
+            self.register = value;
+            return;
+        }
+
+        match self.register {
+            ROM_BANK_LOW_REGISTER => self.rom_bank = (self.rom_bank & 0x10) | usize::from(value),
+            ROM_BANK_HIGH_REGISTER => {
+                self.rom_bank = (self.rom_bank & 0x0f) | (usize::from(value & 0b0000_0001) << 4)
+            }
+            DATA_LOW_REGISTER => self.data = (self.data & 0xf0) | value,
+            DATA_HIGH_REGISTER => self.data = (self.data & 0x0f) | (value << 4),
+            COMMAND_REGISTER => self.command = value,
+            ADDRESS_LOW_REGISTER => self.run_command(value),
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        self.cycles += cycles;
+
+        while self.cycles >= CLOCK_MHZ {
+            self.cycles -= CLOCK_MHZ;
+            self.clock.tick_second();
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        let mut data = self.ram.to_vec();
+        data.extend(self.footer());
+        data
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        let ram = data.len().min(RAM_SIZE);
+        self.ram[..ram].copy_from_slice(&data[..ram]);
+
+        if data.len() == RAM_SIZE + FOOTER_SIZE {
+            self.load_footer(&data[RAM_SIZE..]);
+        }
+    }
+
+    fn sync_clock(&mut self, now: SystemTime) {
+        if let Some(seconds) = self.rtc_save.elapsed(now) {
+            self.clock.advance(seconds);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::cpu::CLOCK_MHZ;
+    use crate::mapper::tama5::{Clock, Tama5};
+    use crate::mapper::{numbered_rom, Mapper};
+
+    fn get_tama5() -> Tama5 {
+        Tama5::new(numbered_rom(32))
+    }
+
+    fn set(mbc: &mut Tama5, register: u8, value: u8) {
+        mbc.write_ram(0x0001, register);
+        mbc.write_ram(0x0000, value);
+    }
+
+    fn get(mbc: &mut Tama5, register: u8) -> u8 {
+        mbc.write_ram(0x0001, register);
+        mbc.read_ram(0x0000)
+    }
+
+    /// Runs a command, returning the result
+    fn command(mbc: &mut Tama5, command: u8, address: u8, data: u8) -> u8 {
+        set(mbc, 0x4, data & 0x0f);
+        set(mbc, 0x5, data >> 4);
+        set(mbc, 0x6, command << 1 | address >> 4);
+        set(mbc, 0x7, address & 0x0f);
+
+        (get(mbc, 0xd) & 0x0f) << 4 | get(mbc, 0xc) & 0x0f
+    }
+
+    #[test]
+    fn reports_ready_and_switches_rom_banks() {
+        let mut mbc = get_tama5();
+
+        assert_eq!(get(&mut mbc, 0xa), 0xf1);
+
+        set(&mut mbc, 0x0, 0x3);
+        set(&mut mbc, 0x1, 0x1);
+        assert_eq!(mbc.read_rom(0x4000), 0x13);
+    }
+
+    #[test]
+    fn ram_is_written_and_read_through_commands() {
+        let mut mbc = get_tama5();
+
+        command(&mut mbc, 0, 0x1f, 0xa5);
+
+        assert_eq!(command(&mut mbc, 1, 0x1f, 0x00), 0xa5);
+        assert_eq!(mbc.ram[0x1f], 0xa5);
+    }
+
+    #[test]
+    fn clock_digits_are_bcd() {
+        let mut mbc = get_tama5();
+
+        // 23:59:59 on the 28th of February
+        for (register, digit) in [
+            (0, 9),
+            (1, 5),
+            (2, 9),
+            (3, 5),
+            (4, 3),
+            (5, 2),
+            (7, 8),
+            (8, 2),
+            (9, 2),
+        ]
+        .iter()
+        {
+            command(&mut mbc, 2, *register, *digit);
+        }
+        mbc.clock.year = 1;
+        mbc.tick(CLOCK_MHZ);
+
+        assert_eq!(command(&mut mbc, 3, 0x7, 0), 1);
+        assert_eq!(command(&mut mbc, 3, 0x9, 0), 3);
+        assert_eq!(command(&mut mbc, 3, 0x5, 0), 0);
+    }
+
+    #[test]
+    fn clock_is_saved_and_catches_up() {
+        let mut mbc = get_tama5();
+        mbc.ram[0] = 0x42;
+        mbc.clock = Clock {
+            hours: 23,
+            ..Clock::default()
+        };
+
+        let mut restored = get_tama5();
+        restored.load_save_data(&mbc.save_data());
+        let saved_at = UNIX_EPOCH + Duration::from_secs(restored.rtc_save.saved_at().unwrap());
+        restored.sync_clock(saved_at + Duration::from_secs(2 * 60 * 60));
+
+        assert_eq!(restored.ram[0], 0x42);
+        assert_eq!(
+            restored.clock,
+            Clock {
+                hours: 1,
+                day: 2,
+                day_of_week: 1,
+                ..Clock::default()
+            }
+        );
+    }
+}
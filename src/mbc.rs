@@ -1,98 +1,237 @@
+use std::convert::TryFrom;
+use std::time::SystemTime;
+
+use crate::bus::Bus;
+use crate::camera::CameraImage;
 use crate::cartridge::Cartridge;
-use crate::utils::u8s_to_u16;
-use crate::banked_memory::BankedMemory;
+use crate::cartridge_header::ColorGameboySupport;
+use crate::error::EmulatorError;
+use crate::infrared::InfraredLink;
+use crate::interrupts::{Interrupt, INTERRUPT_FLAG_LOCATION};
+use crate::joypad::{Button, Joypad, JOYPAD_LOCATION};
+use crate::mapper::{self, Mapper, RumbleListener, ToneListener};
+use crate::timer::{Timer, DIVIDER_LOCATION, TIMER_CONTROL_LOCATION};
 
 pub const RAM_ENABLE_VALUE: u8 = 0xa;
+pub const DMA_LOCATION: usize = 0xff46;
+pub const SPEED_SWITCH_LOCATION: usize = 0xff4d;
+
+// Bytes copied into the sprite attribute table by an OAM DMA transfer
+const DMA_LENGTH: usize = 0xa0;
 
 pub struct MBC {
-    banking_mode: u8, // Only need 2 bits
-    ram_enabled: bool,
-    ram: BankedMemory,
-    rom: BankedMemory,
+    mapper: Box<dyn Mapper>,
     video_ram: Vec<u8>,
     work_ram: Vec<u8>,
     sprite_attribute_table: Vec<u8>,
     io_registers: Vec<u8>,
     high_ram: Vec<u8>,
     interrupt_enable_register: u8,
+    timer: Timer,
+    dma_source: usize,
+    // Next byte an OAM DMA transfer will copy, if one is running
+    dma_index: Option<usize>,
+    joypad: Joypad,
+    // KEY1 only exists when running a CGB cartridge
+    color: bool,
+    speed_switch_armed: bool,
+    double_speed: bool,
+    stopped: bool,
 }
 
-impl From<Cartridge> for MBC {
-    fn from(cartridge: Cartridge) -> Self {
-        MBC {
-            banking_mode: 0,
-            ram_enabled: false,
-            ram: BankedMemory::of_size(cartridge.header.ram_size),
-            rom: BankedMemory::from(cartridge),
+impl TryFrom<Cartridge> for MBC {
+    type Error = EmulatorError;
+
+    fn try_from(cartridge: Cartridge) -> Result<Self, Self::Error> {
+        let color = !matches!(
+            cartridge.header.color_gameboy_support,
+            ColorGameboySupport::NoSupport
+        );
+
+        Ok(MBC {
+            mapper: mapper::from_cartridge(cartridge)?,
             video_ram: vec![0x0000; 0xa000 - 0x8000],
             work_ram: vec![0x0000; 0xe000 - 0xc000],
             sprite_attribute_table: vec![0x0000; 0xfea0 - 0xfe00],
             io_registers: vec![0x0000; 0xff80 - 0xff00],
             high_ram: vec![0x0000; 0xffff - 0xff80],
             interrupt_enable_register: 0x0000,
-        }
+            timer: Timer::default(),
+            dma_source: 0,
+            dma_index: None,
+            joypad: Joypad::default(),
+            color,
+            speed_switch_armed: false,
+            double_speed: false,
+            stopped: false,
+        })
     }
 }
 
-impl MBC {
-    pub fn get_next_u8(&self, from_location: usize) -> u8 {
-        self.read(from_location + 1)
-    }
-
-    pub fn get_next_u16(&self, from_location: usize) -> u16 {
-        u8s_to_u16(self.read(from_location + 2), self.read(from_location + 1))
-    }
-
-    pub fn write(&mut self, location: usize, value: u8) {
+impl Bus for MBC {
+    fn write(&mut self, location: usize, value: u8) {
         // println!(
         //     "Writing value ({:#06x}) to location ({:#06x})",
         //     value, location
         // );
         match location {
-            // Ram is enabled when the lowest 4 bits written to this range
-            // are equal to 0x00a0
-            0x0000..=0x1fff => self.ram_enabled = (value & 0b0000_1111) == RAM_ENABLE_VALUE,
-
-            // The selected bank number is indicated by the lowest 5 bits
-            // TODO: Mask bank number based on full size of rom
-            0x2000..=0x3fff => self.rom.active_bank = usize::from(value & 0b0001_1111),
-
-            // Ram bank is set to the lowest 2 bits
-            // TODO: Write code for "large" MBC1M carts which handle this differently
-            0x4000..=0x5fff => self.ram.active_bank = usize::from(value & 0b0000_0011),
-
-            // Banking Mode is set to the lowest 2 bits
-            // TODO: Handle banking mode in other operations
-            0x6000..=0x7fff => self.banking_mode = value & 0b0000_0011,
-
+            0x0000..=0x7fff => self.mapper.write_rom(location, value),
             0x8000..=0x9fff => self.video_ram[location - 0x8000] = value,
-            0xa000..=0xbfff => self.ram.set_at(location - 0xa000, value),
+            0xa000..=0xbfff => self.mapper.write_ram(location - 0xa000, value),
             0xc000..=0xdfff => self.work_ram[location - 0xc000] = value,
+            0xe000..=0xfdff => self.work_ram[location - 0xe000] = value,
+            // The DMA transfer has the sprite attribute table to itself
+            0xfe00..=0xfe9f if self.dma_index.is_some() => {}
             0xfe00..=0xfe9f => self.sprite_attribute_table[location - 0xfe00] = value,
-            0xff00..=0xff7f => self.io_registers[location - 0xff00] = value,
+            JOYPAD_LOCATION => self.joypad.write(value),
+            DIVIDER_LOCATION..=TIMER_CONTROL_LOCATION => self.timer.write(location, value),
+            DMA_LOCATION => {
+                self.io_registers[location - 0xff00] = value;
+                self.start_dma(value);
+            }
+            SPEED_SWITCH_LOCATION if self.color => {
+                self.speed_switch_armed = value & 0b0000_0001 != 0
+            }
+            0xff01..=0xff7f => self.io_registers[location - 0xff00] = value,
             0xff80..=0xfffe => self.high_ram[location - 0xff80] = value,
             0xffff => self.interrupt_enable_register = value,
 
-            _ => {
-                println!("Cannot write to memory location {}", location);
-            }
+            // Writes to the prohibited area, or past the end of the address
+            // space, go nowhere
+            _ => {}
         }
     }
 
-    pub fn read(&self, location: usize) -> u8 {
+    fn read(&self, location: usize) -> u8 {
         match location {
-            0x0000..=0x3fff => self.rom.value_in_bank(0, location),
-            0x4000..=0x7fff => self.rom.value_at(location - 0x4000),
+            0x0000..=0x7fff => self.mapper.read_rom(location),
             0x8000..=0x9fff => self.video_ram[location - 0x8000],
-            0xa000..=0xbfff => self.ram.value_at(location - 0xa000),
+            0xa000..=0xbfff => self.mapper.read_ram(location - 0xa000),
             0xc000..=0xdfff => self.work_ram[location - 0xc000],
             0xe000..=0xfdff => self.work_ram[location - 0xe000],
+            0xfe00..=0xfe9f if self.dma_index.is_some() => 0xff,
             0xfe00..=0xfe9f => self.sprite_attribute_table[location - 0xfe00],
-            0xfea0..=0xfeff => panic!("Ram Banks between 0xfea0 and 0xfeff are prohibited"),
-            0xff00..=0xff7f => self.io_registers[location - 0xff00],
+            // The prohibited area reads as 0x00 on DMG, unless OAM is busy
+            0xfea0..=0xfeff if self.dma_index.is_some() => 0xff,
+            0xfea0..=0xfeff => 0x00,
+            // Only the low 5 bits of IF exist, the rest always read as set
+            INTERRUPT_FLAG_LOCATION => self.io_registers[location - 0xff00] | 0b1110_0000,
+            JOYPAD_LOCATION => self.joypad.read(),
+            DIVIDER_LOCATION..=TIMER_CONTROL_LOCATION => self.timer.read(location),
+            SPEED_SWITCH_LOCATION if self.color => {
+                ((self.double_speed as u8) << 7) | 0b0111_1110 | self.speed_switch_armed as u8
+            }
+            0xff01..=0xff7f => self.io_registers[location - 0xff00],
             0xff80..=0xfffe => self.high_ram[location - 0xff80],
             0xffff => self.interrupt_enable_register,
-            _ => panic!("Cannot read from location {:#06x}", location),
+            // Nothing drives the bus past the end of the address space
+            _ => 0xff,
+        }
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        if self.timer.tick(cycles) {
+            self.request_interrupt(Interrupt::TIMER);
+        }
+
+        for _ in 0..cycles / 4 {
+            self.step_dma();
+        }
+
+        // Cartridge clocks run off their own crystal, unaffected by the CPU
+        // running at double speed
+        let cartridge_cycles = if self.double_speed {
+            cycles / 2
+        } else {
+            cycles
+        };
+        self.mapper.tick(cartridge_cycles);
+    }
+
+    fn switch_speed(&mut self) -> bool {
+        if !self.speed_switch_armed {
+            return false;
+        }
+
+        self.speed_switch_armed = false;
+        self.double_speed = !self.double_speed;
+        true
+    }
+
+    fn set_stopped(&mut self, stopped: bool) {
+        self.stopped = stopped;
+    }
+}
+
+impl MBC {
+    pub fn press(&mut self, button: Button) {
+        if self.joypad.press(button) {
+            self.request_interrupt(Interrupt::JOYPAD);
+        }
+    }
+
+    pub fn release(&mut self, button: Button) {
+        self.joypad.release(button);
+    }
+
+    /// The LCD shows nothing while STOP has the machine in low power mode
+    pub fn lcd_blanked(&self) -> bool {
+        self.stopped
+    }
+
+    pub fn double_speed(&self) -> bool {
+        self.double_speed
+    }
+
+    pub fn save_data(&self) -> Vec<u8> {
+        self.mapper.save_data()
+    }
+
+    pub fn load_save_data(&mut self, data: &[u8]) {
+        self.mapper.load_save_data(data);
+    }
+
+    pub fn sync_clock(&mut self, now: SystemTime) {
+        self.mapper.sync_clock(now);
+    }
+
+    pub fn set_rumble_listener(&mut self, listener: RumbleListener) {
+        self.mapper.set_rumble_listener(listener);
+    }
+
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        self.mapper.set_tilt(x, y);
+    }
+
+    pub fn set_infrared_link(&mut self, link: Box<dyn InfraredLink>) {
+        self.mapper.set_infrared_link(link);
+    }
+
+    pub fn set_tone_listener(&mut self, listener: ToneListener) {
+        self.mapper.set_tone_listener(listener);
+    }
+
+    pub fn set_camera_image(&mut self, image: CameraImage) {
+        self.mapper.set_camera_image(image);
+    }
+
+    fn start_dma(&mut self, page: u8) {
+        // Pages past work RAM read from its echo instead
+        let page = if page >= 0xe0 { page - 0x20 } else { page };
+
+        self.dma_source = usize::from(page) << 8;
+        self.dma_index = Some(0);
+    }
+
+    /// Copies a single byte of a running OAM DMA transfer, one per M-cycle
+    fn step_dma(&mut self) {
+        if let Some(index) = self.dma_index {
+            self.sprite_attribute_table[index] = self.read(self.dma_source + index);
+            self.dma_index = if index + 1 < DMA_LENGTH {
+                Some(index + 1)
+            } else {
+                None
+            };
         }
     }
 
@@ -101,23 +240,39 @@ impl MBC {
     }
 }
 
+#[cfg(test)]
 mod tests {
-    use crate::banked_memory;
+    use crate::banked_memory::BankedMemory;
+    use crate::bus::Bus;
+    use crate::cartridge_header::RAM_BANK_SIZE;
+
+    use crate::interrupts::INTERRUPT_FLAG_LOCATION;
+    use crate::joypad::Joypad;
+    use crate::mapper::mbc1::Mbc1;
+    use crate::timer::Timer;
 
-    use super::{MBC, RAM_ENABLE_VALUE};
+    use super::{DMA_LOCATION, MBC, RAM_ENABLE_VALUE};
 
     fn get_mock_mbc() -> MBC {
         MBC {
-            banking_mode: 0,
-            ram_enabled: false,
-            ram: banked_memory::BankedMemory::of_size(Some(0)),
-            rom: banked_memory::BankedMemory::of_size(Some(0)),
+            mapper: Box::new(Mbc1::new(
+                BankedMemory::new(0, 0, 0),
+                BankedMemory::new(0, RAM_BANK_SIZE, 1),
+            )),
             video_ram: vec![0x0000; 0xa000 - 0x8000],
             work_ram: vec![0x0000; 0xe000 - 0xc000],
             sprite_attribute_table: vec![0x0000; 0xfea0 - 0xfe00],
             io_registers: vec![0x0000; 0xff80 - 0xff00],
             high_ram: vec![0x0000; 0xffff - 0xff80],
             interrupt_enable_register: 0x0000,
+            timer: Timer::default(),
+            dma_source: 0,
+            dma_index: None,
+            joypad: Joypad::default(),
+            color: false,
+            speed_switch_armed: false,
+            double_speed: false,
+            stopped: false,
         }
     }
 
@@ -125,10 +280,61 @@ mod tests {
     fn test_write_ram_enable() {
         let mut value = get_mock_mbc();
 
-        assert!(!value.ram_enabled);
+        value.write(0xa000, 0x42);
+        assert_eq!(value.read(0xa000), 0xff);
 
         value.write(0x0000, RAM_ENABLE_VALUE);
+        value.write(0xa000, 0x42);
+
+        assert_eq!(value.read(0xa000), 0x42);
+    }
+
+    #[test]
+    fn dma_copies_into_sprite_attribute_table() {
+        let mut value = get_mock_mbc();
 
-        assert!(value.ram_enabled);
+        for offset in 0..0xa0 {
+            value.write(0xc100 + offset, offset as u8);
+        }
+
+        value.write(DMA_LOCATION, 0xc1);
+        assert_eq!(value.read(0xfe00), 0xff);
+
+        value.tick(4 * 0x9f);
+        assert_eq!(value.read(0xfe9f), 0xff);
+
+        value.tick(4);
+        assert_eq!(value.read(0xfe00), 0x00);
+        assert_eq!(value.read(0xfe9f), 0x9f);
+    }
+
+    #[test]
+    fn timer_overflow_requests_interrupt() {
+        let mut value = get_mock_mbc();
+
+        value.write(0xff05, 0xff);
+        value.write(0xff07, 0b101);
+        value.tick(20);
+
+        assert_eq!(value.read(INTERRUPT_FLAG_LOCATION) & 0b100, 0b100);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn prohibited_area_reads_zero() {
+        let mut value = get_mock_mbc();
+
+        value.write(0xfea0, 0x42);
+
+        assert_eq!(value.read(0xfea0), 0x00);
+        assert_eq!(value.read(0xfeff), 0x00);
+    }
+
+    #[test]
+    fn echo_ram_mirrors_work_ram() {
+        let mut value = get_mock_mbc();
+
+        value.write(0xe010, 0x42);
+
+        assert_eq!(value.read(0xc010), 0x42);
+    }
+}
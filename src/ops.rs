@@ -1,99 +1,140 @@
+use crate::bus::Bus;
 use crate::cpu_registers::{CombinedRegister, GeneralRegister};
 use crate::flag_register::FlagRegisterValue;
-use crate::mbc::MBC;
 
 pub trait Ops {
     fn nop(&mut self);
 
     fn ccf(&mut self);
-    fn stop(&mut self);
+    fn daa(&mut self);
+    fn stop<B: Bus>(&mut self, bus: &mut B);
+    fn halt<B: Bus>(&mut self, bus: &B);
     fn di(&mut self);
     fn ei(&mut self);
 
-    fn ret(&mut self, mbc: &MBC);
-    fn ret_f(&mut self, mbc: &MBC, flag: FlagRegisterValue, truthy: bool);
-    fn reti(&mut self, mbc: &MBC);
+    fn ret<B: Bus>(&mut self, bus: &mut B);
+    fn ret_f<B: Bus>(&mut self, bus: &mut B, flag: FlagRegisterValue, truthy: bool);
+    fn reti<B: Bus>(&mut self, bus: &mut B);
+    fn rst<B: Bus>(&mut self, bus: &mut B, address: u16);
 
     fn ld_r_r(&mut self, to: GeneralRegister, from: GeneralRegister);
-    fn ld_r_d8(&mut self, mbc: &MBC, register: GeneralRegister);
-    fn ld_rr_d16(&mut self, mbc: &MBC, register: CombinedRegister);
-
-    fn ld_mem_rr_r(&mut self, mbc: &mut MBC, to_address: CombinedRegister, from: GeneralRegister);
-    fn ldi_mem_rr_r(&mut self, mbc: &mut MBC, to_address: CombinedRegister, from: GeneralRegister);
-    fn ldd_mem_rr_r(&mut self, mbc: &mut MBC, to_address: CombinedRegister, from: GeneralRegister);
-
-    fn ld_r_mem_rr(&mut self, mbc: &MBC, to: GeneralRegister, from_address: CombinedRegister);
-    fn ldi_r_mem_rr(&mut self, mbc: &MBC, to: GeneralRegister, from_address: CombinedRegister);
-    fn ldd_r_mem_rr(&mut self, mbc: &MBC, to: GeneralRegister, from_address: CombinedRegister);
-
-    fn ld_mem_rr_d8(&mut self, mbc: &mut MBC, address: CombinedRegister);
-
-    fn ld_mem_a8_a(&mut self, mbc: &mut MBC);
-    fn ld_a_mem_a8(&mut self, mbc: &MBC);
-
-    fn ld_mem_a16_a(&mut self, mbc: &mut MBC);
-    fn ld_a_mem_a16(&mut self, mbc: &MBC);
-
-    fn ld_mem_r_a(&mut self, mbc: &mut MBC, register: GeneralRegister);
-    fn ld_a_mem_r(&mut self, mbc: &MBC, register: GeneralRegister);
-
-    fn ld_sp_d16(&mut self, mbc: &MBC);
-    fn ld_mem_a16_sp(&mut self, mbc: &mut MBC);
+    fn ld_r_d8(&mut self, register: GeneralRegister, value: u8);
+    fn ld_rr_d16(&mut self, register: CombinedRegister, value: u16);
+
+    fn ld_mem_rr_r<B: Bus>(
+        &mut self,
+        bus: &mut B,
+        to_address: CombinedRegister,
+        from: GeneralRegister,
+    );
+    fn ldi_mem_rr_r<B: Bus>(
+        &mut self,
+        bus: &mut B,
+        to_address: CombinedRegister,
+        from: GeneralRegister,
+    );
+    fn ldd_mem_rr_r<B: Bus>(
+        &mut self,
+        bus: &mut B,
+        to_address: CombinedRegister,
+        from: GeneralRegister,
+    );
+
+    fn ld_r_mem_rr<B: Bus>(
+        &mut self,
+        bus: &mut B,
+        to: GeneralRegister,
+        from_address: CombinedRegister,
+    );
+    fn ldi_r_mem_rr<B: Bus>(
+        &mut self,
+        bus: &mut B,
+        to: GeneralRegister,
+        from_address: CombinedRegister,
+    );
+    fn ldd_r_mem_rr<B: Bus>(
+        &mut self,
+        bus: &mut B,
+        to: GeneralRegister,
+        from_address: CombinedRegister,
+    );
+
+    fn ld_mem_rr_d8<B: Bus>(&mut self, bus: &mut B, address: CombinedRegister, value: u8);
+
+    fn ld_mem_a8_a<B: Bus>(&mut self, bus: &mut B, offset: u8);
+    fn ld_a_mem_a8<B: Bus>(&mut self, bus: &mut B, offset: u8);
+
+    fn ld_mem_a16_a<B: Bus>(&mut self, bus: &mut B, location: u16);
+    fn ld_a_mem_a16<B: Bus>(&mut self, bus: &mut B, location: u16);
+
+    fn ld_mem_r_a<B: Bus>(&mut self, bus: &mut B, register: GeneralRegister);
+    fn ld_a_mem_r<B: Bus>(&mut self, bus: &mut B, register: GeneralRegister);
+
+    fn ld_sp_d16(&mut self, value: u16);
+    fn ld_mem_a16_sp<B: Bus>(&mut self, bus: &mut B, location: u16);
     fn ld_sp_rr(&mut self, register: CombinedRegister);
+    fn ld_rr_sp_s8(&mut self, register: CombinedRegister, offset: i8);
 
     fn add_r(&mut self, register: GeneralRegister);
-    fn add_d8(&mut self, mbc: &MBC);
-    fn add_mem_rr(&mut self, mbc: &MBC, register: CombinedRegister);
+    fn add_d8(&mut self, value: u8);
+    fn add_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
 
     fn add_rr(&mut self, register: CombinedRegister);
     fn add_sp(&mut self);
-    // fn add_sp_s8(&mut self, mbc: &MBC);
+    fn add_sp_s8(&mut self, offset: i8);
 
     fn adc_r(&mut self, register: GeneralRegister);
-    fn adc_d8(&mut self, mbc: &MBC);
-    fn adc_mem_rr(&mut self, mbc: &MBC, register: CombinedRegister);
+    fn adc_d8(&mut self, value: u8);
+    fn adc_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
 
     fn sub_r(&mut self, register: GeneralRegister);
-    fn sub_d8(&mut self, mbc: &MBC);
-    fn sub_mem_rr(&mut self, mbc: &MBC, register: CombinedRegister);
+    fn sub_d8(&mut self, value: u8);
+    fn sub_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
 
     fn sbc_r(&mut self, register: GeneralRegister);
-    fn sbc_d8(&mut self, mbc: &MBC);
-    fn sbc_mem_rr(&mut self, mbc: &MBC, register: CombinedRegister);
+    fn sbc_d8(&mut self, value: u8);
+    fn sbc_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
 
     fn and_r(&mut self, register: GeneralRegister);
-    fn and_d8(&mut self, mbc: &MBC);
-    fn and_mem_rr(&mut self, mbc: &MBC, register: CombinedRegister);
+    fn and_d8(&mut self, value: u8);
+    fn and_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
 
     fn xor_r(&mut self, register: GeneralRegister);
-    fn xor_d8(&mut self, mbc: &MBC);
-    fn xor_mem_rr(&mut self, mbc: &MBC, register: CombinedRegister);
+    fn xor_d8(&mut self, value: u8);
+    fn xor_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
 
     fn or_r(&mut self, register: GeneralRegister);
-    fn or_d8(&mut self, mbc: &MBC);
-    fn or_mem_rr(&mut self, mbc: &MBC, register: CombinedRegister);
+    fn or_d8(&mut self, value: u8);
+    fn or_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
 
     fn cp_r(&mut self, register: GeneralRegister);
-    fn cp_d8(&mut self, mbc: &MBC);
-    fn cp_mem_rr(&mut self, mbc: &MBC, register: CombinedRegister);
+    fn cp_d8(&mut self, value: u8);
+    fn cp_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
 
     fn inc_r(&mut self, register: GeneralRegister);
-    fn inc_mem_rr(&mut self, mbc: &mut MBC, register: CombinedRegister);
+    fn inc_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
     fn inc_rr(&mut self, register: CombinedRegister);
     fn inc_sp(&mut self);
 
     fn dec_r(&mut self, register: GeneralRegister);
-    fn dec_mem_rr(&mut self, mbc: &mut MBC, register: CombinedRegister);
+    fn dec_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
     fn dec_rr(&mut self, register: CombinedRegister);
     fn dec_sp(&mut self);
 
-    fn push_rr(&mut self, mbc: &mut MBC, register: CombinedRegister);
-    fn pop_rr(&mut self, mbc: &MBC, register: CombinedRegister);
+    fn push_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
+    fn pop_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
 
-    fn jp(&mut self, mbc: &MBC, flag: Option<FlagRegisterValue>, truthy: bool);
-    fn jr(&mut self, mbc: &MBC, flag: Option<FlagRegisterValue>, truthy: bool);
+    fn jp(&mut self, flag: Option<FlagRegisterValue>, truthy: bool, location: u16);
+    fn jr(&mut self, flag: Option<FlagRegisterValue>, truthy: bool, offset: i8);
+    fn jp_rr(&mut self, register: CombinedRegister);
 
-    fn call_f_a16(&mut self, mbc: &mut MBC, flag: FlagRegisterValue, truthy: bool);
+    fn call_f_a16<B: Bus>(
+        &mut self,
+        bus: &mut B,
+        flag: FlagRegisterValue,
+        truthy: bool,
+        location: u16,
+    );
 
     fn scf(&mut self);
 
@@ -1,21 +1,37 @@
+use crate::bus::Bus;
 use crate::cpu_registers::{CombinedRegister, GeneralRegister};
-use crate::mbc::MBC;
 
 pub trait PrefixOps {
     fn rlc_r(&mut self, register: GeneralRegister);
-    fn rlc_mem_rr(&mut self, mbc: &mut MBC, register: CombinedRegister);
+    fn rlc_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
 
     fn rl_r(&mut self, register: GeneralRegister);
-    fn rl_mem_rr(&mut self, mbc: &mut MBC, register: CombinedRegister);
+    fn rl_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
 
     fn rrc_r(&mut self, register: GeneralRegister);
-    fn rrc_mem_rr(&mut self, mbc: &mut MBC, register: CombinedRegister);
+    fn rrc_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
 
     fn rr_r(&mut self, register: GeneralRegister);
-    fn rr_mem_rr(&mut self, mbc: &mut MBC, register: CombinedRegister);
+    fn rr_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
+
+    fn sla_r(&mut self, register: GeneralRegister);
+    fn sla_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
+
+    fn sra_r(&mut self, register: GeneralRegister);
+    fn sra_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
+
+    fn swap_r(&mut self, register: GeneralRegister);
+    fn swap_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
 
     fn srl_r(&mut self, register: GeneralRegister);
+    fn srl_mem_rr<B: Bus>(&mut self, bus: &mut B, register: CombinedRegister);
 
     fn bit_r(&mut self, bit: u8, register: GeneralRegister);
-    fn bit_mem_rr(&mut self, mbc: &MBC, bit: u8, register: CombinedRegister);
+    fn bit_mem_rr<B: Bus>(&mut self, bus: &mut B, bit: u8, register: CombinedRegister);
+
+    fn res_r(&mut self, bit: u8, register: GeneralRegister);
+    fn res_mem_rr<B: Bus>(&mut self, bus: &mut B, bit: u8, register: CombinedRegister);
+
+    fn set_r(&mut self, bit: u8, register: GeneralRegister);
+    fn set_mem_rr<B: Bus>(&mut self, bus: &mut B, bit: u8, register: CombinedRegister);
 }
@@ -1,7 +1,5 @@
-use std::{
-    fs::File,
-    time::{Duration, Instant},
-};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
 
 #[allow(unused_imports)]
 use glium::{glutin, Surface};
@@ -11,14 +9,16 @@ use glium::{
     uniforms::{EmptyUniforms, Sampler, UniformsStorage},
     Program,
 };
+use glutin::event::{ElementState, VirtualKeyCode};
 use winit::window::WindowBuilder;
 
-use crate::{
-    cartridge::Cartridge,
+use oxide_gb::{
     cpu::{Cpu, CLOCK_MHZ},
+    joypad::Button,
     mbc::MBC,
     pixel::Pixel,
-    video::{Video, Frame, SCREEN_HEIGHT, SCREEN_WIDTH},
+    save_file::SaveFile,
+    video::{Frame, Video, CYCLES_PER_FRAME, SCREEN_HEIGHT, SCREEN_WIDTH},
 };
 
 type PixelColor = (u8, u8, u8, u8);
@@ -28,6 +28,9 @@ const DARK_GREEN: PixelColor = (48, 98, 48, 0);
 const LIGHT_GREEN: PixelColor = (139, 172, 15, 0);
 const LIGHTEST_GREEN: PixelColor = (155, 188, 15, 0);
 
+// How often battery-backed RAM is written out while the game runs
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
 #[derive(Copy, Clone)]
 struct Vertex {
     position: [f32; 2],
@@ -40,7 +43,7 @@ type Uniforms<'a> = UniformsStorage<
     UniformsStorage<'a, [[f32; 4]; 4], EmptyUniforms>,
 >;
 
-const VERTEX_SHADER_140: &'static str = "
+const VERTEX_SHADER_140: &str = "
 #version 140
 
 uniform mat4 matrix;
@@ -56,7 +59,7 @@ void main() {
 }
 ";
 
-const FRAGMENT_SHADER_140: &'static str = "
+const FRAGMENT_SHADER_140: &str = "
 #version 140
 
 uniform sampler2D tex;
@@ -69,9 +72,9 @@ void main() {
 }
 ";
 
-fn build_vertex_buffer(display: &glium::Display) -> glium::VertexBuffer<Vertex> {
-    implement_vertex!(Vertex, position, tex_coords);
+implement_vertex!(Vertex, position, tex_coords);
 
+fn build_vertex_buffer(display: &glium::Display) -> glium::VertexBuffer<Vertex> {
     glium::VertexBuffer::new(
         display,
         &[
@@ -97,7 +100,7 @@ fn build_vertex_buffer(display: &glium::Display) -> glium::VertexBuffer<Vertex>
 }
 
 fn build_index_buffer(display: &glium::Display) -> glium::IndexBuffer<u16> {
-    glium::IndexBuffer::new(display, PrimitiveType::TriangleStrip, &[1 as u16, 2, 0, 3]).unwrap()
+    glium::IndexBuffer::new(display, PrimitiveType::TriangleStrip, &[1_u16, 2, 0, 3]).unwrap()
 }
 
 fn build_window_builder() -> WindowBuilder {
@@ -128,7 +131,7 @@ fn get_uniforms<'a>(screen_texture: &'a glium::texture::Texture2d) -> Uniforms<'
 fn build_display(event_loop: &glutin::event_loop::EventLoop<()>) -> glium::Display {
     let wb = build_window_builder();
     let cb = glutin::ContextBuilder::new();
-    glium::Display::new(wb, cb, &event_loop).unwrap()
+    glium::Display::new(wb, cb, event_loop).unwrap()
 }
 
 fn build_program(display: &glium::Display) -> Program {
@@ -145,15 +148,16 @@ fn build_program(display: &glium::Display) -> Program {
 type FrameColors = Vec<Vec<PixelColor>>;
 
 fn frame_to_colors(frame: Frame) -> FrameColors {
-    fn frame_row_to_colors(row: &Vec<Pixel>) -> Vec<PixelColor> {
+    fn frame_row_to_colors(row: &[Pixel]) -> Vec<PixelColor> {
         row.iter().map(pixel_to_color).collect()
     }
 
-    frame.iter().map(frame_row_to_colors).collect()
+    frame.iter().map(|row| frame_row_to_colors(row)).collect()
 }
 
 fn init_texture(display: &glium::Display) -> glium::texture::Texture2d {
-    glium::texture::texture2d::Texture2d::new(display, frame_to_colors(Video::blank_frame())).unwrap()
+    glium::texture::texture2d::Texture2d::new(display, frame_to_colors(Video::blank_frame()))
+        .unwrap()
 }
 
 fn pixel_to_color(pixel: &Pixel) -> PixelColor {
@@ -165,7 +169,40 @@ fn pixel_to_color(pixel: &Pixel) -> PixelColor {
     }
 }
 
-pub fn render<'a>(input_file: File) {
+fn key_to_button(key: VirtualKeyCode) -> Option<Button> {
+    match key {
+        VirtualKeyCode::Right => Some(Button::Right),
+        VirtualKeyCode::Left => Some(Button::Left),
+        VirtualKeyCode::Up => Some(Button::Up),
+        VirtualKeyCode::Down => Some(Button::Down),
+        VirtualKeyCode::Z => Some(Button::A),
+        VirtualKeyCode::X => Some(Button::B),
+        VirtualKeyCode::Back => Some(Button::Select),
+        VirtualKeyCode::Return => Some(Button::Start),
+        _ => None,
+    }
+}
+
+/// Which way a key tilts carts with an accelerometer
+fn key_to_tilt(key: VirtualKeyCode) -> Option<(f32, f32)> {
+    match key {
+        VirtualKeyCode::L => Some((1.0, 0.0)),
+        VirtualKeyCode::J => Some((-1.0, 0.0)),
+        VirtualKeyCode::K => Some((0.0, 1.0)),
+        VirtualKeyCode::I => Some((0.0, -1.0)),
+        _ => None,
+    }
+}
+
+fn write_save(memory: &MBC, save_file: &mut Option<SaveFile>) {
+    if let Some(save_file) = save_file {
+        if let Err(error) = save_file.write(&memory.save_data()) {
+            eprintln!("Could not write {}: {}", save_file.path().display(), error);
+        }
+    }
+}
+
+pub fn render(mut memory: MBC, mut save_file: Option<SaveFile>) {
     let event_loop = glutin::event_loop::EventLoop::new();
     let display = build_display(&event_loop);
     let vertex_buffer = build_vertex_buffer(&display);
@@ -174,15 +211,39 @@ pub fn render<'a>(input_file: File) {
     let program = build_program(&display);
     let screen_texture = init_texture(&display);
 
-    let cartridge = Cartridge::from(input_file);
-    let mut memory = MBC::from(cartridge);
     let mut cpu = Cpu::default();
+    let mut tilt_keys = HashSet::new();
+    let mut last_save = Instant::now();
 
     // Skip over the Boot Rom
     cpu.program_counter = 0x100;
 
     event_loop.run(move |event, _, control_flow| {
-        cpu.apply_operation(&mut memory);
+        let mut frame_cycles = 0;
+
+        while frame_cycles < CYCLES_PER_FRAME {
+            let cycles = match cpu.step(&mut memory) {
+                Ok(cycles) => cycles,
+                Err(error) => {
+                    // The CPU is locked up, but the rest of the machine
+                    // carries on just like the real thing
+                    eprintln!("{}", error);
+                    4
+                }
+            };
+
+            // In CGB double speed the CPU gets through twice the cycles per frame
+            frame_cycles += if memory.double_speed() {
+                cycles / 2
+            } else {
+                cycles
+            };
+        }
+
+        if last_save.elapsed() >= SAVE_INTERVAL {
+            write_save(&memory, &mut save_file);
+            last_save = Instant::now();
+        }
 
         // Draw
         let pixels = Video::blank_frame()
@@ -219,13 +280,49 @@ pub fn render<'a>(input_file: File) {
         target.finish().unwrap();
 
         match event {
-            glutin::event::Event::WindowEvent { event, .. } => match event {
-                glutin::event::WindowEvent::CloseRequested => {
-                    *control_flow = glutin::event_loop::ControlFlow::Exit;
-                    return;
+            glutin::event::Event::WindowEvent {
+                event: glutin::event::WindowEvent::CloseRequested,
+                ..
+            } => {
+                write_save(&memory, &mut save_file);
+                *control_flow = glutin::event_loop::ControlFlow::Exit;
+                return;
+            }
+            glutin::event::Event::WindowEvent {
+                event:
+                    glutin::event::WindowEvent::KeyboardInput {
+                        input:
+                            glutin::event::KeyboardInput {
+                                virtual_keycode: Some(key),
+                                state,
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                if let Some(button) = key_to_button(key) {
+                    match state {
+                        ElementState::Pressed => memory.press(button),
+                        ElementState::Released => memory.release(button),
+                    }
                 }
-                _ => return,
-            },
+
+                if key_to_tilt(key).is_some() {
+                    match state {
+                        ElementState::Pressed => tilt_keys.insert(key),
+                        ElementState::Released => tilt_keys.remove(&key),
+                    };
+
+                    let (x, y) = tilt_keys
+                        .iter()
+                        .filter_map(|key| key_to_tilt(*key))
+                        .fold((0.0, 0.0), |(x, y), (dx, dy)| (x + dx, y + dy));
+                    memory.set_tilt(x, y);
+                }
+                return;
+            }
+            glutin::event::Event::WindowEvent { .. } => return,
             glutin::event::Event::NewEvents(cause) => match cause {
                 glutin::event::StartCause::ResumeTimeReached { .. } => (),
                 glutin::event::StartCause::Init => (),
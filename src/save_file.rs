@@ -0,0 +1,110 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+pub const SAVE_EXTENSION: &str = "sav";
+
+// Written first and renamed over the save, so it's never half written
+const TEMPORARY_EXTENSION: &str = "sav.tmp";
+
+/// Battery-backed cartridge memory, kept in a `.sav` file between sessions
+pub struct SaveFile {
+    path: PathBuf,
+    // What's on disk, so memory that hasn't changed isn't written again
+    written: Vec<u8>,
+}
+
+impl SaveFile {
+    /// Named after the ROM, next to it unless there's a `directory` for saves
+    pub fn for_rom(rom: &Path, directory: Option<&Path>) -> Self {
+        let path = rom.with_extension(SAVE_EXTENSION);
+        let path = match (directory, path.file_name()) {
+            (Some(directory), Some(name)) => directory.join(name),
+            _ => path,
+        };
+
+        SaveFile {
+            path,
+            written: Vec::new(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// What was saved last time, if the game has ever been saved
+    pub fn load(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(&self.path) {
+            Ok(data) => {
+                self.written = data.clone();
+                Ok(Some(data))
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Saves `data` if it's changed since the last write. A crash part way
+    /// through leaves the previous save as it was.
+    pub fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        if data == self.written.as_slice() {
+            return Ok(());
+        }
+
+        if let Some(directory) = self.path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+
+        let temporary = self.path.with_extension(TEMPORARY_EXTENSION);
+        let mut file = File::create(&temporary)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(&temporary, &self.path)?;
+
+        self.written = data.to_vec();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::process;
+
+    use crate::save_file::SaveFile;
+
+    fn scratch_directory(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!("oxide-gb-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    #[test]
+    fn named_after_the_rom() {
+        let beside = SaveFile::for_rom(Path::new("roms/game.gb"), None);
+        let elsewhere = SaveFile::for_rom(Path::new("roms/game.gbc"), Some(Path::new("saves")));
+
+        assert_eq!(beside.path(), Path::new("roms/game.sav"));
+        assert_eq!(elsewhere.path(), Path::new("saves/game.sav"));
+    }
+
+    #[test]
+    fn round_trips_through_disk() {
+        let directory = scratch_directory("round-trip");
+        let rom = directory.join("game.gb");
+
+        let mut save_file = SaveFile::for_rom(&rom, Some(&directory));
+        assert_eq!(save_file.load().unwrap(), None);
+
+        save_file.write(&[0x12, 0x34]).unwrap();
+
+        let mut reopened = SaveFile::for_rom(&rom, Some(&directory));
+        assert_eq!(reopened.load().unwrap(), Some(vec![0x12, 0x34]));
+        assert!(!directory.join("game.sav.tmp").exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
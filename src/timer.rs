@@ -0,0 +1,169 @@
+pub const DIVIDER_LOCATION: usize = 0xff04;
+pub const TIMER_COUNTER_LOCATION: usize = 0xff05;
+pub const TIMER_MODULO_LOCATION: usize = 0xff06;
+pub const TIMER_CONTROL_LOCATION: usize = 0xff07;
+
+const TIMER_ENABLE: u8 = 0b0000_0100;
+
+// T-cycles between TIMA overflowing and it being reloaded from TMA
+const RELOAD_DELAY: u32 = 4;
+
+/// DIV, TIMA, TMA and TAC. TIMA counts falling edges of one bit of the
+/// internal 16 bit divider, which is also why writing DIV can bump TIMA.
+#[derive(Default)]
+pub struct Timer {
+    // DIV is the top 8 bits of this
+    divider: u16,
+    counter: u8,
+    modulo: u8,
+    control: u8,
+    // Counts down after TIMA overflows, TIMA reads as 0 until it runs out
+    reload_in: u32,
+}
+
+impl Timer {
+    pub fn read(&self, location: usize) -> u8 {
+        match location {
+            DIVIDER_LOCATION => (self.divider >> 8) as u8,
+            TIMER_COUNTER_LOCATION => self.counter,
+            TIMER_MODULO_LOCATION => self.modulo,
+            // Only the low 3 bits of TAC exist
+            TIMER_CONTROL_LOCATION => self.control | 0b1111_1000,
+            _ => unreachable!("{:#06x} is not a timer register", location),
+        }
+    }
+
+    pub fn write(&mut self, location: usize, value: u8) {
+        match location {
+            DIVIDER_LOCATION => self.set_divider(0),
+            TIMER_COUNTER_LOCATION => {
+                // Writing during the reload delay cancels the reload
+                self.counter = value;
+                self.reload_in = 0;
+            }
+            TIMER_MODULO_LOCATION => self.modulo = value,
+            TIMER_CONTROL_LOCATION => {
+                let was_high = self.selected_bit();
+                self.control = value & 0b0000_0111;
+
+                if was_high && !self.selected_bit() {
+                    self.increment_counter();
+                }
+            }
+            _ => unreachable!("{:#06x} is not a timer register", location),
+        }
+    }
+
+    /// Advances the timer by a number of T-cycles. Returns true if the timer
+    /// interrupt should be requested.
+    pub fn tick(&mut self, cycles: u32) -> bool {
+        let mut interrupt = false;
+
+        // The divider only ever counts in whole M-cycles
+        for _ in 0..cycles / 4 {
+            if self.reload_in > 0 {
+                self.reload_in -= 4;
+
+                if self.reload_in == 0 {
+                    self.counter = self.modulo;
+                    interrupt = true;
+                }
+            }
+
+            self.set_divider(self.divider.wrapping_add(4));
+        }
+
+        interrupt
+    }
+
+    fn set_divider(&mut self, value: u16) {
+        let was_high = self.selected_bit();
+        self.divider = value;
+
+        if was_high && !self.selected_bit() {
+            self.increment_counter();
+        }
+    }
+
+    /// The divider bit TIMA is watching, ANDed with the enable bit
+    fn selected_bit(&self) -> bool {
+        let bit = match self.control & 0b0000_0011 {
+            0b00 => 9,
+            0b01 => 3,
+            0b10 => 5,
+            _ => 7,
+        };
+
+        self.control & TIMER_ENABLE != 0 && self.divider & (1 << bit) != 0
+    }
+
+    fn increment_counter(&mut self) {
+        let (result, overflowed) = self.counter.overflowing_add(1);
+        self.counter = result;
+
+        if overflowed {
+            self.reload_in = RELOAD_DELAY;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::timer::{
+        Timer, DIVIDER_LOCATION, TIMER_CONTROL_LOCATION, TIMER_COUNTER_LOCATION,
+        TIMER_MODULO_LOCATION,
+    };
+
+    #[test]
+    fn divider_counts_every_256_cycles() {
+        let mut timer = Timer::default();
+
+        timer.tick(252);
+        assert_eq!(timer.read(DIVIDER_LOCATION), 0);
+
+        timer.tick(4);
+        assert_eq!(timer.read(DIVIDER_LOCATION), 1);
+
+        timer.write(DIVIDER_LOCATION, 0x42);
+        assert_eq!(timer.read(DIVIDER_LOCATION), 0);
+    }
+
+    #[test]
+    fn counter_uses_selected_frequency() {
+        let mut timer = Timer::default();
+
+        // Enabled, every 16 T-cycles
+        timer.write(TIMER_CONTROL_LOCATION, 0b101);
+        timer.tick(64);
+
+        assert_eq!(timer.read(TIMER_COUNTER_LOCATION), 4);
+    }
+
+    #[test]
+    fn overflow_reloads_after_a_delay() {
+        let mut timer = Timer::default();
+
+        timer.write(TIMER_MODULO_LOCATION, 0xf0);
+        timer.write(TIMER_COUNTER_LOCATION, 0xff);
+        timer.write(TIMER_CONTROL_LOCATION, 0b101);
+
+        assert!(!timer.tick(16));
+        assert_eq!(timer.read(TIMER_COUNTER_LOCATION), 0x00);
+
+        assert!(timer.tick(4));
+        assert_eq!(timer.read(TIMER_COUNTER_LOCATION), 0xf0);
+    }
+
+    #[test]
+    fn resetting_divider_can_bump_counter() {
+        let mut timer = Timer::default();
+
+        timer.write(TIMER_CONTROL_LOCATION, 0b101);
+        timer.tick(8);
+        assert_eq!(timer.read(TIMER_COUNTER_LOCATION), 0);
+
+        // Bit 3 of the divider is set, so clearing it is a falling edge
+        timer.write(DIVIDER_LOCATION, 0);
+        assert_eq!(timer.read(TIMER_COUNTER_LOCATION), 1);
+    }
+}
@@ -59,9 +59,18 @@ pub trait Carryable<T: num::Integer> {
     fn sub_should_carry(&self, b: T) -> bool;
 }
 
+/// The flag checks for ADC and SBC, which also take the carry flag as an
+/// extra operand
+pub trait CarryableWith<T: num::Integer> {
+    fn add_should_half_carry_with(&self, b: T, carry: T) -> bool;
+    fn add_should_carry_with(&self, b: T, carry: T) -> bool;
+    fn sub_should_half_carry_with(&self, b: T, carry: T) -> bool;
+    fn sub_should_carry_with(&self, b: T, carry: T) -> bool;
+}
+
 impl Carryable<u8> for u8 {
     fn add_should_half_carry(&self, b: u8) -> bool {
-        ((self & 0xf) + (b & 0xf) & 0x10) == 0x10
+        (((self & 0xf) + (b & 0xf)) & 0x10) == 0x10
     }
 
     fn add_should_carry(&self, b: u8) -> bool {
@@ -85,6 +94,24 @@ impl Carryable<u8> for u8 {
     }
 }
 
+impl CarryableWith<u8> for u8 {
+    fn add_should_half_carry_with(&self, b: u8, carry: u8) -> bool {
+        (self & 0xf) + (b & 0xf) + carry > 0xf
+    }
+
+    fn add_should_carry_with(&self, b: u8, carry: u8) -> bool {
+        *self as u16 + b as u16 + carry as u16 > 0xff
+    }
+
+    fn sub_should_half_carry_with(&self, b: u8, carry: u8) -> bool {
+        (self & 0xf) < (b & 0xf) + carry
+    }
+
+    fn sub_should_carry_with(&self, b: u8, carry: u8) -> bool {
+        (*self as u16) < b as u16 + carry as u16
+    }
+}
+
 impl Carryable<u16> for u16 {
     fn add_should_half_carry(&self, b: u16) -> bool {
         (self & 0xfff) + (b & 0xfff) > 0xfff
@@ -113,16 +140,30 @@ impl Carryable<u16> for u16 {
 
 #[cfg(test)]
 mod tests {
-    use crate::utils::{BitWise, Carryable};
+    use crate::utils::{BitWise, Carryable, CarryableWith};
 
     #[test]
     fn add_half_carry_check_10plus12() {
-        assert!((10 as u8).add_should_half_carry(12));
+        assert!(10_u8.add_should_half_carry(12));
     }
 
     #[test]
     fn add_half_carry_check_5plus4() {
-        assert!(!(5 as u8).add_should_half_carry(4));
+        assert!(!5_u8.add_should_half_carry(4));
+    }
+
+    #[test]
+    fn add_with_carry_counts_carry_separately() {
+        assert!(0x00_u8.add_should_half_carry_with(0x0f, 1));
+        assert!(0x01_u8.add_should_carry_with(0xff, 1));
+        assert!(!0x00_u8.add_should_carry_with(0xff, 0));
+    }
+
+    #[test]
+    fn sub_with_carry_counts_carry_separately() {
+        assert!(0x10_u8.sub_should_half_carry_with(0x0f, 1));
+        assert!(0x00_u8.sub_should_carry_with(0xff, 1));
+        assert!(!0xff_u8.sub_should_carry_with(0xfe, 1));
     }
 
     #[test]
@@ -137,26 +178,26 @@ mod tests {
 
     #[test]
     fn bitwise_is_bit_set_3c() {
-        assert!(!(0x3c as u8).is_bit_set(1 << 0));
-        assert!(!(0x3c as u8).is_bit_set(1 << 1));
-        assert!((0x3c as u8).is_bit_set(1 << 2));
-        assert!((0x3c as u8).is_bit_set(1 << 3));
-        assert!((0x3c as u8).is_bit_set(1 << 4));
-        assert!((0x3c as u8).is_bit_set(1 << 5));
-        assert!(!(0x3c as u8).is_bit_set(1 << 6));
-        assert!(!(0x3c as u8).is_bit_set(1 << 7));
+        assert!(!0x3c_u8.is_bit_set(1 << 0));
+        assert!(!0x3c_u8.is_bit_set(1 << 1));
+        assert!(0x3c_u8.is_bit_set(1 << 2));
+        assert!(0x3c_u8.is_bit_set(1 << 3));
+        assert!(0x3c_u8.is_bit_set(1 << 4));
+        assert!(0x3c_u8.is_bit_set(1 << 5));
+        assert!(!0x3c_u8.is_bit_set(1 << 6));
+        assert!(!0x3c_u8.is_bit_set(1 << 7));
     }
 
     #[test]
     fn bitwise_is_bit_set_7e() {
-        assert!(!(0x7e as u8).is_bit_set(1 << 0));
-        assert!((0x7e as u8).is_bit_set(1 << 1));
-        assert!((0x7e as u8).is_bit_set(1 << 2));
-        assert!((0x7e as u8).is_bit_set(1 << 3));
-        assert!((0x7e as u8).is_bit_set(1 << 4));
-        assert!((0x7e as u8).is_bit_set(1 << 5));
-        assert!((0x7e as u8).is_bit_set(1 << 6));
-        assert!(!(0x7e as u8).is_bit_set(1 << 7));
+        assert!(!0x7e_u8.is_bit_set(1 << 0));
+        assert!(0x7e_u8.is_bit_set(1 << 1));
+        assert!(0x7e_u8.is_bit_set(1 << 2));
+        assert!(0x7e_u8.is_bit_set(1 << 3));
+        assert!(0x7e_u8.is_bit_set(1 << 4));
+        assert!(0x7e_u8.is_bit_set(1 << 5));
+        assert!(0x7e_u8.is_bit_set(1 << 6));
+        assert!(!0x7e_u8.is_bit_set(1 << 7));
     }
 
     #[test]
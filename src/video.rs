@@ -9,12 +9,15 @@ pub type Frame = Vec<Vec<Pixel>>;
 pub const SCREEN_HEIGHT: u8 = 144;
 pub const SCREEN_WIDTH: u8 = 160;
 
+// 154 scanlines of 456 T-cycles each
+pub const CYCLES_PER_FRAME: u32 = 70224;
+
 // The maximum rendered background size. Larger than the height and width because
 // there is overdraw.
 pub const BACKGROUND_SIZE: usize = 256;
 
 pub struct VideoBackground {
-    pixels: [[Pixel; BACKGROUND_SIZE]; BACKGROUND_SIZE],
+    pub pixels: [[Pixel; BACKGROUND_SIZE]; BACKGROUND_SIZE],
 }
 
 impl Default for VideoBackground {
@@ -36,17 +39,13 @@ impl Video {
         vec![row; SCREEN_HEIGHT.into()]
     }
 
-    fn build_tile_map(ram: MBC, tile_index: u8) -> (Vec<Tile>, Vec<Tile>) {
-        (vec![], vec![])
-    }
-
     pub fn collect_tiles(&mut self, lcdc: LCDC, ram: &MBC) {
         for i in 0..256 {
-            self.tiles.set(i,Tile::from_ram(lcdc, &ram, i));
+            self.tiles.set(i,Tile::from_ram(lcdc, ram, i));
         }
     }
 
-    fn compose_tiles(&self, tiles: Vec<Tile>) -> Frame {
+    pub fn compose_tiles(&self, tiles: Vec<Tile>) -> Frame {
         let mut result = Video::blank_frame();
         let mut i: usize = 0;
 
@@ -0,0 +1,184 @@
+//! Runs the mooneye test suite's MBC1 ROMs against the whole machine.
+//!
+//! Each ROM does its checks and then hits `LD B, B` as a breakpoint, with
+//! the Fibonacci numbers 3, 5, 8, 13, 21 and 34 in B through L if everything
+//! passed. The ROMs aren't checked in, so point `MOONEYE_TESTS` at a build of
+//! the suite, or drop one into `tests/mooneye`. Without them nothing is run
+//! and MBC1 goes unvalidated, which is only allowed when `MOONEYE_TESTS`
+//! isn't set. Any extra arguments filter the ROMs by name:
+//!
+//!     MOONEYE_TESTS=~/mooneye-test-suite/build cargo test --test mooneye -- ram
+
+use std::convert::TryFrom;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use oxide_gb::bus::Bus;
+use oxide_gb::cartridge::Cartridge;
+use oxide_gb::cpu::{Cpu, CLOCK_MHZ};
+use oxide_gb::cpu_registers::GeneralRegister;
+use oxide_gb::mbc::MBC;
+
+const TESTS_VARIABLE: &str = "MOONEYE_TESTS";
+const DEFAULT_DIRECTORY: &str = "tests/mooneye";
+const MBC1_DIRECTORY: &str = "emulator-only/mbc1";
+
+// The opcode for LD B, B, which the suite uses as a breakpoint
+const BREAKPOINT: u8 = 0x40;
+
+// Every test finishes well within this much emulated time
+const TIMEOUT_CYCLES: u64 = CLOCK_MHZ as u64 * 30;
+
+// The ROMs MBC1 is expected to pass. Any of these failing or going missing
+// is a regression, while other ROMs are reported without failing the run.
+const EXPECTED_PASSES: [&str; 13] = [
+    "bits_bank1",
+    "bits_bank2",
+    "bits_mode",
+    "bits_ramg",
+    "multicart_rom_8Mb",
+    "ram_256kb",
+    "ram_64kb",
+    "rom_16Mb",
+    "rom_1Mb",
+    "rom_2Mb",
+    "rom_4Mb",
+    "rom_512kb",
+    "rom_8Mb",
+];
+
+const PASSED: [(GeneralRegister, u8); 6] = [
+    (GeneralRegister::B, 3),
+    (GeneralRegister::C, 5),
+    (GeneralRegister::D, 8),
+    (GeneralRegister::E, 13),
+    (GeneralRegister::H, 21),
+    (GeneralRegister::L, 34),
+];
+
+fn run_rom(path: &Path) -> Result<(), String> {
+    let data = fs::read(path).map_err(|error| error.to_string())?;
+    let cartridge = Cartridge::try_from(data).map_err(|error| error.to_string())?;
+    let mut memory = MBC::try_from(cartridge).map_err(|error| error.to_string())?;
+    let mut cpu = Cpu::default();
+
+    // Where the boot ROM leaves things on a DMG
+    cpu.program_counter = 0x100;
+    cpu.stack_pointer = 0xfffe;
+    cpu.registers.set(GeneralRegister::A, 0x01);
+
+    let mut cycles = 0;
+
+    while cycles < TIMEOUT_CYCLES {
+        if memory.read(cpu.program_counter) == BREAKPOINT {
+            return match PASSED
+                .iter()
+                .find(|(register, value)| cpu.registers.get(*register) != *value)
+            {
+                None => Ok(()),
+                Some((register, _)) => Err(format!(
+                    "{:?} was {:#04x} at the breakpoint",
+                    register,
+                    cpu.registers.get(*register)
+                )),
+            };
+        }
+
+        cycles += u64::from(cpu.step(&mut memory).map_err(|error| error.to_string())?);
+    }
+
+    Err("timed out".to_string())
+}
+
+fn test_roms(directory: &Path, filters: &[String]) -> Vec<PathBuf> {
+    let mut roms: Vec<PathBuf> = fs::read_dir(directory.join(MBC1_DIRECTORY))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "gb"))
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            filters.is_empty() || filters.iter().any(|filter| name.contains(filter.as_str()))
+        })
+        .collect();
+
+    roms.sort();
+    roms
+}
+
+fn rom_name(path: &Path) -> String {
+    path.file_stem().unwrap().to_string_lossy().into_owned()
+}
+
+fn main() {
+    let configured = env::var_os(TESTS_VARIABLE);
+    let directory = configured
+        .clone()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_DIRECTORY));
+
+    // Cargo passes its own flags through to the harness, skip those
+    let filters: Vec<String> = env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .collect();
+    let roms = test_roms(&directory, &filters);
+
+    if roms.is_empty() {
+        println!(
+            "MBC1 NOT VALIDATED: no mooneye ROMs found in {}",
+            directory.join(MBC1_DIRECTORY).display()
+        );
+
+        if configured.is_some() {
+            process::exit(1);
+        }
+
+        println!("Set {} to a build of the suite to run them", TESTS_VARIABLE);
+        return;
+    }
+
+    let mut passed = 0;
+    let mut regressions = Vec::new();
+
+    for path in &roms {
+        let name = rom_name(path);
+        let expected = EXPECTED_PASSES.contains(&name.as_str());
+        let result = run_rom(path);
+
+        if result.is_ok() {
+            passed += 1;
+        }
+
+        match result {
+            Ok(()) if expected => println!("{:<24} ok", name),
+            Ok(()) => println!("{:<24} ok, but not in EXPECTED_PASSES", name),
+            Err(reason) if expected => {
+                println!("{:<24} FAILED, {}", name, reason);
+                regressions.push(name.clone());
+            }
+            Err(reason) => println!("{:<24} failed as expected, {}", name, reason),
+        }
+    }
+
+    // A partial copy of the suite shouldn't pass for a full one
+    if filters.is_empty() {
+        let names: Vec<String> = roms.iter().map(|path| rom_name(path)).collect();
+
+        for expected in EXPECTED_PASSES.iter() {
+            if !names.iter().any(|name| name == expected) {
+                println!("{:<24} MISSING", expected);
+                regressions.push(expected.to_string());
+            }
+        }
+    }
+
+    println!("\n{} of {} ROMs passed", passed, roms.len());
+
+    if !regressions.is_empty() {
+        println!("Regressions: {}", regressions.join(", "));
+        process::exit(1);
+    }
+}
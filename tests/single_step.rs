@@ -0,0 +1,294 @@
+//! Runs the community single-step test vectors for the SM83 against `Cpu`.
+//!
+//! Each JSON file holds a few hundred cases for one opcode. A case gives the
+//! registers and the bytes of memory it touches before and after a single
+//! instruction, along with one entry per M-cycle it took. The vectors aren't
+//! checked in, so point `SM83_TESTS` at a directory of them, or drop them
+//! into `tests/sm83`. Any extra arguments filter the files by name:
+//!
+//!     SM83_TESTS=~/sm83/v1 cargo test --test single_step -- cb
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use serde_json::Value;
+
+use oxide_gb::bus::{Bus, FlatMemory};
+use oxide_gb::cpu::Cpu;
+use oxide_gb::cpu_registers::GeneralRegister;
+use oxide_gb::instruction::Instruction;
+
+const TESTS_VARIABLE: &str = "SM83_TESTS";
+const DEFAULT_DIRECTORY: &str = "tests/sm83";
+
+const INTERRUPT_ENABLE_LOCATION: usize = 0xffff;
+
+const REGISTERS: [(&str, GeneralRegister); 8] = [
+    ("a", GeneralRegister::A),
+    ("f", GeneralRegister::F),
+    ("b", GeneralRegister::B),
+    ("c", GeneralRegister::C),
+    ("d", GeneralRegister::D),
+    ("e", GeneralRegister::E),
+    ("h", GeneralRegister::H),
+    ("l", GeneralRegister::L),
+];
+
+struct Report {
+    name: String,
+    instruction: String,
+    passed: usize,
+    failures: Vec<String>,
+}
+
+fn field(state: &Value, name: &str) -> Option<u64> {
+    state.get(name).and_then(Value::as_u64)
+}
+
+fn ram(state: &Value) -> impl Iterator<Item = (usize, u8)> + '_ {
+    state["ram"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| Some((entry[0].as_u64()? as usize, entry[1].as_u64()? as u8)))
+}
+
+/// The opcode bytes a file covers, going by names like `3e.json` or
+/// `cb 11.json`
+fn opcode_from_name(path: &Path) -> Vec<u8> {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(|stem| {
+            stem.split_whitespace()
+                .map_while(|byte| u8::from_str_radix(byte, 16).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Some releases of the vectors have `pc` one past the opcode, since the
+/// SM83 fetches the next opcode during the last M-cycle of the current one.
+/// Returns how far behind `pc` the opcode actually is.
+fn prefetch_offset(initial: &Value, opcode: &[u8]) -> usize {
+    let pc = field(initial, "pc").unwrap_or(0) as usize;
+    let matches_at = |location: usize| {
+        !opcode.is_empty()
+            && opcode.iter().enumerate().all(|(index, byte)| {
+                ram(initial).any(|entry| entry == ((location + index) & 0xffff, *byte))
+            })
+    };
+
+    if !matches_at(pc) && matches_at(pc.wrapping_sub(1) & 0xffff) {
+        1
+    } else {
+        0
+    }
+}
+
+fn load(state: &Value, offset: usize) -> (Cpu, FlatMemory) {
+    let mut cpu = Cpu::default();
+    let mut memory = FlatMemory::default();
+
+    for (name, register) in REGISTERS.iter() {
+        cpu.registers
+            .set(*register, field(state, name).unwrap_or(0) as u8);
+    }
+
+    cpu.program_counter = (field(state, "pc").unwrap_or(0) as usize).wrapping_sub(offset) & 0xffff;
+    cpu.stack_pointer = field(state, "sp").unwrap_or(0) as u16;
+    cpu.interrupts_enabled = field(state, "ime").unwrap_or(0) != 0;
+
+    for (location, value) in ram(state) {
+        memory.write(location, value);
+    }
+
+    if let Some(enabled) = field(state, "ie") {
+        memory.write(INTERRUPT_ENABLE_LOCATION, enabled as u8);
+    }
+
+    (cpu, memory)
+}
+
+/// Describes the first difference between the machine and the expected
+/// final state
+fn compare(
+    expected: &Value,
+    offset: usize,
+    cpu: &Cpu,
+    memory: &FlatMemory,
+    cycles: usize,
+) -> Result<(), String> {
+    let mismatch = |what: &str, expected: u64, actual: u64| {
+        if expected == actual {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} was {:#x}, expected {:#x}",
+                what, actual, expected
+            ))
+        }
+    };
+
+    for (name, register) in REGISTERS.iter() {
+        if let Some(value) = field(expected, name) {
+            mismatch(name, value, cpu.registers.get(*register).into())?;
+        }
+    }
+
+    if let Some(pc) = field(expected, "pc") {
+        let pc = (pc as usize).wrapping_sub(offset) & 0xffff;
+        mismatch("pc", pc as u64, cpu.program_counter as u64)?;
+    }
+
+    if let Some(sp) = field(expected, "sp") {
+        mismatch("sp", sp, cpu.stack_pointer.into())?;
+    }
+
+    if let Some(ime) = field(expected, "ime") {
+        mismatch("ime", ime, cpu.interrupts_enabled as u64)?;
+    }
+
+    if let Some(enabled) = field(expected, "ie") {
+        mismatch("ie", enabled, memory.read(INTERRUPT_ENABLE_LOCATION).into())?;
+    }
+
+    for (location, value) in ram(expected) {
+        mismatch(
+            &format!("({:#06x})", location),
+            value.into(),
+            memory.read(location).into(),
+        )?;
+    }
+
+    mismatch("cycles", cycles as u64 * 4, memory.cycles)
+}
+
+fn run_case(case: &Value, opcode: &[u8]) -> Result<(), String> {
+    let initial = &case["initial"];
+    let offset = prefetch_offset(initial, opcode);
+    let (mut cpu, mut memory) = load(initial, offset);
+
+    cpu.step(&mut memory).map_err(|error| error.to_string())?;
+
+    let cycles = case["cycles"].as_array().map_or(0, Vec::len);
+    compare(&case["final"], offset, &cpu, &memory, cycles)
+}
+
+fn run_file(path: &Path) -> Result<Report, String> {
+    let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let json: Value = serde_json::from_str(&text).map_err(|error| error.to_string())?;
+    let cases = json.as_array().ok_or("expected an array of cases")?;
+    let opcode = opcode_from_name(path);
+
+    let instruction = cases
+        .first()
+        .map(|case| {
+            let offset = prefetch_offset(&case["initial"], &opcode);
+            let (cpu, memory) = load(&case["initial"], offset);
+            Instruction::decode(&memory, cpu.program_counter)
+                .operation
+                .mnemonic()
+                .to_string()
+        })
+        .unwrap_or_default();
+
+    let mut report = Report {
+        name: path.file_stem().unwrap().to_string_lossy().into_owned(),
+        instruction,
+        passed: 0,
+        failures: Vec::new(),
+    };
+
+    for case in cases {
+        match run_case(case, &opcode) {
+            Ok(()) => report.passed += 1,
+            Err(reason) => {
+                let name = case["name"].as_str().unwrap_or("?");
+                report.failures.push(format!("{}: {}", name, reason));
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+fn test_files(directory: &Path, filters: &[String]) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(directory)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            filters.is_empty() || filters.iter().any(|filter| name.contains(filter.as_str()))
+        })
+        .collect();
+
+    files.sort();
+    files
+}
+
+fn main() {
+    let directory = env::var_os(TESTS_VARIABLE)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_DIRECTORY));
+
+    // Cargo passes its own flags through to the harness, skip those
+    let filters: Vec<String> = env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .collect();
+    let files = test_files(&directory, &filters);
+
+    if files.is_empty() {
+        println!(
+            "No single-step tests found in {}, set {} to run them",
+            directory.display(),
+            TESTS_VARIABLE
+        );
+        return;
+    }
+
+    let mut failed_opcodes = 0;
+
+    for path in &files {
+        match run_file(path) {
+            Ok(report) if report.failures.is_empty() => {
+                println!(
+                    "{:<6} {:<5} ok ({})",
+                    report.name, report.instruction, report.passed
+                );
+            }
+            Ok(report) => {
+                failed_opcodes += 1;
+                println!(
+                    "{:<6} {:<5} FAILED ({} of {}), first was {}",
+                    report.name,
+                    report.instruction,
+                    report.failures.len(),
+                    report.passed + report.failures.len(),
+                    report.failures[0]
+                );
+            }
+            Err(error) => {
+                failed_opcodes += 1;
+                println!("{:<6} could not be loaded: {}", path.display(), error);
+            }
+        }
+    }
+
+    println!(
+        "\n{} of {} opcodes passed",
+        files.len() - failed_opcodes,
+        files.len()
+    );
+
+    if failed_opcodes > 0 {
+        process::exit(1);
+    }
+}